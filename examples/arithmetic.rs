@@ -4,12 +4,14 @@ use zelkova::{self, tsr, Tensor, TensorOrder};
 fn main() {
     let instance = zelkova::init();
 
-    let t1 = tsr![[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12],];
-    let t2 = tsr![[2, 4, 6, 8], [10, 12, 14, 16], [18, 20, 22, 24]];
+    let t1: Tensor<i32, i32> = tsr![[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12],];
+    let t2: Tensor<i32, i32> = tsr![[2, 4, 6, 8], [10, 12, 14, 16], [18, 20, 22, 24]];
+    let t3: Tensor<i32, i32> = tsr![[1, 1, 1, 1], [1, 1, 1, 1], [1, 1, 1, 1]];
 
-    let t3 = t1 + t2;
+    // Chained elementwise operations are fused into a single kernel once resolved.
+    let t4 = (&t1 + &t2) * t3 - t1;
 
-    // instance.resolve(t3); for actually running the chained operations.
+    instance.resolve(&t4).unwrap();
 
-    println!("{}", t3.order);
+    println!("{}", t4.order);
 }
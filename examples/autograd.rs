@@ -0,0 +1,17 @@
+use zelkova::{self, Tensor, TensorOrder};
+
+fn main() {
+    let instance = zelkova::init();
+
+    let x: Tensor<f32, f32> =
+        Tensor::from_array([1.0, -2.0, 3.0, -4.0], TensorOrder::new(vec![2, 2])).requires_grad();
+    let w: Tensor<f32, f32> =
+        Tensor::from_array([0.5, 0.5, 0.5, 0.5], TensorOrder::new(vec![2, 2])).requires_grad();
+
+    // Gradients are recorded as pending operations too, only run once read.
+    let y = x.matmul(&w).relu().sum(0).sum(1);
+    y.backward().unwrap();
+
+    println!("{:?}", x.grad().unwrap().to_vec(&instance).unwrap());
+    println!("{:?}", w.grad().unwrap().to_vec(&instance).unwrap());
+}
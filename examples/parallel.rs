@@ -0,0 +1,54 @@
+use zelkova::{
+    loss::{self, Reduction},
+    optim::{Optimizer, Sgd, SgdOpts},
+    Error, Instance, InstanceGroup, Tensor, TensorOrder,
+};
+
+fn main() -> Result<(), Error> {
+    // Every adapter when there are several, otherwise two devices on the only one, which is
+    // enough to exercise the group on a machine with nothing but a software adapter. Drivers
+    // that can't hand out a second device report it as an error.
+    let group = match InstanceGroup::init() {
+        Ok(group) if group.len() > 1 => group,
+        _ => InstanceGroup::from_instances(vec![Instance::init()?, Instance::init()?])?,
+    };
+    println!("training over {} instances", group.len());
+
+    let inputs: Vec<f32> = (0..8).map(|i| i as f32).collect();
+    let targets: Vec<f32> = inputs.iter().map(|x| 3.0 * x).collect();
+
+    let weights: Vec<Tensor<f32, f32>> = group
+        .replicate(&[0.0], TensorOrder::new(vec![1, 1]))
+        .into_iter()
+        .map(Tensor::requires_grad)
+        .collect();
+    let mut optims: Vec<Sgd> = weights
+        .iter()
+        .map(|w| Sgd::new(&[w], SgdOpts::default()))
+        .collect();
+
+    let x = group.shard(&inputs, TensorOrder::new(vec![8, 1]))?;
+    let y = group.shard(&targets, TensorOrder::new(vec![8, 1]))?;
+
+    for _ in 0..20 {
+        for ((w, x), y) in weights.iter().zip(x.iter()).zip(y.iter()) {
+            loss::mse(&x.matmul(w), y, Reduction::Mean).backward()?;
+        }
+
+        group.all_reduce(&weights)?;
+
+        for (optim, instance) in optims.iter_mut().zip(group.instances()) {
+            optim.step(instance)?;
+            optim.zero_grad();
+        }
+    }
+
+    let replicas: Vec<Vec<f32>> = weights
+        .iter()
+        .zip(group.instances())
+        .map(|(w, instance)| w.to_vec(instance))
+        .collect::<Result<_, _>>()?;
+    println!("{:?}", replicas);
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::single_component_path_imports)]
+
 use {pollster, std::borrow::Cow, wgpu};
 
 async fn coroutine() {
@@ -16,7 +18,7 @@ async fn coroutine() {
 
     // `request_device` instantiates the feature specific connection to the GPU, defining some parameters,
     //  `features` being the available features.
-    let (device, queue) = adapter
+    let (device, _queue) = adapter
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
@@ -35,8 +37,8 @@ async fn coroutine() {
         )),
     });
 
-    for i in 1..100000000 {
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+    for _ in 1..100000000 {
+        let _pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
             label: None,
             layout: None,
             module: &compiled_shader,
@@ -0,0 +1,346 @@
+// GGUF, the llama.cpp model format: a header of typed key-value metadata and tensor infos,
+// then the tensor data, aligned. Dimensions are listed innermost first.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    api::{nn::Param, Tensor, TensorOrder},
+    core::{
+        ops::{DimensionalType, Quant, Shader},
+        Bundle, Error, Operand, Operation,
+    },
+};
+
+const MAGIC: &[u8; 4] = b"GGUF";
+
+/// Data alignment unless the metadata sets `general.alignment`.
+const ALIGNMENT: u64 = 32;
+
+/// Strings and arrays longer than this are taken for corruption rather than allocated.
+const MAX_LEN: u64 = 1 << 30;
+
+/// Arrays nested deeper than this are rejected, as reading recurses on them.
+const MAX_DEPTH: usize = 16;
+
+/// Metadata value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Any unsigned or non-negative integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::U8(value) => Some(value as u64),
+            Value::U16(value) => Some(value as u64),
+            Value::U32(value) => Some(value as u64),
+            Value::U64(value) => Some(value),
+            Value::I8(value) => u64::try_from(value).ok(),
+            Value::I16(value) => u64::try_from(value).ok(),
+            Value::I32(value) => u64::try_from(value).ok(),
+            Value::I64(value) => u64::try_from(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Element types of ggml, by their id in the file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GgmlType {
+    F32,
+    F16,
+    BF16,
+    F64,
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+    Q8_1,
+    /// K-quants, i-quants and integer types, listed but not loadable.
+    Other(u32),
+}
+
+impl GgmlType {
+    fn from_id(id: u32) -> Self {
+        match id {
+            0 => GgmlType::F32,
+            1 => GgmlType::F16,
+            2 => GgmlType::Q4_0,
+            3 => GgmlType::Q4_1,
+            6 => GgmlType::Q5_0,
+            7 => GgmlType::Q5_1,
+            8 => GgmlType::Q8_0,
+            9 => GgmlType::Q8_1,
+            28 => GgmlType::F64,
+            30 => GgmlType::BF16,
+            id => GgmlType::Other(id),
+        }
+    }
+
+    /// Packed layout dequantized on the device, if supported.
+    fn quant(&self) -> Option<Quant> {
+        let quant = match self {
+            GgmlType::F16 => Quant::F16,
+            GgmlType::BF16 => Quant::BF16,
+            GgmlType::Q4_0 => Quant::Q4_0,
+            GgmlType::Q4_1 => Quant::Q4_1,
+            GgmlType::Q5_0 => Quant::Q5_0,
+            GgmlType::Q5_1 => Quant::Q5_1,
+            GgmlType::Q8_0 => Quant::Q8_0,
+            _ => return None,
+        };
+
+        Some(quant)
+    }
+}
+
+/// Tensor stored in a GGUF file, as described by its header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorInfo {
+    /// Outermost axis first, as `TensorOrder` has it.
+    pub shape: Vec<u32>,
+    pub ty: GgmlType,
+    /// Offset within the data section.
+    pub offset: u64,
+}
+
+/// Open GGUF file. Metadata and tensor infos are read up front; tensors are read on request,
+/// as packed on disk, and unpacked to f32 by a kernel once resolved.
+pub struct Gguf {
+    file: BufReader<File>,
+    pub version: u32,
+    pub metadata: Vec<(String, Value)>,
+    tensors: Vec<(String, TensorInfo)>,
+    /// Start of the data section within the file.
+    data: u64,
+}
+
+impl Gguf {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Format("not a GGUF file".into()));
+        }
+
+        let version = read_u32(&mut file)?;
+        if !(2..=3).contains(&version) {
+            return Err(Error::Format(format!(
+                "GGUF version {} is unsupported",
+                version
+            )));
+        }
+
+        let count = read_u64(&mut file)?;
+        let entries = read_u64(&mut file)?;
+
+        let mut metadata = Vec::new();
+        for _ in 0..entries {
+            let key = read_string(&mut file)?;
+            let ty = read_u32(&mut file)?;
+            metadata.push((key, read_value(&mut file, ty, 0)?));
+        }
+
+        let mut tensors = Vec::new();
+        for _ in 0..count {
+            let name = read_string(&mut file)?;
+            let rank = read_u32(&mut file)?;
+            let mut shape = (0..rank)
+                .map(|_| {
+                    let dim = read_u64(&mut file)?;
+                    u32::try_from(dim).map_err(|_| Error::Format("dimension out of range".into()))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            shape.reverse();
+
+            let ty = GgmlType::from_id(read_u32(&mut file)?);
+            let offset = read_u64(&mut file)?;
+            tensors.push((name, TensorInfo { shape, ty, offset }));
+        }
+
+        let alignment = metadata
+            .iter()
+            .find_map(|(key, value)| (key == "general.alignment").then(|| value.as_u64()))
+            .flatten()
+            .unwrap_or(ALIGNMENT);
+        let data = file.stream_position()?.next_multiple_of(alignment.max(1));
+
+        Ok(Self {
+            file,
+            version,
+            metadata,
+            tensors,
+            data,
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.metadata
+            .iter()
+            .find_map(|(name, value)| (name == key).then_some(value))
+    }
+
+    /// Names of every tensor, in header order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tensors.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn info(&self, name: &str) -> Option<&TensorInfo> {
+        self.tensors
+            .iter()
+            .find_map(|(key, info)| (key == name).then_some(info))
+    }
+
+    /// Read the tensor called `name` as f32. Packed types are uploaded as is and unpacked on
+    /// the device when first resolved.
+    pub fn tensor(&mut self, name: &str) -> Result<Param, Error> {
+        let info = self
+            .info(name)
+            .ok_or_else(|| Error::Format(format!("no tensor named {}", name)))?
+            .clone();
+        let malformed = || Error::Format(format!("tensor {} runs past the end of file", name));
+        let count = info
+            .shape
+            .iter()
+            .try_fold(1u64, |count, dim| count.checked_mul(*dim as u64))
+            .ok_or_else(malformed)?;
+
+        let (quant, size) = match info.ty {
+            GgmlType::F32 => (None, count.checked_mul(4).ok_or_else(malformed)?),
+            ty => {
+                let quant = ty.quant().ok_or_else(|| {
+                    Error::Format(format!("tensor {} is stored as unsupported {:?}", name, ty))
+                })?;
+                if !count.is_multiple_of(quant.block() as u64) {
+                    return Err(Error::Format(format!(
+                        "tensor {} doesn't fill whole {:?} blocks",
+                        name, ty
+                    )));
+                }
+                (
+                    Some(quant),
+                    count / quant.block() as u64 * quant.bytes() as u64,
+                )
+            }
+        };
+
+        let length = self.file.get_ref().metadata()?.len();
+        let start = self.data.checked_add(info.offset).ok_or_else(malformed)?;
+        if start.checked_add(size).is_none_or(|end| end > length) {
+            return Err(malformed());
+        }
+        let order = TensorOrder::new(info.shape.clone());
+
+        let mut contents = vec![0; size as usize];
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.read_exact(&mut contents)?;
+
+        let Some(quant) = quant else {
+            return Ok(Tensor::from_bytes(contents, order));
+        };
+
+        let words = size.div_ceil(4) as u32;
+        let packed: Arc<dyn Operand> =
+            Arc::new(Bundle::<u32>::bind_init(vec![words], contents).map_err(|_| Error::Wgpu)?);
+        let op = Operation::<f32>::from_operands(
+            vec![packed],
+            Shader::Dimensional(DimensionalType::Dequantize(quant)),
+        );
+        let unpacked: Arc<dyn Operand> =
+            Arc::new(Bundle::<f32>::bind_future(order.pull(), op).map_err(|_| Error::Wgpu)?);
+
+        Tensor::from_operand(unpacked).ok_or(Error::Toolkit)
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, Error> {
+    let mut word = [0; 4];
+    reader.read_exact(&mut word)?;
+    Ok(u32::from_le_bytes(word))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, Error> {
+    let mut word = [0; 8];
+    reader.read_exact(&mut word)?;
+    Ok(u64::from_le_bytes(word))
+}
+
+fn read_len(reader: &mut impl Read) -> Result<usize, Error> {
+    match read_u64(reader)? {
+        len if len <= MAX_LEN => Ok(len as usize),
+        _ => Err(Error::Format("GGUF length out of range".into())),
+    }
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String, Error> {
+    let mut bytes = vec![0; read_len(reader)?];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| Error::Format("GGUF string is not utf-8".into()))
+}
+
+/// Value of type `ty`, within `depth` arrays.
+fn read_value(reader: &mut impl Read, ty: u32, depth: usize) -> Result<Value, Error> {
+    fn bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], Error> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    let value = match ty {
+        0 => Value::U8(u8::from_le_bytes(bytes(reader)?)),
+        1 => Value::I8(i8::from_le_bytes(bytes(reader)?)),
+        2 => Value::U16(u16::from_le_bytes(bytes(reader)?)),
+        3 => Value::I16(i16::from_le_bytes(bytes(reader)?)),
+        4 => Value::U32(u32::from_le_bytes(bytes(reader)?)),
+        5 => Value::I32(i32::from_le_bytes(bytes(reader)?)),
+        6 => Value::F32(f32::from_le_bytes(bytes(reader)?)),
+        7 => Value::Bool(bytes::<1>(reader)?[0] != 0),
+        8 => Value::String(read_string(reader)?),
+        9 if depth == MAX_DEPTH => {
+            return Err(Error::Format("GGUF arrays nested too deeply".into()))
+        }
+        9 => {
+            let ty = read_u32(reader)?;
+            let len = read_len(reader)?;
+            Value::Array(
+                (0..len)
+                    .map(|_| read_value(reader, ty, depth + 1))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        10 => Value::U64(u64::from_le_bytes(bytes(reader)?)),
+        11 => Value::I64(i64::from_le_bytes(bytes(reader)?)),
+        12 => Value::F64(f64::from_le_bytes(bytes(reader)?)),
+        ty => return Err(Error::Format(format!("unknown GGUF value type {}", ty))),
+    };
+
+    Ok(value)
+}
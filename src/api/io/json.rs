@@ -0,0 +1,289 @@
+// Just enough JSON for file headers, which are small and machine written.
+
+use crate::core::Error;
+
+/// Arrays and objects nested deeper than this are rejected, as parsing recurses on them.
+const MAX_DEPTH: usize = 64;
+
+/// Largest integer every smaller one of which an f64 holds exactly, 2^53.
+const MAX_EXACT: f64 = 9007199254740992.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they appear.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            at: 0,
+            depth: 0,
+        };
+
+        let value = parser.value()?;
+        parser.skip();
+        match parser.at == parser.text.len() {
+            true => Ok(value),
+            false => Err(parser.error("trailing characters")),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find_map(|(name, value)| (name == key).then_some(value)),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Non-negative integer, as sizes and offsets are. Numbers past 2^53 aren't exact as f64, so
+    /// they're refused rather than rounded.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(value) if (0.0..=MAX_EXACT).contains(value) && value.fract() == 0.0 => {
+                Some(*value as u64)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Quote and escape `value` as a JSON string.
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct Parser<'t> {
+    text: &'t [u8],
+    at: usize,
+    /// Arrays and objects the parser is currently within.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &str) -> Error {
+        Error::Format(format!("json header: {} at byte {}", reason, self.at))
+    }
+
+    fn skip(&mut self) {
+        while self.text.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), Error> {
+        self.skip();
+        match self.text.get(self.at) {
+            Some(found) if *found == byte => {
+                self.at += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("expected '{}'", byte as char))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, Error> {
+        match self.text[self.at..].starts_with(word.as_bytes()) {
+            true => {
+                self.at += word.len();
+                Ok(value)
+            }
+            false => Err(self.error("unexpected token")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, Error> {
+        self.skip();
+        match self.text.get(self.at) {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.keyword("true", Json::Bool(true)),
+            Some(b'f') => self.keyword("false", Json::Bool(false)),
+            Some(b'n') => self.keyword("null", Json::Null),
+            Some(_) => self.number(),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json, Error>) -> Result<Json, Error> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Json, Error> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+
+        self.skip();
+        if self.text.get(self.at) == Some(&b'}') {
+            self.at += 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip();
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+
+            self.skip();
+            match self.text.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b'}') => {
+                    self.at += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, Error> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+
+        self.skip();
+        if self.text.get(self.at) == Some(&b']') {
+            self.at += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip();
+            match self.text.get(self.at) {
+                Some(b',') => self.at += 1,
+                Some(b']') => {
+                    self.at += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect(b'"')?;
+        let mut value = Vec::new();
+
+        loop {
+            let Some(&byte) = self.text.get(self.at) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.at += 1;
+
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escaped) = self.text.get(self.at) else {
+                        return Err(self.error("unterminated escape"));
+                    };
+                    self.at += 1;
+
+                    match escaped {
+                        b'n' => value.push(b'\n'),
+                        b't' => value.push(b'\t'),
+                        b'r' => value.push(b'\r'),
+                        b'b' => value.push(0x08),
+                        b'f' => value.push(0x0c),
+                        b'u' => {
+                            let c = self.unicode()?;
+                            value.extend(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => value.push(other),
+                    }
+                }
+                byte => value.push(byte),
+            }
+        }
+
+        String::from_utf8(value).map_err(|_| self.error("invalid utf-8"))
+    }
+
+    /// Code point of a `\u` escape, combining surrogate pairs.
+    fn unicode(&mut self) -> Result<char, Error> {
+        let high = self.hex()?;
+        let code =
+            match (0xd800..0xdc00).contains(&high) && self.text[self.at..].starts_with(b"\\u") {
+                true => {
+                    self.at += 2;
+                    let low = self.hex()?;
+                    0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                }
+                false => high,
+            };
+
+        char::from_u32(code).ok_or_else(|| self.error("invalid code point"))
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let unit = self
+            .text
+            .get(self.at..self.at + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.at += 4;
+
+        Ok(unit)
+    }
+
+    fn number(&mut self) -> Result<Json, Error> {
+        let start = self.at;
+        while self
+            .text
+            .get(self.at)
+            .is_some_and(|byte| matches!(byte, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+        {
+            self.at += 1;
+        }
+
+        std::str::from_utf8(&self.text[start..self.at])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .map(Json::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+}
@@ -0,0 +1,104 @@
+// Read-only file mappings, so tensors get uploaded straight from the page cache.
+
+use std::{
+    fs::File,
+    ops::{Deref, Range},
+    sync::Arc,
+};
+
+#[cfg(not(unix))]
+use std::io::Read;
+
+/// Whole file mapped read-only. Platforms without `mmap` read the file into memory instead.
+pub(crate) struct Mmap {
+    #[cfg(unix)]
+    map: *mut libc::c_void,
+    #[cfg(unix)]
+    len: usize,
+    #[cfg(not(unix))]
+    contents: Vec<u8>,
+}
+
+// The mapping is private and never written to, so it can be read from any thread.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    #[cfg(unix)]
+    pub fn map(file: &File) -> std::io::Result<Self> {
+        use std::{io, os::unix::io::AsRawFd, ptr};
+
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        // Empty mappings are invalid; there is nothing to read from an empty file anyway.
+        if len == 0 {
+            return Ok(Self {
+                map: ptr::null_mut(),
+                len,
+            });
+        }
+
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self { map, len })
+    }
+
+    #[cfg(not(unix))]
+    pub fn map(mut file: &File) -> std::io::Result<Self> {
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        Ok(Self { contents })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[cfg(unix)]
+    fn deref(&self) -> &Self::Target {
+        match self.len {
+            0 => &[],
+            len => unsafe { std::slice::from_raw_parts(self.map as *const u8, len) },
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn deref(&self) -> &Self::Target {
+        &self.contents
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe { libc::munmap(self.map, self.len) };
+        }
+    }
+}
+
+/// Byte range of a mapped file, handed to tensors as their contents without a copy.
+pub(crate) struct Region {
+    pub map: Arc<Mmap>,
+    pub range: Range<usize>,
+}
+
+impl AsRef<[u8]> for Region {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        &self.map[self.range.clone()]
+    }
+}
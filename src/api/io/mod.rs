@@ -0,0 +1,84 @@
+// Reading and writing tensors in the file formats weights usually ship in.
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use crate::core::Error;
+
+pub mod gguf;
+pub(crate) mod json;
+pub(crate) mod mmap;
+pub mod npy;
+pub mod onnx;
+pub mod safetensors;
+
+pub use gguf::Gguf;
+pub use npy::Npz;
+pub use onnx::Onnx;
+pub use safetensors::Safetensors;
+
+/// Element types as files describe them.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Dtype {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    F16,
+    BF16,
+    U32,
+    I32,
+    F32,
+    U64,
+    I64,
+    F64,
+    F8E4M3,
+    F8E5M2,
+}
+
+impl Dtype {
+    /// Size of a single element, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Dtype::Bool | Dtype::U8 | Dtype::I8 | Dtype::F8E4M3 | Dtype::F8E5M2 => 1,
+            Dtype::U16 | Dtype::I16 | Dtype::F16 | Dtype::BF16 => 2,
+            Dtype::U32 | Dtype::I32 | Dtype::F32 => 4,
+            Dtype::U64 | Dtype::I64 | Dtype::F64 => 8,
+        }
+    }
+}
+
+/// Save to `path` through `write`, under a temporary name renamed into place once complete, so
+/// a failed save leaves whatever was there before untouched.
+pub(crate) fn replace(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| Error::Io(format!("{} is not a file path", path.display())))?
+        .to_os_string();
+    name.push(".partial");
+    let partial = path.with_file_name(name);
+
+    let written = File::create(&partial)
+        .map_err(Error::from)
+        .and_then(|file| {
+            let mut file = BufWriter::new(file);
+            write(&mut file)?;
+            file.into_inner()
+                .map_err(|error| error.into_error())?
+                .sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(fs::rename(&partial, path)?));
+
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
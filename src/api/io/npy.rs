@@ -0,0 +1,561 @@
+// NumPy arrays: `.npy` files, a magic string and a Python dict literal describing the dtype,
+// memory order and shape, followed by the raw data; `.npz` archives, zip files of `.npy`s.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    api::{Tensor, TensorOrder},
+    core::{Bundle, Error, Instance, Operand},
+    types::{Component, Packet, SupportedPacket},
+};
+
+use super::Dtype;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// Headers, magic and length included, are padded to a multiple of this.
+const ALIGNMENT: usize = 64;
+
+impl Dtype {
+    /// Little-endian NumPy type string.
+    fn descr(&self) -> Option<&'static str> {
+        let descr = match self {
+            Dtype::Bool => "|b1",
+            Dtype::U8 => "|u1",
+            Dtype::I8 => "|i1",
+            Dtype::U16 => "<u2",
+            Dtype::I16 => "<i2",
+            Dtype::F16 => "<f2",
+            Dtype::U32 => "<u4",
+            Dtype::I32 => "<i4",
+            Dtype::F32 => "<f4",
+            Dtype::U64 => "<u8",
+            Dtype::I64 => "<i8",
+            Dtype::F64 => "<f8",
+            Dtype::BF16 | Dtype::F8E4M3 | Dtype::F8E5M2 => return None,
+        };
+
+        Some(descr)
+    }
+
+    /// Dtype of a NumPy type string, and whether its data is big-endian.
+    fn from_descr(descr: &str) -> Result<(Self, bool), Error> {
+        let unsupported = || Error::Format(format!("unsupported numpy dtype '{}'", descr));
+
+        let mut chars = descr.chars();
+        let big = match chars.next() {
+            Some('<' | '|' | '=') => false,
+            Some('>') => true,
+            _ => return Err(unsupported()),
+        };
+        let kind = chars.next().ok_or_else(unsupported)?;
+        let size: usize = chars.as_str().parse().map_err(|_| unsupported())?;
+
+        let dtype = match (kind, size) {
+            ('b', 1) => Dtype::Bool,
+            ('u', 1) => Dtype::U8,
+            ('i', 1) => Dtype::I8,
+            ('u', 2) => Dtype::U16,
+            ('i', 2) => Dtype::I16,
+            ('f', 2) => Dtype::F16,
+            ('u', 4) => Dtype::U32,
+            ('i', 4) => Dtype::I32,
+            ('f', 4) => Dtype::F32,
+            ('u', 8) => Dtype::U64,
+            ('i', 8) => Dtype::I64,
+            ('f', 8) => Dtype::F64,
+            _ => return Err(unsupported()),
+        };
+
+        Ok((dtype, big))
+    }
+}
+
+/// Read a single array of at most `size` bytes, converting it to little-endian, C-ordered data of
+/// the dtype `U` packs. Lengths in the header are checked against `size` before anything is
+/// allocated for them.
+pub(crate) fn read<U>(reader: &mut impl Read, size: u64) -> Result<(TensorOrder, Vec<u8>), Error>
+where
+    Packet<U>: SupportedPacket,
+{
+    let truncated = || Error::Format(".npy array is larger than its file".into());
+
+    let mut preamble = [0; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != MAGIC {
+        return Err(Error::Format("not a .npy file".into()));
+    }
+
+    let (length, preamble) = match preamble[6] {
+        1 => {
+            let mut word = [0; 2];
+            reader.read_exact(&mut word)?;
+            (u16::from_le_bytes(word) as u64, 10)
+        }
+        2 | 3 => {
+            let mut word = [0; 4];
+            reader.read_exact(&mut word)?;
+            (u32::from_le_bytes(word) as u64, 12)
+        }
+        major => return Err(Error::Format(format!(".npy version {} is unknown", major))),
+    };
+    let remaining = size.checked_sub(preamble + length).ok_or_else(truncated)?;
+
+    let mut header = vec![0; length as usize];
+    reader.read_exact(&mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    let descr = field(&header, "descr")
+        .and_then(|value| value.strip_prefix('\''))
+        .and_then(|value| value.split('\'').next())
+        .ok_or_else(|| Error::Format("structured or missing numpy dtype".into()))?;
+    let (dtype, big) = Dtype::from_descr(descr)?;
+    let fortran = field(&header, "fortran_order").is_some_and(|value| value.starts_with("True"));
+
+    let mut dims = field(&header, "shape")
+        .and_then(|value| value.strip_prefix('('))
+        .and_then(|value| value.split(')').next())
+        .ok_or_else(|| Error::Format("missing numpy shape".into()))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.trim_end_matches('L').parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| Error::Format("malformed numpy shape".into()))?;
+    // Scalars are stored without dimensions.
+    if dims.is_empty() {
+        dims.push(1);
+    }
+
+    if Packet::<U>::DTYPE != Some(dtype) {
+        return Err(Error::Format(format!(
+            "array is stored as '{}', which doesn't match the tensor's dtype",
+            descr
+        )));
+    }
+    if !Packet::<U>::SHADABLE {
+        return Err(Error::Format(format!(
+            "array is stored as '{}', which shaders can't read",
+            descr
+        )));
+    }
+
+    let length = dims
+        .iter()
+        .try_fold(dtype.size() as u64, |length, dim| {
+            length.checked_mul(*dim as u64)
+        })
+        .filter(|length| *length <= remaining)
+        .ok_or_else(truncated)?;
+    let mut contents = vec![0; length as usize];
+    reader.read_exact(&mut contents)?;
+
+    if big {
+        for element in contents.chunks_exact_mut(dtype.size()) {
+            element.reverse();
+        }
+    }
+    if fortran {
+        contents = transpose(&contents, &dims, dtype.size());
+    }
+
+    Ok((TensorOrder::new(dims), contents))
+}
+
+/// Write a single array in C order.
+pub(crate) fn write(
+    writer: &mut impl Write,
+    dtype: Dtype,
+    dims: &[u32],
+    contents: &[u8],
+) -> Result<(), Error> {
+    let descr = dtype
+        .descr()
+        .ok_or(Error::Unsupported("dtype without a numpy equivalent"))?;
+
+    let shape = match dims {
+        [dim] => format!("({},)", dim),
+        dims => {
+            let dims: Vec<_> = dims.iter().map(u32::to_string).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+
+    // Version 1 headers: magic, version, a 2-byte length, then the header ending in a newline.
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.extend(std::iter::repeat_n(
+        ' ',
+        (ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT,
+    ));
+    header.push('\n');
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&(header.len() as u16).to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    writer.write_all(contents)?;
+
+    Ok(())
+}
+
+/// Text following `'key':` in a header dict.
+fn field<'h>(header: &'h str, key: &str) -> Option<&'h str> {
+    let at = header.find(&format!("'{}'", key))? + key.len() + 2;
+    Some(header[at..].trim_start().strip_prefix(':')?.trim_start())
+}
+
+/// Reorder column-major elements of `size` bytes into row-major order.
+fn transpose(contents: &[u8], dims: &[u32], size: usize) -> Vec<u8> {
+    let mut reordered = vec![0; contents.len()];
+    let mut index = vec![0u32; dims.len()];
+
+    for element in reordered.chunks_exact_mut(size) {
+        let (source, _) = index
+            .iter()
+            .zip(dims.iter())
+            .fold((0usize, 1usize), |(offset, stride), (at, dim)| {
+                (offset + *at as usize * stride, stride * *dim as usize)
+            });
+        element.copy_from_slice(&contents[source * size..(source + 1) * size]);
+
+        // Advance the row-major index, last axis fastest.
+        for (at, dim) in index.iter_mut().zip(dims.iter()).rev() {
+            *at += 1;
+            if *at < *dim {
+                break;
+            }
+            *at = 0;
+        }
+    }
+
+    reordered
+}
+
+/// Stored file within a zip archive.
+struct Member {
+    name: String,
+    /// Start of the data, past the local header.
+    offset: u64,
+    size: u64,
+}
+
+/// Open `.npz` archive. Arrays are read on request; only archives written by `np.savez`, which
+/// stores them uncompressed, are supported; compressed ones would need an inflater the toolkit
+/// doesn't ship.
+pub struct Npz {
+    file: BufReader<File>,
+    members: Vec<Member>,
+}
+
+impl Npz {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut file = BufReader::new(File::open(path)?);
+        let members = zip::members(&mut file)?;
+
+        Ok(Self { file, members })
+    }
+
+    /// Names of every array, as passed to `np.savez`.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.members
+            .iter()
+            .map(|member| member.name.strip_suffix(".npy").unwrap_or(&member.name))
+    }
+
+    /// Read the array called `name`, which must be stored with the dtype `U` packs.
+    pub fn tensor<T, U>(&mut self, name: &str) -> Result<Tensor<'static, T, U>, Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let member = self
+            .members
+            .iter()
+            .find(|member| member.name.strip_suffix(".npy").unwrap_or(&member.name) == name)
+            .ok_or_else(|| Error::Format(format!("no array named {}", name)))?;
+
+        self.file.seek(SeekFrom::Start(member.offset))?;
+        let (order, contents) = read::<U>(&mut (&mut self.file).take(member.size), member.size)?;
+
+        Ok(Tensor::from_bytes(contents, order))
+    }
+}
+
+/// Write `tensors` into an uncompressed `.npz`, as `np.savez` would, resolving them on
+/// `instance` first.
+pub fn save<T, U>(
+    path: impl AsRef<Path>,
+    instance: &Instance,
+    tensors: &[(&str, &Tensor<'_, T, U>)],
+) -> Result<(), Error>
+where
+    T: Component,
+    Packet<U>: SupportedPacket,
+    Bundle<U>: Operand + 'static,
+{
+    let dtype = Packet::<U>::DTYPE.ok_or(Error::Unsupported("dtype without a numpy equivalent"))?;
+    let arrays = tensors
+        .iter()
+        .map(|(name, tensor)| {
+            (
+                format!("{}.npy", name),
+                tensor.order.pull(),
+                tensor.operand(),
+            )
+        })
+        .collect::<Vec<(String, Vec<u32>, Arc<dyn Operand>)>>();
+
+    super::replace(path.as_ref(), |file| {
+        let mut archive = zip::Writer::new(file);
+        for (name, dims, operand) in arrays.iter() {
+            let mut array = Vec::new();
+            write(&mut array, dtype, dims, &instance.read(operand)?)?;
+            archive.store(name, &array)?;
+        }
+        archive.finish()
+    })
+}
+
+/// The subset of zip `.npz` files need: stored members, with zip64 extensions when reading, as
+/// NumPy always writes them.
+mod zip {
+    use std::io::{Read, Seek, SeekFrom, Write};
+
+    use crate::core::Error;
+
+    use super::Member;
+
+    const LOCAL: u32 = 0x0403_4b50;
+    const CENTRAL: u32 = 0x0201_4b50;
+    const END: u32 = 0x0605_4b50;
+    const END64: u32 = 0x0606_4b50;
+    const LOCATOR64: u32 = 0x0706_4b50;
+
+    fn malformed() -> Error {
+        Error::Format("malformed .npz archive".into())
+    }
+
+    fn u16_at(bytes: &[u8], at: usize) -> Result<u16, Error> {
+        let bytes = bytes.get(at..at + 2).ok_or_else(malformed)?;
+        Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> Result<u32, Error> {
+        let bytes = bytes.get(at..at + 4).ok_or_else(malformed)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64_at(bytes: &[u8], at: usize) -> Result<u64, Error> {
+        let bytes = bytes.get(at..at + 8).ok_or_else(malformed)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Members listed by the central directory.
+    pub(super) fn members(file: &mut (impl Read + Seek)) -> Result<Vec<Member>, Error> {
+        // The end record sits within the last 64 KiB, trailing comment included.
+        let size = file.seek(SeekFrom::End(0))?;
+        let tail = size.min(22 + u16::MAX as u64);
+        file.seek(SeekFrom::Start(size - tail))?;
+        let mut end = vec![0; tail as usize];
+        file.read_exact(&mut end)?;
+
+        let at = (0..end.len().saturating_sub(21))
+            .rev()
+            .find(|at| u32_at(&end, *at).is_ok_and(|word| word == END))
+            .ok_or_else(malformed)?;
+
+        let mut count = u16_at(&end, at + 10)? as u64;
+        let mut directory = u32_at(&end, at + 16)? as u64;
+
+        // Counts and offsets that don't fit move to the zip64 end record.
+        if count == u16::MAX as u64 || directory == u32::MAX as u64 {
+            let locator = at.checked_sub(20).ok_or_else(malformed)?;
+            if u32_at(&end, locator)? != LOCATOR64 {
+                return Err(malformed());
+            }
+            file.seek(SeekFrom::Start(u64_at(&end, locator + 8)?))?;
+            let mut record = [0; 56];
+            file.read_exact(&mut record)?;
+            if u32_at(&record, 0)? != END64 {
+                return Err(malformed());
+            }
+            count = u64_at(&record, 32)?;
+            directory = u64_at(&record, 48)?;
+        }
+
+        file.seek(SeekFrom::Start(directory))?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let mut header = [0; 46];
+            file.read_exact(&mut header)?;
+            if u32_at(&header, 0)? != CENTRAL {
+                return Err(malformed());
+            }
+
+            let method = u16_at(&header, 10)?;
+            let mut packed = u32_at(&header, 20)? as u64;
+            let mut size = u32_at(&header, 24)? as u64;
+            let mut offset = u32_at(&header, 42)? as u64;
+
+            let mut tail = vec![
+                0;
+                u16_at(&header, 28)? as usize
+                    + u16_at(&header, 30)? as usize
+                    + u16_at(&header, 32)? as usize
+            ];
+            file.read_exact(&mut tail)?;
+            let (name, extra) = tail.split_at(u16_at(&header, 28)? as usize);
+            let name = String::from_utf8_lossy(name).into_owned();
+            let extra = &extra[..u16_at(&header, 30)? as usize];
+
+            // Zip64 extra fields only hold the values saturated in the header, in this order.
+            let mut at = 0;
+            while at + 4 <= extra.len() {
+                let (id, len) = (u16_at(extra, at)?, u16_at(extra, at + 2)? as usize);
+                if id == 0x0001 {
+                    let mut field = at + 4;
+                    for value in [&mut size, &mut packed, &mut offset] {
+                        if *value == u32::MAX as u64 {
+                            *value = u64_at(extra, field)?;
+                            field += 8;
+                        }
+                    }
+                }
+                at += 4 + len;
+            }
+
+            if method != 0 {
+                return Err(Error::Format(format!(
+                    "array {} is compressed; only archives written by np.savez are supported",
+                    name
+                )));
+            }
+            entries.push((name, offset, size));
+        }
+
+        // Data starts past each local header, whose extra field may differ from the central one.
+        let mut members = Vec::new();
+        for (name, offset, size) in entries {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut header = [0; 30];
+            file.read_exact(&mut header)?;
+            if u32_at(&header, 0)? != LOCAL {
+                return Err(malformed());
+            }
+            let skip = u16_at(&header, 26)? as u64 + u16_at(&header, 28)? as u64;
+
+            members.push(Member {
+                name,
+                offset: offset + 30 + skip,
+                size,
+            });
+        }
+
+        Ok(members)
+    }
+
+    /// Zip archive of stored members, without zip64 extensions, so each member and the whole
+    /// archive stay under 4 GiB, and there are at most 65535 of them.
+    pub(super) struct Writer<W: Write> {
+        inner: W,
+        written: u64,
+        directory: Vec<u8>,
+        count: u16,
+    }
+
+    impl<W: Write> Writer<W> {
+        pub fn new(inner: W) -> Self {
+            Self {
+                inner,
+                written: 0,
+                directory: Vec::new(),
+                count: 0,
+            }
+        }
+
+        pub fn store(&mut self, name: &str, contents: &[u8]) -> Result<(), Error> {
+            let too_large = || Error::Unsupported(".npz archives above 4 GiB");
+            let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+            let offset = u32::try_from(self.written).map_err(|_| too_large())?;
+            if self.count == u16::MAX {
+                return Err(Error::Unsupported(
+                    ".npz archives of more than 65535 arrays",
+                ));
+            }
+            let name_len = u16::try_from(name.len())
+                .map_err(|_| Error::Unsupported(".npz member names above 64 KiB"))?;
+            let checksum = crc32(contents);
+
+            // Fields shared by the local and central headers: version needed, flags, method,
+            // time, date, checksum, sizes and name length, then an empty extra field.
+            let mut common = Vec::new();
+            common.extend(20u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+            common.extend(0x21u16.to_le_bytes());
+            common.extend(checksum.to_le_bytes());
+            common.extend(size.to_le_bytes());
+            common.extend(size.to_le_bytes());
+            common.extend(name_len.to_le_bytes());
+            common.extend(0u16.to_le_bytes());
+
+            let mut local = LOCAL.to_le_bytes().to_vec();
+            local.extend(&common);
+            local.extend(name.as_bytes());
+            self.inner.write_all(&local)?;
+            self.inner.write_all(contents)?;
+            self.written += local.len() as u64 + contents.len() as u64;
+
+            self.directory.extend(CENTRAL.to_le_bytes());
+            self.directory.extend(20u16.to_le_bytes());
+            self.directory.extend(&common);
+            // Comment length, disk, internal and external attributes, then the local header.
+            self.directory.extend([0; 2 + 2 + 2 + 4]);
+            self.directory.extend(offset.to_le_bytes());
+            self.directory.extend(name.as_bytes());
+            self.count += 1;
+
+            Ok(())
+        }
+
+        pub fn finish(mut self) -> Result<(), Error> {
+            let offset = u32::try_from(self.written)
+                .map_err(|_| Error::Unsupported(".npz archives above 4 GiB"))?;
+
+            self.inner.write_all(&self.directory)?;
+            let mut end = END.to_le_bytes().to_vec();
+            end.extend([0; 4]);
+            end.extend(self.count.to_le_bytes());
+            end.extend(self.count.to_le_bytes());
+            end.extend((self.directory.len() as u32).to_le_bytes());
+            end.extend(offset.to_le_bytes());
+            end.extend([0; 2]);
+            self.inner.write_all(&end)?;
+            self.inner.flush()?;
+
+            Ok(())
+        }
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for byte in bytes {
+            crc ^= *byte as u32;
+            for _ in 0..8 {
+                crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+            }
+        }
+        !crc
+    }
+}
@@ -0,0 +1,1756 @@
+// ONNX: a protobuf `ModelProto` wrapping a graph of operator nodes over named values, weights
+// being stored inline as initializers or in files next to the model. Decoded by hand, the
+// handful of messages involved not being worth a protobuf dependency.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{self, Path},
+};
+
+use crate::{
+    api::{
+        nn::{Conv2d, ConvOpts, Module, Param},
+        Tensor, TensorOrder,
+    },
+    core::{ops::Window, Error},
+};
+
+use self::wire::Reader;
+
+/// Operators of the default domain that map onto tensor operations. Some of them only in some
+/// of their forms, which `Onnx::run` reports by node.
+const OPERATORS: &[&str] = &[
+    "Abs",
+    "Add",
+    "AveragePool",
+    "BatchNormalization",
+    "Cast",
+    "Concat",
+    "Constant",
+    "Conv",
+    "Div",
+    "Dropout",
+    "Exp",
+    "Flatten",
+    "Gather",
+    "Gelu",
+    "Gemm",
+    "GlobalAveragePool",
+    "GlobalMaxPool",
+    "Identity",
+    "Log",
+    "LogSoftmax",
+    "MatMul",
+    "MaxPool",
+    "Mul",
+    "Neg",
+    "ReduceMax",
+    "ReduceMean",
+    "ReduceSum",
+    "Relu",
+    "Reshape",
+    "Shape",
+    "Sigmoid",
+    "Softmax",
+    "Sqrt",
+    "Squeeze",
+    "Sub",
+    "Tanh",
+    "Transpose",
+    "Unsqueeze",
+];
+
+/// Graph input or output, as the model declares it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValueInfo {
+    pub name: String,
+    /// Outermost axis first; `None` for axes only sized at run time, e.g. the batch. Empty when
+    /// the model doesn't declare a shape.
+    pub shape: Vec<Option<u32>>,
+}
+
+/// Loaded ONNX model. Floating-point initializers become tensors up front, uploaded once when
+/// first resolved and shared by every run; integer ones, typically shapes, stay on the host.
+pub struct Onnx {
+    pub ir_version: i64,
+    /// Version of the default operator set the graph was exported against.
+    pub opset: i64,
+    pub producer: String,
+    /// Values `run` has to be fed, i.e. graph inputs that aren't initializers.
+    pub inputs: Vec<ValueInfo>,
+    pub outputs: Vec<ValueInfo>,
+    nodes: Vec<Node>,
+    initializers: HashMap<String, Slot>,
+}
+
+impl Onnx {
+    /// Read a model along with any weights it stores in external files, looked up relative to
+    /// the model.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::decode(&fs::read(path)?, path.parent())
+    }
+
+    /// Model serialized in memory; it can't refer to external weights.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode(bytes, None)
+    }
+
+    fn decode(bytes: &[u8], base: Option<&Path>) -> Result<Self, Error> {
+        let mut model = Self {
+            ir_version: 0,
+            opset: 1,
+            producer: String::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            nodes: Vec::new(),
+            initializers: HashMap::new(),
+        };
+
+        let mut graph = None;
+        let mut reader = Reader::new(bytes);
+        while let Some((number, field)) = reader.field()? {
+            match number {
+                1 => model.ir_version = field.int()?,
+                2 => model.producer = field.string()?,
+                7 => graph = Some(field.bytes()?),
+                8 => {
+                    let (domain, version) = opset(field.bytes()?)?;
+                    if domain.is_empty() || domain == "ai.onnx" {
+                        model.opset = version;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let graph = graph.ok_or_else(|| Error::Format("ONNX model without a graph".into()))?;
+        let mut inputs = Vec::new();
+        let mut reader = Reader::new(graph);
+        while let Some((number, field)) = reader.field()? {
+            match number {
+                1 => model.nodes.push(Node::decode(field.bytes()?)?),
+                5 => {
+                    let (name, constant) = Constant::decode(field.bytes()?, base)?;
+                    let slot = match constant.data {
+                        Data::Floats(values) => {
+                            Slot::Device(Tensor::from_vec(values, order(&constant.dims)))
+                        }
+                        Data::Ints(_) => Slot::Host(constant),
+                    };
+                    model.initializers.insert(name, slot);
+                }
+                11 => inputs.push(value_info(field.bytes()?)?),
+                12 => model.outputs.push(value_info(field.bytes()?)?),
+                15 => return Err(Error::Format("sparse initializers are unsupported".into())),
+                _ => {}
+            }
+        }
+
+        // Initializers used to be listed among inputs as well, to allow overriding them.
+        model.inputs = inputs
+            .into_iter()
+            .filter(|input| !model.initializers.contains_key(&input.name))
+            .collect();
+
+        Ok(model)
+    }
+
+    /// Operator types with no counterpart among tensor operations, in the order the graph first
+    /// uses them; those of other domains are prefixed with it.
+    pub fn unmapped(&self) -> Vec<String> {
+        let mut unmapped: Vec<String> = Vec::new();
+        for node in self.nodes.iter() {
+            let default = node.domain.is_empty() || node.domain == "ai.onnx";
+            if default && OPERATORS.contains(&node.op.as_str()) {
+                continue;
+            }
+
+            let op = match default {
+                true => node.op.clone(),
+                false => format!("{}.{}", node.domain, node.op),
+            };
+            if !unmapped.contains(&op) {
+                unmapped.push(op);
+            }
+        }
+        unmapped
+    }
+
+    /// Build the graph over `inputs`, given by name, and return its outputs in declaration
+    /// order. Nothing runs until the outputs are resolved, like any other tensor; shape
+    /// computations on constants are carried out on the host right away.
+    pub fn run(&self, inputs: &[(&str, &Param)]) -> Result<Vec<Param>, Error> {
+        let unmapped = self.unmapped();
+        if !unmapped.is_empty() {
+            return Err(Error::Unmapped(unmapped));
+        }
+
+        let mut values = self.initializers.clone();
+        for (name, tensor) in inputs.iter() {
+            let declared = self
+                .inputs
+                .iter()
+                .find(|input| input.name == *name)
+                .ok_or_else(|| Error::Graph(format!("no input named {}", name)))?;
+
+            let dims = tensor.order.pull();
+            let fits = declared.shape.is_empty()
+                || declared.shape.len() == dims.len()
+                    && declared
+                        .shape
+                        .iter()
+                        .zip(dims.iter())
+                        .all(|(want, dim)| want.is_none_or(|want| want == *dim));
+            if !fits {
+                return Err(Error::Graph(format!(
+                    "input {} has shape {:?}, expected {:?}",
+                    name, dims, declared.shape
+                )));
+            }
+
+            values.insert(name.to_string(), Slot::Device((*tensor).clone()));
+        }
+        if let Some(missing) = self
+            .inputs
+            .iter()
+            .find(|input| !values.contains_key(&input.name))
+        {
+            return Err(Error::Graph(format!("missing input {}", missing.name)));
+        }
+
+        for node in self.nodes.iter() {
+            let step = Step {
+                node,
+                values: &values,
+                opset: self.opset,
+            };
+            let slot = step.apply()?;
+
+            // Secondary outputs, such as dropout masks or pooling indices, are never produced.
+            if let Some(name) = node.outputs.first().filter(|name| !name.is_empty()) {
+                values.insert(name.clone(), slot);
+            }
+        }
+
+        self.outputs
+            .iter()
+            .map(|output| {
+                values
+                    .get(&output.name)
+                    .map(Slot::tensor)
+                    .ok_or_else(|| Error::Graph(format!("output {} is never set", output.name)))
+            })
+            .collect()
+    }
+}
+
+/// Protobuf wire format: every field is a varint key, holding the field number and wire type,
+/// followed by its value.
+mod wire {
+    use crate::core::Error;
+
+    pub enum Field<'b> {
+        Varint(u64),
+        Fixed64(u64),
+        Bytes(&'b [u8]),
+        Fixed32(u32),
+    }
+
+    pub struct Reader<'b> {
+        buf: &'b [u8],
+    }
+
+    impl<'b> Reader<'b> {
+        pub fn new(buf: &'b [u8]) -> Self {
+            Self { buf }
+        }
+
+        /// Next field number and value, or `None` past the end of the message.
+        pub fn field(&mut self) -> Result<Option<(u32, Field<'b>)>, Error> {
+            if self.buf.is_empty() {
+                return Ok(None);
+            }
+
+            let key = self.varint()?;
+            let field = match key & 7 {
+                0 => Field::Varint(self.varint()?),
+                1 => Field::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+                2 => {
+                    let len = self.varint()?;
+                    Field::Bytes(self.take(usize::try_from(len).map_err(|_| truncated())?)?)
+                }
+                5 => Field::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+                ty => {
+                    return Err(Error::Format(format!(
+                        "protobuf wire type {} is unsupported",
+                        ty
+                    )))
+                }
+            };
+
+            Ok(Some(((key >> 3) as u32, field)))
+        }
+
+        fn varint(&mut self) -> Result<u64, Error> {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let (&byte, rest) = self.buf.split_first().ok_or_else(truncated)?;
+                self.buf = rest;
+
+                value |= ((byte & 0x7f) as u64) << shift;
+                if byte & 0x80 == 0 {
+                    return Ok(value);
+                }
+            }
+
+            Err(Error::Format("protobuf varint is too long".into()))
+        }
+
+        fn take(&mut self, len: usize) -> Result<&'b [u8], Error> {
+            if len > self.buf.len() {
+                return Err(truncated());
+            }
+
+            let (head, rest) = self.buf.split_at(len);
+            self.buf = rest;
+            Ok(head)
+        }
+    }
+
+    impl<'b> Field<'b> {
+        pub fn bytes(&self) -> Result<&'b [u8], Error> {
+            match self {
+                Field::Bytes(bytes) => Ok(bytes),
+                _ => Err(mistyped()),
+            }
+        }
+
+        pub fn string(&self) -> Result<String, Error> {
+            String::from_utf8(self.bytes()?.to_vec())
+                .map_err(|_| Error::Format("protobuf string is not utf-8".into()))
+        }
+
+        pub fn int(&self) -> Result<i64, Error> {
+            match *self {
+                Field::Varint(value) => Ok(value as i64),
+                _ => Err(mistyped()),
+            }
+        }
+
+        pub fn float(&self) -> Result<f32, Error> {
+            match *self {
+                Field::Fixed32(bits) => Ok(f32::from_bits(bits)),
+                _ => Err(mistyped()),
+            }
+        }
+
+        /// Append a repeated integer field, whether packed or not.
+        pub fn ints(&self, out: &mut Vec<i64>) -> Result<(), Error> {
+            match self {
+                Field::Bytes(bytes) => {
+                    let mut reader = Reader::new(bytes);
+                    while !reader.buf.is_empty() {
+                        out.push(reader.varint()? as i64);
+                    }
+                }
+                field => out.push(field.int()?),
+            }
+            Ok(())
+        }
+
+        /// Append a repeated float field, whether packed or not.
+        pub fn floats(&self, out: &mut Vec<f32>) -> Result<(), Error> {
+            match self {
+                Field::Bytes(bytes) if bytes.len() % 4 == 0 => out.extend(
+                    bytes
+                        .chunks_exact(4)
+                        .map(|word| f32::from_le_bytes(word.try_into().unwrap())),
+                ),
+                field => out.push(field.float()?),
+            }
+            Ok(())
+        }
+
+        /// Append a repeated double field, narrowed to floats, whether packed or not.
+        pub fn doubles(&self, out: &mut Vec<f32>) -> Result<(), Error> {
+            match *self {
+                Field::Bytes(bytes) if bytes.len() % 8 == 0 => out.extend(
+                    bytes
+                        .chunks_exact(8)
+                        .map(|word| f64::from_le_bytes(word.try_into().unwrap()) as f32),
+                ),
+                Field::Fixed64(bits) => out.push(f64::from_bits(bits) as f32),
+                _ => return Err(mistyped()),
+            }
+            Ok(())
+        }
+    }
+
+    fn truncated() -> Error {
+        Error::Format("protobuf message is truncated".into())
+    }
+
+    fn mistyped() -> Error {
+        Error::Format("protobuf field has an unexpected wire type".into())
+    }
+}
+
+/// Host tensor, from an initializer or computed out of constants.
+#[derive(Clone, Debug)]
+struct Constant {
+    /// Empty for scalars.
+    dims: Vec<u32>,
+    data: Data,
+}
+
+#[derive(Clone, Debug)]
+enum Data {
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+}
+
+impl Constant {
+    /// `TensorProto`, returned along with its name.
+    fn decode(bytes: &[u8], base: Option<&Path>) -> Result<(String, Self), Error> {
+        let mut name = String::new();
+        let (mut dims, mut ty, mut external) = (Vec::new(), 0, false);
+        let mut raw = None;
+        let (mut floats, mut ints, mut wide) = (Vec::new(), Vec::new(), Vec::new());
+        let mut locations = Vec::new();
+
+        let mut reader = Reader::new(bytes);
+        while let Some((number, field)) = reader.field()? {
+            match number {
+                1 => field.ints(&mut dims)?,
+                2 => ty = field.int()?,
+                4 => field.floats(&mut floats)?,
+                5 => field.ints(&mut ints)?,
+                7 | 11 => field.ints(&mut wide)?,
+                8 => name = field.string()?,
+                9 => raw = Some(field.bytes()?.to_vec()),
+                10 => field.doubles(&mut floats)?,
+                13 => locations.push(entry(field.bytes()?)?),
+                14 => external = field.int()? == 1,
+                _ => {}
+            }
+        }
+
+        let dims = dims
+            .into_iter()
+            .map(u32::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::Format(format!("tensor {} has a dimension out of range", name)))?;
+        let count = dims.iter().map(|dim| *dim as usize).product::<usize>();
+
+        if external {
+            raw = Some(external_data(&name, &locations, base)?);
+        }
+
+        let data = match (ty, raw) {
+            (1, Some(raw)) => Data::Floats(
+                raw.chunks_exact(4)
+                    .map(|word| f32::from_le_bytes(word.try_into().unwrap()))
+                    .collect(),
+            ),
+            (11, Some(raw)) => Data::Floats(
+                raw.chunks_exact(8)
+                    .map(|word| f64::from_le_bytes(word.try_into().unwrap()) as f32)
+                    .collect(),
+            ),
+            (10 | 16, Some(raw)) => Data::Floats(
+                raw.chunks_exact(2)
+                    .map(|half| widen(u16::from_le_bytes([half[0], half[1]]), ty))
+                    .collect(),
+            ),
+            (1 | 11, None) => Data::Floats(floats),
+            (10 | 16, None) => {
+                Data::Floats(ints.iter().map(|bits| widen(*bits as u16, ty)).collect())
+            }
+            (2 | 3 | 4 | 5 | 6 | 7 | 9 | 12 | 13, Some(raw)) => Data::Ints(integers(&raw, ty)),
+            (2 | 3 | 4 | 5 | 6 | 9, None) => Data::Ints(ints),
+            (7 | 12 | 13, None) => Data::Ints(wide),
+            (ty, _) => {
+                return Err(Error::Format(format!(
+                    "tensor {} has unsupported data type {}",
+                    name, ty
+                )))
+            }
+        };
+
+        let len = match &data {
+            Data::Floats(values) => values.len(),
+            Data::Ints(values) => values.len(),
+        };
+        if len != count {
+            return Err(Error::Format(format!(
+                "tensor {} holds {} elements, expected {}",
+                name, len, count
+            )));
+        }
+
+        Ok((name, Self { dims, data }))
+    }
+
+    fn floats(&self) -> Vec<f32> {
+        match &self.data {
+            Data::Floats(values) => values.clone(),
+            Data::Ints(values) => values.iter().map(|value| *value as f32).collect(),
+        }
+    }
+
+    fn ints(&self) -> Vec<i64> {
+        match &self.data {
+            Data::Floats(values) => values.iter().map(|value| *value as i64).collect(),
+            Data::Ints(values) => values.clone(),
+        }
+    }
+
+    /// Same values converted to data type `to`, booleans being kept as zeros and ones.
+    fn cast(&self, to: i64) -> Self {
+        let data = match to {
+            1 | 10 | 11 | 16 => Data::Floats(self.floats()),
+            9 => Data::Ints(
+                self.floats()
+                    .iter()
+                    .map(|value| i64::from(*value != 0.0))
+                    .collect(),
+            ),
+            _ => Data::Ints(self.ints()),
+        };
+
+        Self {
+            dims: self.dims.clone(),
+            data,
+        }
+    }
+}
+
+/// Value flowing through the graph while building it.
+#[derive(Clone)]
+enum Slot {
+    Host(Constant),
+    Device(Param),
+}
+
+impl Slot {
+    fn dims(&self) -> Vec<u32> {
+        match self {
+            Slot::Host(constant) => constant.dims.clone(),
+            Slot::Device(tensor) => tensor.order.pull(),
+        }
+    }
+
+    fn tensor(&self) -> Param {
+        match self {
+            Slot::Host(constant) => Tensor::from_vec(constant.floats(), order(&constant.dims)),
+            Slot::Device(tensor) => tensor.clone(),
+        }
+    }
+
+    /// Same elements under `dims`, which must hold as many.
+    fn reshape(&self, dims: Vec<u32>) -> Self {
+        match self {
+            Slot::Host(constant) => Slot::Host(Constant {
+                dims,
+                data: constant.data.clone(),
+            }),
+            Slot::Device(tensor) if tensor.order.pull() == dims => self.clone(),
+            Slot::Device(tensor) => Slot::Device(tensor.reshape(order(&dims))),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Attribute {
+    Float(f32),
+    Int(i64),
+    String(String),
+    Tensor(Constant),
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+    /// Graphs, sparse tensors and the like, which no mapped operator takes.
+    Other,
+}
+
+struct Node {
+    name: String,
+    op: String,
+    domain: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: HashMap<String, Attribute>,
+}
+
+impl Node {
+    /// `NodeProto`.
+    fn decode(bytes: &[u8]) -> Result<Self, Error> {
+        let mut node = Self {
+            name: String::new(),
+            op: String::new(),
+            domain: String::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            attributes: HashMap::new(),
+        };
+
+        let mut reader = Reader::new(bytes);
+        while let Some((number, field)) = reader.field()? {
+            match number {
+                1 => node.inputs.push(field.string()?),
+                2 => node.outputs.push(field.string()?),
+                3 => node.name = field.string()?,
+                4 => node.op = field.string()?,
+                5 => {
+                    let (name, attribute) = attribute(field.bytes()?)?;
+                    node.attributes.insert(name, attribute);
+                }
+                7 => node.domain = field.string()?,
+                _ => {}
+            }
+        }
+
+        Ok(node)
+    }
+}
+
+/// `AttributeProto`, returned along with its name.
+fn attribute(bytes: &[u8]) -> Result<(String, Attribute), Error> {
+    let mut name = String::new();
+    let mut ty = 0;
+    let (mut float, mut int, mut string, mut tensor) = (None, None, None, None);
+    let (mut floats, mut ints) = (Vec::new(), Vec::new());
+
+    let mut reader = Reader::new(bytes);
+    while let Some((number, field)) = reader.field()? {
+        match number {
+            1 => name = field.string()?,
+            2 => float = Some(field.float()?),
+            3 => int = Some(field.int()?),
+            4 => string = Some(field.string()?),
+            5 => tensor = Some(Constant::decode(field.bytes()?, None)?.1),
+            7 => field.floats(&mut floats)?,
+            8 => field.ints(&mut ints)?,
+            20 => ty = field.int()?,
+            _ => {}
+        }
+    }
+
+    let attribute = match (ty, float, int, string, tensor) {
+        (1, Some(value), ..) | (0, Some(value), None, None, None) => Attribute::Float(value),
+        (2, _, Some(value), ..) | (0, None, Some(value), None, None) => Attribute::Int(value),
+        (3, _, _, Some(value), _) | (0, None, None, Some(value), None) => Attribute::String(value),
+        (4, .., Some(value)) | (0, None, None, None, Some(value)) => Attribute::Tensor(value),
+        (6, ..) => Attribute::Floats(floats),
+        (7, ..) => Attribute::Ints(ints),
+        (0, None, None, None, None) if !floats.is_empty() => Attribute::Floats(floats),
+        (0, None, None, None, None) if !ints.is_empty() => Attribute::Ints(ints),
+        _ => Attribute::Other,
+    };
+
+    Ok((name, attribute))
+}
+
+/// `ValueInfoProto`, keeping only the shape of tensor types.
+fn value_info(bytes: &[u8]) -> Result<ValueInfo, Error> {
+    let mut info = ValueInfo {
+        name: String::new(),
+        shape: Vec::new(),
+    };
+
+    // `TypeProto.tensor_type.shape.dim`, each one either a value or a symbolic parameter.
+    fn nested(bytes: &[u8], want: u32) -> Result<Option<&[u8]>, Error> {
+        let mut reader = Reader::new(bytes);
+        while let Some((number, field)) = reader.field()? {
+            if number == want {
+                return Ok(Some(field.bytes()?));
+            }
+        }
+        Ok(None)
+    }
+
+    let mut reader = Reader::new(bytes);
+    while let Some((number, field)) = reader.field()? {
+        match number {
+            1 => info.name = field.string()?,
+            2 => {
+                let Some(tensor) = nested(field.bytes()?, 1)? else {
+                    continue;
+                };
+                let Some(shape) = nested(tensor, 2)? else {
+                    continue;
+                };
+
+                let mut dims = Reader::new(shape);
+                while let Some((number, dim)) = dims.field()? {
+                    if number != 1 {
+                        continue;
+                    }
+
+                    let mut value = None;
+                    let mut reader = Reader::new(dim.bytes()?);
+                    while let Some((number, field)) = reader.field()? {
+                        if number == 1 {
+                            value = u32::try_from(field.int()?).ok();
+                        }
+                    }
+                    info.shape.push(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(info)
+}
+
+/// `OperatorSetIdProto`, as its domain and version.
+fn opset(bytes: &[u8]) -> Result<(String, i64), Error> {
+    let (mut domain, mut version) = (String::new(), 1);
+
+    let mut reader = Reader::new(bytes);
+    while let Some((number, field)) = reader.field()? {
+        match number {
+            1 => domain = field.string()?,
+            2 => version = field.int()?,
+            _ => {}
+        }
+    }
+
+    Ok((domain, version))
+}
+
+/// `StringStringEntryProto`.
+fn entry(bytes: &[u8]) -> Result<(String, String), Error> {
+    let (mut key, mut value) = (String::new(), String::new());
+
+    let mut reader = Reader::new(bytes);
+    while let Some((number, field)) = reader.field()? {
+        match number {
+            1 => key = field.string()?,
+            2 => value = field.string()?,
+            _ => {}
+        }
+    }
+
+    Ok((key, value))
+}
+
+/// Raw data of a tensor stored outside the model, at a path relative to it.
+fn external_data(
+    name: &str,
+    locations: &[(String, String)],
+    base: Option<&Path>,
+) -> Result<Vec<u8>, Error> {
+    let base = base.ok_or_else(|| {
+        Error::Format(format!(
+            "tensor {} is stored outside the model, which has to be opened from a file",
+            name
+        ))
+    })?;
+    let get = |key: &str| {
+        locations
+            .iter()
+            .find_map(|(k, value)| (k == key).then_some(value.as_str()))
+    };
+    let number = |key: &str| -> Result<Option<u64>, Error> {
+        get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| Error::Format(format!("tensor {} has an invalid {}", name, key)))
+            })
+            .transpose()
+    };
+
+    let location =
+        get("location").ok_or_else(|| Error::Format(format!("tensor {} has no location", name)))?;
+    let relative = Path::new(location)
+        .components()
+        .all(|component| matches!(component, path::Component::Normal(_)));
+    if !relative {
+        return Err(Error::Format(format!(
+            "tensor {} is stored outside the model's directory",
+            name
+        )));
+    }
+
+    let mut file = File::open(base.join(location))?;
+    let size = file.metadata()?.len();
+    let offset = number("offset")?.unwrap_or(0);
+    let length = number("length")?;
+    if length
+        .unwrap_or(0)
+        .checked_add(offset)
+        .is_none_or(|end| end > size)
+    {
+        return Err(Error::Format(format!(
+            "tensor {} runs past the end of {}",
+            name, location
+        )));
+    }
+    file.seek(SeekFrom::Start(offset))?;
+
+    let mut contents = Vec::new();
+    match length {
+        Some(length) => {
+            contents.resize(length as usize, 0);
+            file.read_exact(&mut contents)?;
+        }
+        None => {
+            file.read_to_end(&mut contents)?;
+        }
+    }
+
+    Ok(contents)
+}
+
+/// Half-precision bits, IEEE `float16` for data type 10 and `bfloat16` for 16, as a float.
+fn widen(bits: u16, ty: i64) -> f32 {
+    if ty == 16 {
+        return f32::from_bits((bits as u32) << 16);
+    }
+
+    let sign = ((bits as u32) & 0x8000) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as u32;
+
+    let magnitude = match exponent {
+        0 => mantissa as f32 / (1 << 24) as f32,
+        0x1f => f32::from_bits(0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits((((exponent + 112) as u32) << 23) | (mantissa << 13)),
+    };
+    f32::from_bits(sign | magnitude.to_bits())
+}
+
+/// Little-endian integers of data type `ty` as `i64`s.
+fn integers(raw: &[u8], ty: i64) -> Vec<i64> {
+    let (width, signed) = match ty {
+        2 | 9 => (1, false),
+        3 => (1, true),
+        4 => (2, false),
+        5 => (2, true),
+        6 => (4, true),
+        12 => (4, false),
+        _ => (8, true),
+    };
+
+    raw.chunks_exact(width)
+        .map(|bytes| {
+            let mut word = [0; 8];
+            word[..width].copy_from_slice(bytes);
+            if signed && bytes[width - 1] & 0x80 != 0 {
+                word[width..].fill(0xff);
+            }
+            i64::from_le_bytes(word)
+        })
+        .collect()
+}
+
+/// Tensor order of `dims`, scalars being held as a single element.
+fn order(dims: &[u32]) -> TensorOrder {
+    match dims.is_empty() {
+        true => TensorOrder::new(vec![1]),
+        false => TensorOrder::new(dims.to_vec()),
+    }
+}
+
+/// Shape two operands broadcast to under numpy rules, if they're compatible.
+fn broadcast_dims(lhs: &[u32], rhs: &[u32]) -> Option<Vec<u32>> {
+    let rank = lhs.len().max(rhs.len());
+    let dim = |dims: &[u32], axis: usize| {
+        (axis + dims.len())
+            .checked_sub(rank)
+            .map_or(1, |axis| dims[axis])
+    };
+
+    (0..rank)
+        .map(|axis| match (dim(lhs, axis), dim(rhs, axis)) {
+            (a, b) if a == b || b == 1 => Some(a),
+            (1, b) => Some(b),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Values of several tensors concatenated along an axis, each one contributing runs of `spans`
+/// elements in turn, i.e. its size from that axis on.
+fn join<V: Copy>(parts: &[Vec<V>], spans: &[usize]) -> Vec<V> {
+    let outer = parts
+        .first()
+        .zip(spans.first())
+        .map_or(0, |(values, span)| values.len() / span.max(&1));
+
+    let mut out = Vec::with_capacity(parts.iter().map(Vec::len).sum());
+    for o in 0..outer {
+        for (values, span) in parts.iter().zip(spans.iter()) {
+            out.extend_from_slice(&values[o * span..(o + 1) * span]);
+        }
+    }
+    out
+}
+
+/// Slices of `inner` elements picked along an axis of `count` entries, for every index of the
+/// axes before it.
+fn pick<V: Copy>(values: &[V], count: usize, picks: &[usize], inner: usize) -> Vec<V> {
+    let outer = values.len() / (count * inner).max(1);
+
+    let mut out = Vec::with_capacity(outer * picks.len() * inner);
+    for o in 0..outer {
+        for pick in picks.iter() {
+            let start = (o * count + pick) * inner;
+            out.extend_from_slice(&values[start..start + inner]);
+        }
+    }
+    out
+}
+
+/// Axes reordered so that output axis `i` is input axis `perm[i]`. Built out of swaps of
+/// adjacent axes, each one a transpose of the two innermost axes between reshapes, or two when
+/// axes follow the swapped ones.
+fn permute(tensor: &Param, perm: &[usize]) -> Param {
+    let mut dims = tensor.order.pull();
+    let mut target = vec![0; perm.len()];
+    for (position, axis) in perm.iter().enumerate() {
+        target[*axis] = position;
+    }
+
+    let mut axes: Vec<usize> = (0..dims.len()).collect();
+    let mut out = tensor.clone();
+    let mut sorted = false;
+    while !sorted {
+        sorted = true;
+        for i in 0..axes.len().saturating_sub(1) {
+            if target[axes[i]] < target[axes[i + 1]] {
+                continue;
+            }
+
+            let pre: u32 = dims[..i].iter().product();
+            let (a, b) = (dims[i], dims[i + 1]);
+            let post: u32 = dims[i + 2..].iter().product();
+            let shape = |dims: Vec<u32>| TensorOrder::new(dims);
+
+            if a != 1 && b != 1 {
+                out = out.reshape(shape(vec![pre, a, b * post])).transpose();
+                if post != 1 {
+                    out = out.reshape(shape(vec![pre * b, post, a])).transpose();
+                }
+            }
+
+            dims.swap(i, i + 1);
+            axes.swap(i, i + 1);
+            out = out.reshape(shape(dims.clone()));
+            sorted = false;
+        }
+    }
+
+    out
+}
+
+/// Node being mapped, along with every value computed so far.
+struct Step<'g> {
+    node: &'g Node,
+    values: &'g HashMap<String, Slot>,
+    opset: i64,
+}
+
+impl Step<'_> {
+    fn apply(&self) -> Result<Slot, Error> {
+        let op = self.node.op.as_str();
+        let slot = match op {
+            "Identity" | "Dropout" => self.input(0)?.clone(),
+            "Constant" => Slot::Host(self.constant()?),
+            "Shape" => {
+                let dims = self.input(0)?.dims();
+                let rank = dims.len() as i64;
+                let clamp = |axis: i64| (if axis < 0 { axis + rank } else { axis }).clamp(0, rank);
+                let start = clamp(self.int("start", 0)) as usize;
+                let end = clamp(self.int("end", rank)) as usize;
+                let dims: Vec<i64> = dims[start..end.max(start)]
+                    .iter()
+                    .map(|dim| *dim as i64)
+                    .collect();
+
+                Slot::Host(Constant {
+                    dims: vec![dims.len() as u32],
+                    data: Data::Ints(dims),
+                })
+            }
+            "Cast" => {
+                let to = self.int("to", 1);
+                match self.input(0)? {
+                    Slot::Host(constant) => Slot::Host(constant.cast(to)),
+                    Slot::Device(tensor) if matches!(to, 1 | 10 | 11 | 16) => {
+                        Slot::Device(tensor.clone())
+                    }
+                    Slot::Device(_) => return Err(self.unmapped("cast of a tensor to integers")),
+                }
+            }
+            "Add" | "Sub" | "Mul" | "Div" => {
+                let (lhs, rhs) = self.broadcast(self.tensor(0)?, self.tensor(1)?)?;
+                Slot::Device(match op {
+                    "Add" => &lhs + &rhs,
+                    "Sub" => &lhs - &rhs,
+                    "Mul" => &lhs * &rhs,
+                    _ => &lhs / &rhs,
+                })
+            }
+            "Abs" => Slot::Device(self.tensor(0)?.abs()),
+            "Exp" => Slot::Device(self.tensor(0)?.exp()),
+            "Log" => Slot::Device(self.tensor(0)?.log()),
+            "Neg" => Slot::Device(self.tensor(0)?.neg()),
+            "Sqrt" => Slot::Device(self.tensor(0)?.sqrt()),
+            "Relu" => Slot::Device(self.tensor(0)?.relu()),
+            "Sigmoid" => Slot::Device(self.tensor(0)?.sigmoid()),
+            "Tanh" => Slot::Device(self.tensor(0)?.tanh()),
+            // The exact, erf-based form is computed with the tanh approximation, which stays
+            // within 1e-3 of it.
+            "Gelu" => Slot::Device(self.tensor(0)?.gelu()),
+            "MatMul" => Slot::Device(self.matmul(self.tensor(0)?, self.tensor(1)?)?),
+            "Gemm" => Slot::Device(self.gemm()?),
+            "Softmax" | "LogSoftmax" => Slot::Device(self.softmax(op == "LogSoftmax")?),
+            "Reshape" => {
+                let dims = self.input(0)?.dims();
+                let shape = self.host(1)?.ints();
+                let allowzero = self.int("allowzero", 0) != 0;
+
+                let mut target = Vec::with_capacity(shape.len());
+                let mut infer = None;
+                for (axis, dim) in shape.iter().enumerate() {
+                    match *dim {
+                        -1 if infer.is_none() => {
+                            infer = Some(axis);
+                            target.push(1);
+                        }
+                        0 if !allowzero => target.push(*dims.get(axis).ok_or_else(|| {
+                            self.invalid("copies a dimension past the input's rank")
+                        })?),
+                        dim => target.push(
+                            u32::try_from(dim)
+                                .map_err(|_| self.invalid("has an invalid target shape"))?,
+                        ),
+                    }
+                }
+                if let Some(axis) = infer {
+                    let known: u32 = target.iter().product();
+                    let size: u32 = dims.iter().product();
+                    if known == 0 || !size.is_multiple_of(known) {
+                        return Err(self.invalid("has a shape that can't be inferred"));
+                    }
+                    target[axis] = size / known;
+                }
+
+                self.reshape(target)?
+            }
+            "Flatten" => {
+                let dims = self.input(0)?.dims();
+                let axis = self.axis(self.int("axis", 1), dims.len() + 1)?;
+
+                self.reshape(vec![
+                    dims[..axis].iter().product(),
+                    dims[axis..].iter().product(),
+                ])?
+            }
+            "Squeeze" => {
+                let dims = self.input(0)?.dims();
+                let axes = match self.axes()? {
+                    Some(axes) => axes
+                        .iter()
+                        .map(|axis| self.axis(*axis, dims.len()))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => (0..dims.len()).filter(|axis| dims[*axis] == 1).collect(),
+                };
+                if axes.iter().any(|axis| dims[*axis] != 1) {
+                    return Err(self.invalid("squeezes an axis that isn't of unit size"));
+                }
+
+                let target = (0..dims.len())
+                    .filter(|axis| !axes.contains(axis))
+                    .map(|axis| dims[axis])
+                    .collect();
+                self.reshape(target)?
+            }
+            "Unsqueeze" => {
+                let dims = self.input(0)?.dims();
+                let axes = self
+                    .axes()?
+                    .ok_or_else(|| self.invalid("has no axes to insert"))?;
+                let rank = dims.len() + axes.len();
+                let mut axes = axes
+                    .iter()
+                    .map(|axis| self.axis(*axis, rank))
+                    .collect::<Result<Vec<_>, _>>()?;
+                axes.sort_unstable();
+
+                let mut target = dims;
+                for axis in axes {
+                    target.insert(axis, 1);
+                }
+                self.reshape(target)?
+            }
+            "Transpose" => {
+                let tensor = self.tensor(0)?;
+                let rank = tensor.order.pull().len();
+                let perm = match self.ints("perm") {
+                    Some(perm) => perm
+                        .iter()
+                        .map(|axis| self.axis(*axis, rank))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => (0..rank).rev().collect(),
+                };
+
+                let mut seen = vec![false; rank];
+                for axis in perm.iter() {
+                    seen[*axis] = true;
+                }
+                if perm.len() != rank || seen.contains(&false) {
+                    return Err(self.invalid("has an invalid permutation"));
+                }
+
+                Slot::Device(permute(&tensor, &perm))
+            }
+            "Concat" => self.concat()?,
+            "Gather" => self.gather()?,
+            "Conv" => Slot::Device(self.conv()?),
+            "MaxPool" | "AveragePool" => Slot::Device(self.pool(op == "MaxPool")?),
+            "GlobalAveragePool" | "GlobalMaxPool" => {
+                let tensor = self.tensor(0)?;
+                let dims = tensor.order.pull();
+                if dims.len() < 3 {
+                    return Err(self.invalid("pools over an input without spatial axes"));
+                }
+
+                let count: u32 = dims[2..].iter().product();
+                let pooled = (2..dims.len()).fold(tensor, |acc, axis| match op {
+                    "GlobalMaxPool" => acc.max(axis),
+                    _ => acc.sum(axis),
+                });
+                Slot::Device(match op {
+                    "GlobalMaxPool" => pooled,
+                    _ => pooled.scale(1.0 / count as f32),
+                })
+            }
+            "BatchNormalization" => Slot::Device(self.batch_norm()?),
+            "ReduceSum" | "ReduceMean" | "ReduceMax" => self.reduce()?,
+            _ => return Err(self.unmapped("unknown operator")),
+        };
+
+        Ok(slot)
+    }
+
+    /// Name to report the node under, its first output when it has none.
+    fn label(&self) -> &str {
+        match self.node.name.is_empty() {
+            true => self.node.outputs.first().map_or("", String::as_str),
+            false => &self.node.name,
+        }
+    }
+
+    fn unmapped(&self, reason: &str) -> Error {
+        Error::Unmapped(vec![format!(
+            "{} in node {} ({})",
+            self.node.op,
+            self.label(),
+            reason
+        )])
+    }
+
+    fn invalid(&self, reason: &str) -> Error {
+        Error::Graph(format!("{} node {} {}", self.node.op, self.label(), reason))
+    }
+
+    fn optional(&self, index: usize) -> Result<Option<&Slot>, Error> {
+        let Some(name) = self.node.inputs.get(index).filter(|name| !name.is_empty()) else {
+            return Ok(None);
+        };
+
+        self.values
+            .get(name)
+            .map(Some)
+            .ok_or_else(|| Error::Graph(format!("value {} is used before being set", name)))
+    }
+
+    fn input(&self, index: usize) -> Result<&Slot, Error> {
+        self.optional(index)?
+            .ok_or_else(|| self.invalid(&format!("lacks input {}", index)))
+    }
+
+    fn tensor(&self, index: usize) -> Result<Param, Error> {
+        Ok(self.input(index)?.tensor())
+    }
+
+    /// Input that has to be known while building the graph, such as a target shape.
+    fn host(&self, index: usize) -> Result<&Constant, Error> {
+        match self.input(index)? {
+            Slot::Host(constant) => Ok(constant),
+            Slot::Device(_) => Err(self.unmapped(&format!("input {} isn't a constant", index))),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.node.attributes.get(name)
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        match self.attribute(name) {
+            Some(Attribute::Int(value)) => *value,
+            _ => default,
+        }
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        match self.attribute(name) {
+            Some(Attribute::Float(value)) => *value,
+            _ => default,
+        }
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        match self.attribute(name) {
+            Some(Attribute::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn ints(&self, name: &str) -> Option<&[i64]> {
+        match self.attribute(name) {
+            Some(Attribute::Ints(values)) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// `axes` as the attribute it used to be or the input it became, depending on the opset.
+    fn axes(&self) -> Result<Option<Vec<i64>>, Error> {
+        if let Some(axes) = self.ints("axes") {
+            return Ok(Some(axes.to_vec()));
+        }
+
+        match self.optional(1)? {
+            Some(Slot::Host(constant)) => Ok(Some(constant.ints())),
+            Some(Slot::Device(_)) => Err(self.unmapped("axes aren't constant")),
+            None => Ok(None),
+        }
+    }
+
+    /// Axis counted from the end when negative, checked against `rank`.
+    fn axis(&self, axis: i64, rank: usize) -> Result<usize, Error> {
+        let rank = rank as i64;
+        match axis {
+            axis if (0..rank).contains(&axis) => Ok(axis as usize),
+            axis if (-rank..0).contains(&axis) => Ok((axis + rank) as usize),
+            _ => Err(self.invalid(&format!("has axis {} out of range", axis))),
+        }
+    }
+
+    fn reshape(&self, dims: Vec<u32>) -> Result<Slot, Error> {
+        let input = self.input(0)?;
+        let size = |dims: &[u32]| dims.iter().map(|dim| *dim as u64).product::<u64>();
+        if size(&dims) != size(&input.dims()) {
+            return Err(self.invalid(&format!("reshapes {:?} to {:?}", input.dims(), dims)));
+        }
+
+        Ok(input.reshape(dims))
+    }
+
+    /// Both operands repeated up to their common shape.
+    fn broadcast(&self, lhs: Param, rhs: Param) -> Result<(Param, Param), Error> {
+        let (ldims, rdims) = (lhs.order.pull(), rhs.order.pull());
+        let dims = broadcast_dims(&ldims, &rdims).ok_or_else(|| {
+            self.invalid(&format!("can't broadcast {:?} against {:?}", ldims, rdims))
+        })?;
+
+        let expand = |tensor: Param, own: Vec<u32>| match own == dims {
+            true => tensor,
+            false => tensor.broadcast(TensorOrder::new(dims.clone())),
+        };
+        Ok((expand(lhs, ldims), expand(rhs, rdims)))
+    }
+
+    fn constant(&self) -> Result<Constant, Error> {
+        let scalar = |data| Constant {
+            dims: Vec::new(),
+            data,
+        };
+        let list = |data: Data, len: usize| Constant {
+            dims: vec![len as u32],
+            data,
+        };
+
+        match [
+            "value",
+            "value_float",
+            "value_int",
+            "value_floats",
+            "value_ints",
+        ]
+        .iter()
+        .find_map(|name| self.attribute(name))
+        {
+            Some(Attribute::Tensor(constant)) => Ok(constant.clone()),
+            Some(Attribute::Float(value)) => Ok(scalar(Data::Floats(vec![*value]))),
+            Some(Attribute::Int(value)) => Ok(scalar(Data::Ints(vec![*value]))),
+            Some(Attribute::Floats(values)) => Ok(list(Data::Floats(values.clone()), values.len())),
+            Some(Attribute::Ints(values)) => Ok(list(Data::Ints(values.clone()), values.len())),
+            _ => Err(self.unmapped("value isn't a dense tensor or number")),
+        }
+    }
+
+    /// Matrix product with numpy semantics: vectors get a unit axis for the product, then lose
+    /// it, and leading axes broadcast.
+    fn matmul(&self, lhs: Param, rhs: Param) -> Result<Param, Error> {
+        let (mut ldims, mut rdims) = (lhs.order.pull(), rhs.order.pull());
+        let (row, column) = (ldims.len() == 1, rdims.len() == 1);
+        if row {
+            ldims.insert(0, 1);
+        }
+        if column {
+            rdims.push(1);
+        }
+
+        let (lrank, rrank) = (ldims.len(), rdims.len());
+        if lrank < 2 || rrank < 2 {
+            return Err(self.invalid("multiplies a scalar"));
+        }
+        if ldims[lrank - 1] != rdims[rrank - 2] {
+            return Err(self.invalid(&format!(
+                "multiplies {:?} by {:?}",
+                lhs.order.pull(),
+                rhs.order.pull()
+            )));
+        }
+
+        let out = if rrank == 2 && lrank > 2 {
+            // Batched inputs against a single matrix, typically weights, run as one product
+            // rather than repeating the matrix across the batch.
+            let rows: u32 = ldims[..lrank - 1].iter().product();
+            let flat = lhs.reshape(TensorOrder::new(vec![rows, ldims[lrank - 1]]));
+
+            let mut dims = ldims.clone();
+            dims[lrank - 1] = rdims[1];
+            flat.matmul(&rhs.reshape(order(&rdims)))
+                .reshape(TensorOrder::new(dims))
+        } else {
+            let batch = broadcast_dims(&ldims[..lrank - 2], &rdims[..rrank - 2])
+                .ok_or_else(|| self.invalid("has batch axes that don't broadcast"))?;
+            let expand = |tensor: Param, dims: &[u32]| {
+                let mut full = batch.clone();
+                full.extend_from_slice(&dims[dims.len() - 2..]);
+                match full == dims {
+                    true => tensor.reshape(order(dims)),
+                    false => tensor.broadcast(TensorOrder::new(full)),
+                }
+            };
+
+            expand(lhs, &ldims).matmul(&expand(rhs, &rdims))
+        };
+
+        let mut dims = out.order.pull();
+        if column {
+            dims.pop();
+        }
+        if row {
+            dims.remove(dims.len() - if column { 1 } else { 2 });
+        }
+
+        Ok(match dims == out.order.pull() {
+            true => out,
+            false => out.reshape(order(&dims)),
+        })
+    }
+
+    /// `alpha * A * B + beta * C`, with either factor optionally transposed.
+    fn gemm(&self) -> Result<Param, Error> {
+        let (mut lhs, mut rhs) = (self.tensor(0)?, self.tensor(1)?);
+        if lhs.order.pull().len() != 2 || rhs.order.pull().len() != 2 {
+            return Err(self.invalid("multiplies tensors that aren't matrices"));
+        }
+        if self.int("transA", 0) != 0 {
+            lhs = lhs.transpose();
+        }
+        if self.int("transB", 0) != 0 {
+            rhs = rhs.transpose();
+        }
+
+        let mut out = self.matmul(lhs, rhs)?;
+        let alpha = self.float("alpha", 1.0);
+        if alpha != 1.0 {
+            out = out.scale(alpha);
+        }
+
+        if let Some(bias) = self.optional(2)? {
+            let beta = self.float("beta", 1.0);
+            let bias = match beta {
+                beta if beta != 1.0 => bias.tensor().scale(beta),
+                _ => bias.tensor(),
+            };
+            let (product, bias) = self.broadcast(out, bias)?;
+            out = &product + &bias;
+        }
+
+        Ok(out)
+    }
+
+    fn softmax(&self, log: bool) -> Result<Param, Error> {
+        let tensor = self.tensor(0)?;
+        let dims = tensor.order.pull();
+        let apply = |tensor: &Param, axis: usize| match log {
+            true => tensor.log_softmax(axis),
+            false => tensor.softmax(axis),
+        };
+
+        if self.opset >= 13 {
+            let axis = self.axis(self.int("axis", -1), dims.len())?;
+            return Ok(apply(&tensor, axis));
+        }
+
+        // Earlier opsets normalize over every axis from `axis` on, as a single one.
+        let axis = self.axis(self.int("axis", 1), dims.len())?;
+        let flat = tensor.reshape(TensorOrder::new(vec![
+            dims[..axis].iter().product(),
+            dims[axis..].iter().product(),
+        ]));
+        Ok(apply(&flat, 1).reshape(TensorOrder::new(dims)))
+    }
+
+    fn reduce(&self) -> Result<Slot, Error> {
+        let tensor = self.tensor(0)?;
+        let dims = tensor.order.pull();
+        let axes = match self.axes()? {
+            Some(axes) if !axes.is_empty() => axes
+                .iter()
+                .map(|axis| self.axis(*axis, dims.len()))
+                .collect::<Result<Vec<_>, _>>()?,
+            _ if self.int("noop_with_empty_axes", 0) != 0 => {
+                return Ok(Slot::Device(tensor));
+            }
+            _ => (0..dims.len()).collect(),
+        };
+
+        let reduced = axes
+            .iter()
+            .fold(tensor, |acc, axis| match self.node.op.as_str() {
+                "ReduceMax" => acc.max(*axis),
+                _ => acc.sum(*axis),
+            });
+        let reduced = match self.node.op.as_str() {
+            "ReduceMean" => {
+                let count: u32 = axes.iter().map(|axis| dims[*axis]).product();
+                reduced.scale(1.0 / count as f32)
+            }
+            _ => reduced,
+        };
+
+        let slot = Slot::Device(reduced);
+        Ok(match self.int("keepdims", 1) {
+            0 => slot.reshape(
+                (0..dims.len())
+                    .filter(|axis| !axes.contains(axis))
+                    .map(|axis| dims[axis])
+                    .collect(),
+            ),
+            _ => slot,
+        })
+    }
+
+    /// Concatenation of constants, as found in shape computations.
+    fn concat(&self) -> Result<Slot, Error> {
+        let parts = (0..self.node.inputs.len())
+            .map(|index| self.host(index))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| self.unmapped("concatenation of tensors that aren't constant"))?;
+        let first = parts.first().ok_or_else(|| self.invalid("has no inputs"))?;
+
+        let rank = first.dims.len();
+        let axis = self.axis(self.int("axis", 0), rank)?;
+        let same = parts.iter().all(|part| {
+            part.dims.len() == rank && (0..rank).all(|i| i == axis || part.dims[i] == first.dims[i])
+        });
+        if !same {
+            return Err(self.invalid("concatenates mismatched shapes"));
+        }
+
+        let mut dims = first.dims.clone();
+        dims[axis] = parts.iter().map(|part| part.dims[axis]).sum();
+        let spans: Vec<usize> = parts
+            .iter()
+            .map(|part| part.dims[axis..].iter().map(|dim| *dim as usize).product())
+            .collect();
+        let data = match parts.iter().all(|part| matches!(part.data, Data::Ints(_))) {
+            true => Data::Ints(join(
+                &parts.iter().map(|part| part.ints()).collect::<Vec<_>>(),
+                &spans,
+            )),
+            false => Data::Floats(join(
+                &parts.iter().map(|part| part.floats()).collect::<Vec<_>>(),
+                &spans,
+            )),
+        };
+
+        Ok(Slot::Host(Constant { dims, data }))
+    }
+
+    /// Slices along `axis` picked by index: embedding lookups on the device, or shape
+    /// computations on the host.
+    fn gather(&self) -> Result<Slot, Error> {
+        let data = self.input(0)?;
+        let dims = data.dims();
+        let axis = self.axis(self.int("axis", 0), dims.len())?;
+
+        if let (Slot::Host(table), Ok(indices)) = (data, self.host(1)) {
+            let count = dims[axis] as i64;
+            let picks = indices
+                .ints()
+                .iter()
+                .map(|index| match *index {
+                    index if (0..count).contains(&index) => Ok(index as usize),
+                    index if (-count..0).contains(&index) => Ok((index + count) as usize),
+                    _ => Err(self.invalid("gathers an index out of range")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let inner: usize = dims[axis + 1..].iter().map(|dim| *dim as usize).product();
+            let mut out = dims[..axis].to_vec();
+            out.extend_from_slice(&indices.dims);
+            out.extend_from_slice(&dims[axis + 1..]);
+            let data = match &table.data {
+                Data::Ints(values) => Data::Ints(pick(values, count as usize, &picks, inner)),
+                Data::Floats(values) => Data::Floats(pick(values, count as usize, &picks, inner)),
+            };
+            return Ok(Slot::Host(Constant { dims: out, data }));
+        }
+
+        if axis != 0 {
+            return Err(self.unmapped("gather along an axis other than the first"));
+        }
+
+        // Indices are passed as floats; negative ones are resolved by the kernel, as computed
+        // indices can only be seen there.
+        let indices = self.input(1)?;
+        let indices = match indices {
+            Slot::Host(constant) => {
+                let count = dims[0] as i64;
+                let values = constant
+                    .ints()
+                    .iter()
+                    .map(|index| match *index {
+                        index if (-count..count).contains(&index) => Ok(index as f32),
+                        _ => Err(self.invalid("gathers an index out of range")),
+                    })
+                    .collect::<Result<_, _>>()?;
+                Tensor::from_vec(values, order(&constant.dims))
+            }
+            Slot::Device(tensor) => tensor.clone(),
+        };
+
+        Ok(Slot::Device(data.tensor().gather(&indices)))
+    }
+
+    /// Symmetric padding out of `pads`, or of `auto_pad` for a window of `kernel` moving by
+    /// `stride` over `extent`.
+    fn padding(&self, kernel: &[u32], stride: &[u32], extent: &[u32]) -> Result<Vec<u32>, Error> {
+        let rank = kernel.len();
+        let pads: Vec<i64> = match self.string("auto_pad").unwrap_or("NOTSET") {
+            "NOTSET" => self
+                .ints("pads")
+                .map_or_else(|| vec![0; 2 * rank], <[i64]>::to_vec),
+            "VALID" => vec![0; 2 * rank],
+            "SAME_UPPER" | "SAME_LOWER" => {
+                let total = (0..rank).map(|axis| {
+                    let out = extent[axis].div_ceil(stride[axis]);
+                    ((out - 1) * stride[axis] + kernel[axis]).saturating_sub(extent[axis]) as i64
+                });
+                let total: Vec<i64> = total.collect();
+                total
+                    .iter()
+                    .map(|pad| pad / 2)
+                    .chain(total.iter().map(|pad| pad - pad / 2))
+                    .collect()
+            }
+            _ => return Err(self.invalid("has an unknown auto_pad")),
+        };
+
+        if pads.len() != 2 * rank || pads[..rank] != pads[rank..] {
+            return Err(self.unmapped("asymmetric padding"));
+        }
+        pads[..rank]
+            .iter()
+            .map(|pad| u32::try_from(*pad).map_err(|_| self.invalid("has negative padding")))
+            .collect()
+    }
+
+    /// Window over the spatial axes of `dims` out of the node's attributes, 1-D windows being
+    /// run as 2-D ones of unit height.
+    fn window(&self, dims: &[u32], kernel: &[u32]) -> Result<Window, Error> {
+        let rank = kernel.len();
+        if !(1..=2).contains(&rank) || dims.len() != rank + 2 {
+            return Err(self.unmapped("window over other than one or two spatial axes"));
+        }
+        if self
+            .ints("dilations")
+            .is_some_and(|d| d.iter().any(|d| *d != 1))
+        {
+            return Err(self.unmapped("dilated window"));
+        }
+        if self.int("ceil_mode", 0) != 0 {
+            return Err(self.unmapped("ceil_mode"));
+        }
+
+        let stride = match self.ints("strides") {
+            Some(strides) => strides
+                .iter()
+                .map(|stride| u32::try_from(*stride).ok().filter(|stride| *stride > 0))
+                .collect::<Option<Vec<_>>>()
+                .filter(|strides| strides.len() == rank)
+                .ok_or_else(|| self.invalid("has invalid strides"))?,
+            None => vec![1; rank],
+        };
+        let padding = self.padding(kernel, &stride, &dims[2..])?;
+
+        let fits = (0..rank).all(|axis| dims[2 + axis] + 2 * padding[axis] >= kernel[axis]);
+        if !fits {
+            return Err(self.invalid("has a window larger than its input"));
+        }
+
+        let pair = |values: &[u32], unit: u32| match values {
+            [x] => (unit, *x),
+            [y, x] => (*y, *x),
+            _ => unreachable!(),
+        };
+        Ok(Window {
+            kernel: pair(kernel, 1),
+            stride: pair(&stride, 1),
+            padding: pair(&padding, 0),
+        })
+    }
+
+    /// Input shaped as an image, 1-D inputs getting a unit height.
+    fn image(tensor: &Param) -> Param {
+        let dims = tensor.order.pull();
+        match dims.len() {
+            3 => tensor.reshape(TensorOrder::new(vec![dims[0], dims[1], 1, dims[2]])),
+            _ => tensor.clone(),
+        }
+    }
+
+    /// Image output shaped back after `image`.
+    fn unimage(tensor: Param, rank: usize) -> Param {
+        let dims = tensor.order.pull();
+        match rank {
+            3 => tensor.reshape(TensorOrder::new(vec![dims[0], dims[1], dims[3]])),
+            _ => tensor,
+        }
+    }
+
+    fn conv(&self) -> Result<Param, Error> {
+        let (input, weight) = (self.tensor(0)?, self.tensor(1)?);
+        let bias = self.optional(2)?.map(Slot::tensor);
+        let (dims, filter) = (input.order.pull(), weight.order.pull());
+
+        if self.int("group", 1) != 1 {
+            return Err(self.unmapped("grouped convolution"));
+        }
+        if filter.len() != dims.len() || filter.len() < 3 || filter[1] != dims[1] {
+            return Err(self.invalid(&format!("convolves {:?} with filters {:?}", dims, filter)));
+        }
+
+        let window = self.window(&dims, &filter[2..])?;
+        let mut conv = Conv2d::from_weights(
+            Self::image(&weight),
+            bias,
+            ConvOpts {
+                stride: window.stride,
+                padding: window.padding,
+                bias: false,
+            },
+        );
+
+        Ok(Self::unimage(
+            conv.forward(&Self::image(&input))?,
+            dims.len(),
+        ))
+    }
+
+    /// Pooling over the patches an unfold lays out as rows, channel by channel.
+    fn pool(&self, max: bool) -> Result<Param, Error> {
+        let input = self.tensor(0)?;
+        let dims = input.order.pull();
+        if self
+            .node
+            .outputs
+            .get(1)
+            .is_some_and(|name| !name.is_empty())
+        {
+            return Err(self.unmapped("pooling indices"));
+        }
+
+        let kernel = self
+            .ints("kernel_shape")
+            .ok_or_else(|| self.invalid("has no kernel_shape"))?
+            .iter()
+            .map(|dim| u32::try_from(*dim).map_err(|_| self.invalid("has an invalid kernel")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let window = self.window(&dims, &kernel)?;
+
+        // Unfolding pads with zeros, which would win over negative maxima and, unless they are
+        // meant to, count towards averages.
+        let padded = window.padding != (0, 0);
+        if padded && (max || self.int("count_include_pad", 0) == 0) {
+            return Err(self.unmapped("padding excluded from the pooled values"));
+        }
+
+        let image = Self::image(&input);
+        let idims = image.order.pull();
+        let (batch, channels) = (idims[0], idims[1]);
+        let (height, width) = window.extent((idims[2], idims[3]));
+        let size = window.kernel.0 * window.kernel.1;
+
+        let patches = image.unfold(window).reshape(TensorOrder::new(vec![
+            batch * height * width,
+            channels,
+            size,
+        ]));
+        let pooled = match max {
+            true => patches.max(2),
+            false => patches.sum(2).scale(1.0 / size as f32),
+        };
+
+        let out = pooled
+            .reshape(TensorOrder::new(vec![batch, height * width, channels]))
+            .transpose()
+            .reshape(TensorOrder::new(vec![batch, channels, height, width]));
+        Ok(Self::unimage(out, dims.len()))
+    }
+
+    /// Normalization by running statistics, as in inference.
+    fn batch_norm(&self) -> Result<Param, Error> {
+        if self.int("training_mode", 0) != 0 {
+            return Err(self.unmapped("training mode"));
+        }
+
+        let input = self.tensor(0)?;
+        let order = input.order.clone();
+        let dims = order.pull();
+        if dims.len() < 2 {
+            return Err(self.invalid("normalizes an input without channels"));
+        }
+
+        let mut unit = vec![1; dims.len()];
+        unit[1] = dims[1];
+        let spread = |tensor: Param| -> Result<Param, Error> {
+            if tensor.order.size() != dims[1] {
+                return Err(self.invalid("has statistics that don't match its channels"));
+            }
+            Ok(tensor
+                .reshape(TensorOrder::new(unit.clone()))
+                .broadcast(order.clone()))
+        };
+
+        let eps = self.float("epsilon", 1e-5);
+        let std = spread(self.tensor(4)?.offset(eps).sqrt())?;
+        let normalized = &(&input - &spread(self.tensor(3)?)?) / &std;
+
+        Ok(&(&normalized * &spread(self.tensor(1)?)?) + &spread(self.tensor(2)?)?)
+    }
+}
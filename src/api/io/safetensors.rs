@@ -0,0 +1,354 @@
+// safetensors: an 8-byte little-endian header length, a JSON header mapping every tensor name
+// to its dtype, shape and byte range, then the raw little-endian data of every tensor.
+
+use std::{fs::File, io::Write, path::Path, sync::Arc};
+
+use crate::{
+    api::{nn::Module, Tensor, TensorOrder},
+    core::{Bundle, Contents, Error, Instance, Operand},
+    types::{Component, Packet, SupportedPacket},
+};
+
+use super::{
+    json::{self, Json},
+    mmap::{Mmap, Region},
+    Dtype,
+};
+
+/// Headers above this size are rejected rather than read into memory.
+const MAX_HEADER: u64 = 100 << 20;
+
+impl Dtype {
+    fn name(&self) -> &'static str {
+        match self {
+            Dtype::Bool => "BOOL",
+            Dtype::U8 => "U8",
+            Dtype::I8 => "I8",
+            Dtype::U16 => "U16",
+            Dtype::I16 => "I16",
+            Dtype::F16 => "F16",
+            Dtype::BF16 => "BF16",
+            Dtype::U32 => "U32",
+            Dtype::I32 => "I32",
+            Dtype::F32 => "F32",
+            Dtype::U64 => "U64",
+            Dtype::I64 => "I64",
+            Dtype::F64 => "F64",
+            Dtype::F8E4M3 => "F8_E4M3",
+            Dtype::F8E5M2 => "F8_E5M2",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        let dtype = match name {
+            "BOOL" => Dtype::Bool,
+            "U8" => Dtype::U8,
+            "I8" => Dtype::I8,
+            "U16" => Dtype::U16,
+            "I16" => Dtype::I16,
+            "F16" => Dtype::F16,
+            "BF16" => Dtype::BF16,
+            "U32" => Dtype::U32,
+            "I32" => Dtype::I32,
+            "F32" => Dtype::F32,
+            "U64" => Dtype::U64,
+            "I64" => Dtype::I64,
+            "F64" => Dtype::F64,
+            "F8_E4M3" => Dtype::F8E4M3,
+            "F8_E5M2" => Dtype::F8E5M2,
+            _ => return None,
+        };
+
+        Some(dtype)
+    }
+}
+
+/// Tensor stored in a safetensors file, as described by its header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub dtype: Dtype,
+    pub shape: Vec<u32>,
+    /// Byte range within the data section.
+    pub offsets: (u64, u64),
+}
+
+/// Open safetensors file, memory-mapped. Only the header is parsed up front; tensors are
+/// uploaded straight from the mapping, and pages the device never asks for are never read.
+pub struct Safetensors {
+    map: Arc<Mmap>,
+    /// Start of the data section within the file.
+    data: u64,
+    entries: Vec<(String, Entry)>,
+    pub metadata: Vec<(String, String)>,
+}
+
+impl Safetensors {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let map = Mmap::map(&File::open(path)?)?;
+        let size = map.len() as u64;
+
+        let word = map
+            .get(..8)
+            .ok_or_else(|| Error::Format("safetensors file too short".into()))?;
+        let length = u64::from_le_bytes(word.try_into().unwrap());
+        if length > MAX_HEADER || 8 + length > size {
+            return Err(Error::Format(
+                "safetensors header length out of range".into(),
+            ));
+        }
+
+        let header = std::str::from_utf8(&map[8..8 + length as usize])
+            .map_err(|_| Error::Format("safetensors header is not utf-8".into()))?;
+
+        let Json::Object(members) = Json::parse(header)? else {
+            return Err(Error::Format("safetensors header is not an object".into()));
+        };
+
+        let data = 8 + length;
+        let mut entries = Vec::new();
+        let mut metadata = Vec::new();
+
+        for (name, value) in members {
+            if name == "__metadata__" {
+                if let Json::Object(pairs) = value {
+                    metadata.extend(
+                        pairs
+                            .into_iter()
+                            .filter_map(|(key, value)| Some((key, value.as_str()?.to_string()))),
+                    );
+                }
+                continue;
+            }
+
+            let entry = Self::describe(&name, &value)?;
+            if data
+                .checked_add(entry.offsets.1)
+                .is_none_or(|end| end > size)
+            {
+                return Err(Error::Format(format!(
+                    "tensor {} runs past the end of file",
+                    name
+                )));
+            }
+            entries.push((name, entry));
+        }
+
+        Ok(Self {
+            map: Arc::new(map),
+            data,
+            entries,
+            metadata,
+        })
+    }
+
+    fn describe(name: &str, value: &Json) -> Result<Entry, Error> {
+        let malformed = || Error::Format(format!("malformed header entry for tensor {}", name));
+
+        let dtype = value
+            .get("dtype")
+            .and_then(Json::as_str)
+            .ok_or_else(malformed)?;
+        let dtype = Dtype::parse(dtype)
+            .ok_or_else(|| Error::Format(format!("unknown dtype {} of tensor {}", dtype, name)))?;
+
+        let shape = value
+            .get("shape")
+            .and_then(Json::as_array)
+            .ok_or_else(malformed)?
+            .iter()
+            .map(|dim| dim.as_u64().and_then(|dim| u32::try_from(dim).ok()))
+            .collect::<Option<Vec<u32>>>()
+            .ok_or_else(malformed)?;
+
+        let offsets = match value.get("data_offsets").and_then(Json::as_array) {
+            Some([start, end]) => (
+                start.as_u64().ok_or_else(malformed)?,
+                end.as_u64().ok_or_else(malformed)?,
+            ),
+            _ => return Err(malformed()),
+        };
+
+        let length = shape.iter().try_fold(dtype.size() as u64, |length, dim| {
+            length.checked_mul(*dim as u64)
+        });
+        if offsets.0 > offsets.1 || Some(offsets.1 - offsets.0) != length {
+            return Err(Error::Format(format!(
+                "byte range of tensor {} doesn't match its shape",
+                name
+            )));
+        }
+
+        Ok(Entry {
+            dtype,
+            shape,
+            offsets,
+        })
+    }
+
+    /// Names of every tensor, in header order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find_map(|(key, entry)| (key == name).then_some(entry))
+    }
+
+    /// Read the tensor called `name`, which must be stored with the dtype `U` packs.
+    pub fn tensor<T, U>(&self, name: &str) -> Result<Tensor<'static, T, U>, Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let (order, contents) = self.read::<U>(name)?;
+        Ok(Tensor::from_bytes(contents, order))
+    }
+
+    /// Overwrite every parameter and buffer of `module` with the tensor stored under its name.
+    /// Tensors missing from the file, or stored with another shape, are errors.
+    pub fn load_module(&self, module: &impl Module) -> Result<(), Error> {
+        for (name, param) in module
+            .named_parameters()
+            .into_iter()
+            .chain(module.named_buffers())
+        {
+            let (order, contents) = self.read::<f32>(&name)?;
+            if order != param.order {
+                return Err(Error::Format(format!(
+                    "tensor {} has shape {:?}, expected {:?}",
+                    name,
+                    order.pull(),
+                    param.order.pull()
+                )));
+            }
+            param.restage(contents);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn read<U>(&self, name: &str) -> Result<(TensorOrder, Contents), Error>
+    where
+        Packet<U>: SupportedPacket,
+    {
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| Error::Format(format!("no tensor named {}", name)))?
+            .clone();
+
+        if Packet::<U>::DTYPE != Some(entry.dtype) {
+            return Err(Error::Format(format!(
+                "tensor {} is stored as {}, not {}",
+                name,
+                entry.dtype.name(),
+                Packet::<U>::DTYPE.map_or("an unsupported dtype", |dtype| dtype.name())
+            )));
+        }
+        if !Packet::<U>::SHADABLE {
+            return Err(Error::Format(format!(
+                "tensor {} is stored as {}, which shaders can't read",
+                name,
+                entry.dtype.name()
+            )));
+        }
+
+        let region = Region {
+            map: self.map.clone(),
+            range: (self.data + entry.offsets.0) as usize..(self.data + entry.offsets.1) as usize,
+        };
+
+        Ok((TensorOrder::new(entry.shape), Contents::new(region)))
+    }
+}
+
+/// Write `tensors` under their names, resolving them on `instance` first.
+pub fn save<T, U>(
+    path: impl AsRef<Path>,
+    instance: &Instance,
+    tensors: &[(&str, &Tensor<'_, T, U>)],
+) -> Result<(), Error>
+where
+    T: Component,
+    Packet<U>: SupportedPacket,
+    Bundle<U>: Operand + 'static,
+{
+    let dtype = Packet::<U>::DTYPE.ok_or(Error::Unsupported("dtype without a safetensors name"))?;
+    let entries = tensors
+        .iter()
+        .map(|(name, tensor)| {
+            (
+                name.to_string(),
+                dtype,
+                tensor.order.pull(),
+                tensor.operand(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    write(path.as_ref(), instance, entries)
+}
+
+/// Write every parameter and buffer of `module` under its name.
+pub fn save_module(
+    path: impl AsRef<Path>,
+    instance: &Instance,
+    module: &impl Module,
+) -> Result<(), Error> {
+    let entries = module
+        .named_parameters()
+        .into_iter()
+        .chain(module.named_buffers())
+        .map(|(name, param)| (name, Dtype::F32, param.order.pull(), param.operand()))
+        .collect::<Vec<_>>();
+
+    write(path.as_ref(), instance, entries)
+}
+
+/// A tensor to write: name, stored dtype, shape and contents.
+type Record = (String, Dtype, Vec<u32>, Arc<dyn Operand>);
+
+fn write(path: &Path, instance: &Instance, entries: Vec<Record>) -> Result<(), Error> {
+    let mut header = String::from("{");
+    let mut offset = 0u64;
+
+    for (index, (name, dtype, shape, operand)) in entries.iter().enumerate() {
+        if entries[..index].iter().any(|(other, ..)| other == name) {
+            return Err(Error::Format(format!("tensor name {} used twice", name)));
+        }
+
+        let size = operand.dims().size() as u64 * operand.stride();
+        let shape = shape.iter().map(u32::to_string).collect::<Vec<_>>();
+        if index > 0 {
+            header.push(',');
+        }
+        header.push_str(&format!(
+            "{}:{{\"dtype\":\"{}\",\"shape\":[{}],\"data_offsets\":[{},{}]}}",
+            json::quote(name),
+            dtype.name(),
+            shape.join(","),
+            offset,
+            offset + size
+        ));
+        offset += size;
+    }
+    header.push('}');
+
+    // Padding the header keeps the data section 8-byte aligned, as readers expect.
+    while header.len() % 8 != 0 {
+        header.push(' ');
+    }
+
+    super::replace(path, |file| {
+        file.write_all(&(header.len() as u64).to_le_bytes())?;
+        file.write_all(header.as_bytes())?;
+
+        for (_, _, _, operand) in entries.iter() {
+            file.write_all(&instance.read(operand)?)?;
+        }
+
+        Ok(())
+    })
+}
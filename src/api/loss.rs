@@ -0,0 +1,80 @@
+// Loss functions over `nn::Param`s, built on tensor operations so they can be backpropagated.
+
+use super::{nn::Param, Tensor, TensorOrder};
+
+/// How the elementwise losses collapse into the returned tensor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Reduction {
+    #[default]
+    Mean,
+    Sum,
+    /// Keep one loss per element, or per sample for class-wise losses.
+    None,
+}
+
+impl Reduction {
+    fn apply(&self, loss: Param) -> Param {
+        if *self == Reduction::None {
+            return loss;
+        }
+
+        let size = loss.order.size() as f32;
+        let rank = loss.order.pull().len();
+        let total = (0..rank)
+            .fold(loss, |acc, axis| acc.sum(axis))
+            .reshape(TensorOrder::new(vec![1]));
+
+        match self {
+            Reduction::Mean => total.scale(1.0 / size),
+            _ => total,
+        }
+    }
+}
+
+/// Mean squared error.
+pub fn mse(input: &Param, target: &Param, reduction: Reduction) -> Param {
+    let diff = input - target;
+    reduction.apply(&diff * &diff)
+}
+
+/// Mean absolute error.
+pub fn l1(input: &Param, target: &Param, reduction: Reduction) -> Param {
+    reduction.apply((input - target).abs())
+}
+
+/// Negative log likelihood of `[batch, classes]` log-probabilities, given the class index of
+/// every sample as a float.
+pub fn nll(input: &Param, target: &Param, reduction: Reduction) -> Param {
+    let dims = input.order.pull();
+    let (batch, classes) = (dims[0], dims[1]);
+
+    // Flat offset of every picked class: `sample * classes + target`.
+    let rows = Tensor::from_vec(
+        (0..batch).map(|sample| (sample * classes) as f32).collect(),
+        TensorOrder::new(vec![batch]),
+    );
+    let picked = input
+        .reshape(TensorOrder::new(vec![batch * classes, 1]))
+        .gather(&(&rows + target))
+        .reshape(TensorOrder::new(vec![batch]));
+
+    reduction.apply(picked.neg())
+}
+
+/// Cross-entropy of `[batch, classes]` unnormalized logits against class indices.
+pub fn cross_entropy(input: &Param, target: &Param, reduction: Reduction) -> Param {
+    nll(&input.log_softmax(1), target, reduction)
+}
+
+/// Binary cross-entropy of logits against probabilities, in its overflow free form
+/// `max(x, 0) - x * y + log(1 + exp(-|x|))`.
+pub fn binary_cross_entropy_with_logits(
+    input: &Param,
+    target: &Param,
+    reduction: Reduction,
+) -> Param {
+    let softplus = input.abs().neg().exp().offset(1.0).log();
+    let loss = &(&input.relu() - &(input * target)) + &softplus;
+
+    reduction.apply(loss)
+}
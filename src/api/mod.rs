@@ -1,3 +1,8 @@
+pub mod io;
+pub mod loss;
+pub mod nn;
+pub mod optim;
+pub mod quant;
 pub mod tensor;
 
 pub use tensor::{Tensor, TensorOrder};
@@ -0,0 +1,512 @@
+// Neural network layers, composed out of `Tensor` operations so gradients come for free.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{
+    core::{
+        ops::{Scores, Window},
+        Error, Instance,
+    },
+    types::{Integer, Packet, SupportedPacket},
+};
+
+use super::{
+    quant::{QuantOpts, Quantized},
+    Tensor, TensorOrder,
+};
+
+/// Tensor type every layer operates on.
+pub type Param = Tensor<'static, f32, f32>;
+
+/// Building block of every model.
+pub trait Module {
+    /// Fails when a layer has to resolve something along the way, as `BatchNorm` does with its
+    /// running statistics while training.
+    fn forward(&mut self, input: &Param) -> Result<Param, Error>;
+
+    /// Learnable parameters, named after the field holding them.
+    fn named_parameters(&self) -> Vec<(String, &Param)>;
+
+    /// State kept next to the parameters without being learned, e.g. running statistics;
+    /// saved and loaded along with them.
+    fn named_buffers(&self) -> Vec<(String, &Param)> {
+        Vec::new()
+    }
+
+    fn parameters(&self) -> Vec<&Param> {
+        self.named_parameters()
+            .into_iter()
+            .map(|(_, param)| param)
+            .collect()
+    }
+
+    /// Switch between training and evaluation behaviour, for layers that differ.
+    fn train(&mut self, _mode: bool) {}
+}
+
+static SEED: AtomicU64 = AtomicU64::new(0x5eed_2e1c_07a0_0000);
+
+/// Reseed the generator used to initialize parameters and draw dropout masks.
+pub fn seed(seed: u64) {
+    SEED.store(seed, Ordering::Relaxed);
+}
+
+/// Next value of a splitmix64 sequence shared by every layer, mapped onto `[0, 1)`.
+fn sample() -> f32 {
+    let mut z = SEED
+        .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+        .wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Parameter drawn uniformly from `[-bound, bound)`.
+fn uniform(dims: Vec<u32>, bound: f32) -> Param {
+    let order = TensorOrder::new(dims);
+    let values = (0..order.size())
+        .map(|_| (sample() * 2.0 - 1.0) * bound)
+        .collect();
+
+    Tensor::from_vec(values, order).requires_grad()
+}
+
+/// Parameter with every element set to `value`, uploaded from the host so it can be tracked.
+fn constant(dims: Vec<u32>, value: f32) -> Param {
+    let order = TensorOrder::new(dims);
+    let values = vec![value; order.size() as usize];
+
+    Tensor::from_vec(values, order).requires_grad()
+}
+
+/// Fully connected layer, `y = x Wᵀ + b` over `[batch, in]` inputs.
+pub struct Linear {
+    pub weight: Param,
+    pub bias: Option<Param>,
+}
+
+impl Linear {
+    pub fn new(input: u32, output: u32, bias: bool) -> Self {
+        let bound = 1.0 / (input as f32).sqrt();
+
+        Self {
+            weight: uniform(vec![output, input], bound),
+            bias: bias.then(|| uniform(vec![output], bound)),
+        }
+    }
+}
+
+impl Module for Linear {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let output = input.matmul(&self.weight.transpose());
+
+        Ok(match &self.bias {
+            Some(bias) => &output + &bias.broadcast(output.order.clone()),
+            None => output,
+        })
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        let mut params = vec![("weight".to_string(), &self.weight)];
+        if let Some(bias) = &self.bias {
+            params.push(("bias".to_string(), bias));
+        }
+        params
+    }
+}
+
+/// `Linear` over weights quantized to `i8` or `i4`, for inference; only the bias stays
+/// learnable, though gradients still flow back to the inputs.
+pub struct QuantizedLinear<Q> {
+    pub weight: Quantized<Q>,
+    pub bias: Option<Param>,
+}
+
+impl<Q> QuantizedLinear<Q>
+where
+    Q: Integer + Send + Sync + 'static,
+    Packet<Q>: SupportedPacket,
+{
+    /// Quantize a trained layer, its weights being read back from `instance`.
+    pub fn from_linear(
+        linear: &Linear,
+        instance: &Instance,
+        opts: QuantOpts,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            weight: Quantized::from_tensor(&linear.weight, instance, opts)?,
+            bias: linear.bias.clone(),
+        })
+    }
+}
+
+impl<Q> Module for QuantizedLinear<Q>
+where
+    Q: Integer + Send + Sync + 'static,
+    Packet<Q>: SupportedPacket,
+{
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let output = input.matmul_quantized(&self.weight);
+
+        Ok(match &self.bias {
+            Some(bias) => &output + &bias.broadcast(output.order.clone()),
+            None => output,
+        })
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        self.bias
+            .iter()
+            .map(|bias| ("bias".to_string(), bias))
+            .collect()
+    }
+}
+
+/// Options shared by `Conv1d` and `Conv2d`, as `(y, x)` pairs for the latter.
+#[derive(Clone, Copy, Debug)]
+pub struct ConvOpts {
+    pub stride: (u32, u32),
+    pub padding: (u32, u32),
+    pub bias: bool,
+}
+
+impl Default for ConvOpts {
+    fn default() -> Self {
+        Self {
+            stride: (1, 1),
+            padding: (0, 0),
+            bias: true,
+        }
+    }
+}
+
+/// 2-D convolution over `[batch, channels, height, width]` inputs, run as a matmul over
+/// unfolded patches.
+pub struct Conv2d {
+    pub weight: Param,
+    pub bias: Option<Param>,
+
+    window: Window,
+}
+
+impl Conv2d {
+    pub fn new(input: u32, output: u32, kernel: (u32, u32), opts: ConvOpts) -> Self {
+        let bound = 1.0 / ((input * kernel.0 * kernel.1) as f32).sqrt();
+
+        Self {
+            weight: uniform(vec![output, input, kernel.0, kernel.1], bound),
+            bias: opts.bias.then(|| uniform(vec![output], bound)),
+            window: Window {
+                kernel,
+                stride: opts.stride,
+                padding: opts.padding,
+            },
+        }
+    }
+
+    /// Layer around existing `[output, input, kernel height, kernel width]` filters, e.g.
+    /// imported ones; `opts.bias` is ignored in favour of `bias`.
+    pub(crate) fn from_weights(weight: Param, bias: Option<Param>, opts: ConvOpts) -> Self {
+        let filter = weight.order.pull();
+
+        Self {
+            weight,
+            bias,
+            window: Window {
+                kernel: (filter[2], filter[3]),
+                stride: opts.stride,
+                padding: opts.padding,
+            },
+        }
+    }
+}
+
+impl Module for Conv2d {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let dims = input.order.pull();
+        let filter = self.weight.order.pull();
+        let (batch, output) = (dims[0], filter[0]);
+        let (height, width) = self.window.extent((dims[2], dims[3]));
+
+        let weight = self.weight.reshape(TensorOrder::new(vec![
+            output,
+            filter[1] * filter[2] * filter[3],
+        ]));
+        let mut cols = input.unfold(self.window).matmul(&weight.transpose());
+        if let Some(bias) = &self.bias {
+            cols = &cols + &bias.broadcast(cols.order.clone());
+        }
+
+        Ok(cols
+            .reshape(TensorOrder::new(vec![batch, height * width, output]))
+            .transpose()
+            .reshape(TensorOrder::new(vec![batch, output, height, width])))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        let mut params = vec![("weight".to_string(), &self.weight)];
+        if let Some(bias) = &self.bias {
+            params.push(("bias".to_string(), bias));
+        }
+        params
+    }
+}
+
+/// 1-D convolution over `[batch, channels, length]` inputs, run as a `Conv2d` of unit height;
+/// only the `x` half of the options applies.
+pub struct Conv1d {
+    inner: Conv2d,
+}
+
+impl Conv1d {
+    pub fn new(input: u32, output: u32, kernel: u32, opts: ConvOpts) -> Self {
+        let opts = ConvOpts {
+            stride: (1, opts.stride.1),
+            padding: (0, opts.padding.1),
+            ..opts
+        };
+
+        Self {
+            inner: Conv2d::new(input, output, (1, kernel), opts),
+        }
+    }
+}
+
+impl Module for Conv1d {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let dims = input.order.pull();
+        let image = input.reshape(TensorOrder::new(vec![dims[0], dims[1], 1, dims[2]]));
+
+        let output = self.inner.forward(&image)?;
+        let dims = output.order.pull();
+        Ok(output.reshape(TensorOrder::new(vec![dims[0], dims[1], dims[3]])))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        self.inner.named_parameters()
+    }
+}
+
+/// Normalization over the innermost axis, with a learnable affine transform.
+pub struct LayerNorm {
+    pub weight: Param,
+    pub bias: Param,
+
+    eps: f32,
+}
+
+impl LayerNorm {
+    pub fn new(features: u32, eps: f32) -> Self {
+        Self {
+            weight: constant(vec![features], 1.0),
+            bias: constant(vec![features], 0.0),
+            eps,
+        }
+    }
+}
+
+impl Module for LayerNorm {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let order = input.order.clone();
+        let axis = order.pull().len() - 1;
+        let features = order.pull()[axis] as f32;
+
+        let mean = input.sum(axis).scale(1.0 / features);
+        let centered = input - &mean.broadcast(order.clone());
+        let var = (&centered * &centered).sum(axis).scale(1.0 / features);
+        let std = var.offset(self.eps).sqrt().broadcast(order.clone());
+
+        let normalized = &centered / &std;
+        Ok(&(&normalized * &self.weight.broadcast(order.clone())) + &self.bias.broadcast(order))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        vec![
+            ("weight".to_string(), &self.weight),
+            ("bias".to_string(), &self.bias),
+        ]
+    }
+}
+
+/// Normalization over every axis but channels, the second one, of `[batch, channels, ...]`
+/// inputs; running statistics replace the batch ones when evaluating.
+pub struct BatchNorm {
+    pub weight: Param,
+    pub bias: Param,
+    pub running_mean: Param,
+    pub running_var: Param,
+
+    eps: f32,
+    momentum: f32,
+    training: bool,
+}
+
+impl BatchNorm {
+    pub fn new(channels: u32, eps: f32, momentum: f32) -> Self {
+        let stat = |value: f32| {
+            Tensor::from_vec(
+                vec![value; channels as usize],
+                TensorOrder::new(vec![channels]),
+            )
+        };
+
+        Self {
+            weight: constant(vec![channels], 1.0),
+            bias: constant(vec![channels], 0.0),
+            running_mean: stat(0.0),
+            running_var: stat(1.0),
+            eps,
+            momentum,
+            training: true,
+        }
+    }
+}
+
+impl Module for BatchNorm {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        let order = input.order.clone();
+        let dims = order.pull();
+        let channels = dims[1];
+
+        // Per-channel tensors shaped to broadcast against the input.
+        let mut unit = vec![1; dims.len()];
+        unit[1] = channels;
+        let spread = |tensor: &Param| {
+            tensor
+                .reshape(TensorOrder::new(unit.clone()))
+                .broadcast(order.clone())
+        };
+
+        let (mean, var) = if self.training {
+            let count = (order.size() / channels) as f32;
+            let reduce = |tensor: &Param| {
+                (0..dims.len())
+                    .filter(|axis| *axis != 1)
+                    .fold(tensor.clone(), |acc, axis| acc.sum(axis))
+                    .reshape(TensorOrder::new(vec![channels]))
+            };
+
+            let mean = reduce(input).scale(1.0 / count);
+            let centered = input - &spread(&mean);
+            let sum = reduce(&(&centered * &centered));
+            let var = sum.scale(1.0 / count);
+
+            let unbiased = sum.scale(1.0 / (count - 1.0).max(1.0));
+            let running_mean =
+                &self.running_mean.scale(1.0 - self.momentum) + &mean.scale(self.momentum);
+            let running_var =
+                &self.running_var.scale(1.0 - self.momentum) + &unbiased.scale(self.momentum);
+
+            // Resolved right away so the statistics don't chain every step's graph together,
+            // which needs an instance to resolve them on.
+            let instance = input.instance().ok_or(Error::Unsupported(
+                "training batch norm needs an input bound to an instance",
+            ))?;
+            self.running_mean = running_mean.detach(&instance)?;
+            self.running_var = running_var.detach(&instance)?;
+
+            (mean, var)
+        } else {
+            (self.running_mean.clone(), self.running_var.clone())
+        };
+
+        let normalized = &(input - &spread(&mean)) / &spread(&var.offset(self.eps).sqrt());
+        Ok(&(&normalized * &spread(&self.weight)) + &spread(&self.bias))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        vec![
+            ("weight".to_string(), &self.weight),
+            ("bias".to_string(), &self.bias),
+        ]
+    }
+
+    fn named_buffers(&self) -> Vec<(String, &Param)> {
+        vec![
+            ("running_mean".to_string(), &self.running_mean),
+            ("running_var".to_string(), &self.running_var),
+        ]
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+}
+
+/// Lookup table of `[count, features]`; inputs hold row indices, stored as floats.
+pub struct Embedding {
+    pub weight: Param,
+}
+
+impl Embedding {
+    pub fn new(count: u32, features: u32) -> Self {
+        Self {
+            weight: uniform(vec![count, features], 1.0),
+        }
+    }
+}
+
+impl Module for Embedding {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        Ok(self.weight.gather(input))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        vec![("weight".to_string(), &self.weight)]
+    }
+}
+
+/// Zeroes elements with probability `p` while training, scaling the rest to compensate.
+pub struct Dropout {
+    p: f32,
+    training: bool,
+}
+
+impl Dropout {
+    pub fn new(p: f32) -> Self {
+        Self { p, training: true }
+    }
+}
+
+impl Module for Dropout {
+    fn forward(&mut self, input: &Param) -> Result<Param, Error> {
+        if !self.training || self.p == 0.0 {
+            return Ok(input.clone());
+        }
+
+        let keep = 1.0 / (1.0 - self.p);
+        let mask = (0..input.order.size())
+            .map(|_| if sample() < self.p { 0.0 } else { keep })
+            .collect();
+
+        Ok(input * &Tensor::from_vec(mask, input.order.clone()))
+    }
+
+    fn named_parameters(&self) -> Vec<(String, &Param)> {
+        Vec::new()
+    }
+
+    fn train(&mut self, mode: bool) {
+        self.training = mode;
+    }
+}
+
+/// Scaled dot-product attention of `[..., queries, features]` against `[..., keys, features]`
+/// and `[..., keys, values]`, batched over leading axes. Scaling and the causal mask, which keeps
+/// every query from attending to later keys, are applied within the softmax kernels, so the
+/// only intermediates are the raw scores and their row statistics.
+pub fn attention(query: &Param, key: &Param, value: &Param, causal: bool) -> Param {
+    let dims = query.order.pull();
+    let features = dims[dims.len() - 1] as f32;
+
+    let scores = Scores {
+        scale: 1.0 / features.sqrt(),
+        causal,
+    };
+    query
+        .matmul(&key.transpose())
+        .normalize(dims.len() - 1, false, scores)
+        .matmul(value)
+}
@@ -0,0 +1,363 @@
+// Optimizers updating parameters in place on the device, one fused kernel per parameter.
+
+use std::sync::Arc;
+
+use crate::{
+    core::{ops::Shader, Bundle, Error, Instance, Operand},
+    shaders::{Rule, Update},
+    types::{Component, Packet, SupportedPacket},
+};
+
+use super::Tensor;
+
+/// Common interface of every optimizer.
+pub trait Optimizer {
+    /// Apply a single update to every parameter from its accumulated gradient.
+    fn step(&mut self, instance: &Instance) -> Result<(), Error>;
+
+    /// Forget the gradients accumulated into every parameter.
+    fn zero_grad(&mut self);
+
+    /// Snapshot of the step count and every state buffer, read back from the device.
+    fn state(&self, instance: &Instance) -> Result<OptimState, Error>;
+
+    /// Restore a snapshot taken by `state`, uploaded on the next step.
+    fn load_state(&mut self, state: OptimState) -> Result<(), Error>;
+}
+
+/// Serializable optimizer state, laid out in parameter order.
+#[derive(Clone, Debug, Default)]
+pub struct OptimState {
+    pub step: u64,
+    pub buffers: Vec<Vec<u8>>,
+}
+
+impl OptimState {
+    const MAGIC: &'static [u8; 4] = b"ZKOS";
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Self::MAGIC.to_vec();
+        bytes.extend(self.step.to_le_bytes());
+        bytes.extend((self.buffers.len() as u64).to_le_bytes());
+        for buffer in self.buffers.iter() {
+            bytes.extend((buffer.len() as u64).to_le_bytes());
+            bytes.extend(buffer);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        fn take<'b>(cursor: &mut &'b [u8], len: usize) -> Result<&'b [u8], Error> {
+            let (head, tail) = cursor
+                .split_at_checked(len)
+                .ok_or_else(|| Error::Format("optimizer state is truncated".into()))?;
+            *cursor = tail;
+            Ok(head)
+        }
+        fn word(cursor: &mut &[u8]) -> Result<u64, Error> {
+            Ok(u64::from_le_bytes(take(cursor, 8)?.try_into().unwrap()))
+        }
+
+        let mut cursor = bytes
+            .strip_prefix(Self::MAGIC.as_slice())
+            .ok_or_else(|| Error::Format("not an optimizer state: bad magic".into()))?;
+
+        let step = word(&mut cursor)?;
+        let count = word(&mut cursor)?;
+        let mut buffers = Vec::new();
+        for _ in 0..count {
+            let len = word(&mut cursor)?;
+            let len = usize::try_from(len)
+                .map_err(|_| Error::Format("optimizer state is truncated".into()))?;
+            buffers.push(take(&mut cursor, len)?.to_vec());
+        }
+        if !cursor.is_empty() {
+            return Err(Error::Format(format!(
+                "optimizer state has {} trailing bytes",
+                cursor.len()
+            )));
+        }
+
+        Ok(Self { step, buffers })
+    }
+}
+
+/// Parameter along with the state buffers its rule keeps.
+struct Slot {
+    param: Arc<dyn Operand>,
+    states: Vec<Arc<dyn Operand>>,
+}
+
+/// Parameters and bookkeeping shared by every optimizer.
+struct Params {
+    rule: Rule,
+    slots: Vec<Slot>,
+    step: u64,
+}
+
+impl Params {
+    fn new<T, U>(params: &[&Tensor<'_, T, U>], rule: Rule) -> Self
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let slots = params
+            .iter()
+            .map(|param| {
+                let param = param.operand();
+                let states = (0..rule.states())
+                    .map(|_| param.derive(param.dims().pull(), Shader::Fill(0.0), vec![]))
+                    .collect();
+                Slot { param, states }
+            })
+            .collect();
+
+        Self {
+            rule,
+            slots,
+            step: 0,
+        }
+    }
+
+    fn step(&mut self, instance: &Instance, scalars: &[f32]) -> Result<(), Error> {
+        // Parameters left out of the graph have nothing to update with.
+        let grads: Vec<_> = self
+            .slots
+            .iter()
+            .filter_map(|slot| Some((slot, slot.param.grad().lock().unwrap().clone()?)))
+            .collect();
+
+        let updates: Vec<_> = grads
+            .iter()
+            .map(|(slot, grad)| {
+                let states = slot.states.iter().map(|state| state.as_ref()).collect();
+                Update::new(self.rule, slot.param.as_ref(), grad.as_ref(), states)
+            })
+            .collect();
+
+        instance.step(&updates, scalars)
+    }
+
+    fn zero_grad(&mut self) {
+        for slot in self.slots.iter() {
+            slot.param.grad().lock().unwrap().take();
+        }
+    }
+
+    fn state(&self, instance: &Instance) -> Result<OptimState, Error> {
+        let buffers = self
+            .slots
+            .iter()
+            .flat_map(|slot| slot.states.iter())
+            .map(|state| instance.read(state))
+            .collect::<Result<_, _>>()?;
+
+        Ok(OptimState {
+            step: self.step,
+            buffers,
+        })
+    }
+
+    fn load_state(&mut self, state: OptimState) -> Result<(), Error> {
+        let count: usize = self.slots.iter().map(|slot| slot.states.len()).sum();
+        if state.buffers.len() != count {
+            return Err(Error::Format(format!(
+                "optimizer state holds {} buffers, the parameters need {}",
+                state.buffers.len(),
+                count
+            )));
+        }
+
+        // Checked up front, so a bad buffer leaves every state as it was.
+        let expected = self
+            .slots
+            .iter()
+            .flat_map(|slot| slot.states.iter())
+            .map(|state| state.dims().size() as u64 * state.stride());
+        for (index, (contents, expected)) in state.buffers.iter().zip(expected).enumerate() {
+            if contents.len() as u64 != expected {
+                return Err(Error::Format(format!(
+                    "optimizer state buffer {} is {} bytes, expected {}",
+                    index,
+                    contents.len(),
+                    expected
+                )));
+            }
+        }
+
+        let mut buffers = state.buffers.into_iter();
+        for slot in self.slots.iter_mut() {
+            for state in slot.states.iter_mut() {
+                *state = state.stage(buffers.next().unwrap().into());
+            }
+        }
+        self.step = state.step;
+
+        Ok(())
+    }
+}
+
+/// Options for `Sgd`.
+#[derive(Clone, Copy, Debug)]
+pub struct SgdOpts {
+    pub lr: f32,
+    pub momentum: f32,
+    pub nesterov: bool,
+    pub weight_decay: f32,
+}
+
+impl Default for SgdOpts {
+    fn default() -> Self {
+        Self {
+            lr: 1e-2,
+            momentum: 0.0,
+            nesterov: false,
+            weight_decay: 0.0,
+        }
+    }
+}
+
+/// Stochastic gradient descent, optionally with (Nesterov) momentum.
+pub struct Sgd {
+    opts: SgdOpts,
+    params: Params,
+}
+
+impl Sgd {
+    pub fn new<T, U>(params: &[&Tensor<'_, T, U>], opts: SgdOpts) -> Self
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let rule = Rule::Sgd {
+            momentum: opts.momentum != 0.0,
+            nesterov: opts.nesterov,
+        };
+
+        Self {
+            opts,
+            params: Params::new(params, rule),
+        }
+    }
+}
+
+impl Optimizer for Sgd {
+    fn step(&mut self, instance: &Instance) -> Result<(), Error> {
+        let SgdOpts {
+            lr,
+            momentum,
+            weight_decay,
+            ..
+        } = self.opts;
+
+        self.params.step += 1;
+        self.params
+            .step(instance, &[lr, momentum, 0.0, 0.0, weight_decay, 1.0, 1.0])
+    }
+
+    fn zero_grad(&mut self) {
+        self.params.zero_grad();
+    }
+
+    fn state(&self, instance: &Instance) -> Result<OptimState, Error> {
+        self.params.state(instance)
+    }
+
+    fn load_state(&mut self, state: OptimState) -> Result<(), Error> {
+        self.params.load_state(state)
+    }
+}
+
+/// Options for `Adam` and `AdamW`.
+#[derive(Clone, Copy, Debug)]
+pub struct AdamOpts {
+    pub lr: f32,
+    pub betas: (f32, f32),
+    pub eps: f32,
+    pub weight_decay: f32,
+}
+
+impl Default for AdamOpts {
+    fn default() -> Self {
+        Self {
+            lr: 1e-3,
+            betas: (0.9, 0.999),
+            eps: 1e-8,
+            weight_decay: 0.0,
+        }
+    }
+}
+
+macro_rules! impl_adam {
+    ($($(#[$doc:meta])* $name:ident $decoupled:literal, )*) => {$(
+        $(#[$doc])*
+        pub struct $name {
+            opts: AdamOpts,
+            params: Params,
+        }
+
+        impl $name {
+            pub fn new<T, U>(params: &[&Tensor<'_, T, U>], opts: AdamOpts) -> Self
+            where
+                T: Component,
+                Packet<U>: SupportedPacket,
+                Bundle<U>: Operand + 'static,
+            {
+                let rule = Rule::Adam {
+                    decoupled: $decoupled,
+                };
+
+                Self {
+                    opts,
+                    params: Params::new(params, rule),
+                }
+            }
+        }
+
+        impl Optimizer for $name {
+            fn step(&mut self, instance: &Instance) -> Result<(), Error> {
+                let AdamOpts {
+                    lr,
+                    betas: (beta1, beta2),
+                    eps,
+                    weight_decay,
+                } = self.opts;
+
+                self.params.step += 1;
+                let step = self.params.step as i32;
+                let scalars = [
+                    lr,
+                    beta1,
+                    beta2,
+                    eps,
+                    weight_decay,
+                    1.0 - beta1.powi(step),
+                    1.0 - beta2.powi(step),
+                ];
+
+                self.params.step(instance, &scalars)
+            }
+
+            fn zero_grad(&mut self) {
+                self.params.zero_grad();
+            }
+
+            fn state(&self, instance: &Instance) -> Result<OptimState, Error> {
+                self.params.state(instance)
+            }
+
+            fn load_state(&mut self, state: OptimState) -> Result<(), Error> {
+                self.params.load_state(state)
+            }
+        }
+    )*}
+}
+
+impl_adam! {
+    /// Adam, with weight decay folded into the gradient.
+    Adam false,
+    /// Adam with weight decay decoupled from the gradient.
+    AdamW true,
+}
@@ -0,0 +1,218 @@
+// Integer weights for inference on small devices: int8 or int4 values with f32 scales and zero
+// points, multiplied without ever being expanded to floats in device memory.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use crate::{
+    core::{
+        ops::{DimensionalType, Shader},
+        Bundle, Error, Instance, Operand,
+    },
+    types::{Integer, Packet, SupportedPacket},
+};
+
+use super::{nn::Param, TensorOrder};
+
+/// Weights sharing a scale and zero point.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Granularity {
+    /// Every input of an output channel.
+    Channel,
+    /// Runs of that many consecutive inputs of an output channel, which must divide the inputs.
+    Block(u32),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QuantOpts {
+    pub granularity: Granularity,
+    /// Keep zero points at zero, scales covering the largest magnitude; otherwise every range
+    /// gets mapped onto the whole integer range.
+    pub symmetric: bool,
+}
+
+impl Default for QuantOpts {
+    fn default() -> Self {
+        Self {
+            granularity: Granularity::Channel,
+            symmetric: true,
+        }
+    }
+}
+
+/// `[output, input]` weights, laid out as `Linear` holds them, quantized to `i8` or `i4`; every
+/// weight stands for `(q - zero) * scale`. Integers are packed into words on the device,
+/// scales and zero points are `[output, groups]` tensors.
+pub struct Quantized<Q> {
+    pub order: TensorOrder,
+    pub granularity: Granularity,
+    pub scales: Param,
+    pub zeros: Param,
+
+    values: Arc<dyn Operand>,
+    integer: PhantomData<Q>,
+}
+
+impl<Q> Quantized<Q>
+where
+    Q: Integer + Send + Sync + 'static,
+    Packet<Q>: SupportedPacket,
+{
+    /// Quantize weights held on the host, rounding to the nearest integer. Ranges always span
+    /// zero, so that zero weights stay exact.
+    pub fn quantize(src: &[f32], order: TensorOrder, opts: QuantOpts) -> Result<Self, Error> {
+        assert_eq!(src.len(), order.size() as usize, "Mismatched tensor sizes.");
+        let block = Self::block(&order, opts.granularity)?;
+        let (min, max) = Self::range();
+
+        let groups = src.len() / block as usize;
+        let (mut scales, mut zeros) = (Vec::with_capacity(groups), Vec::with_capacity(groups));
+        let mut values = Vec::with_capacity(src.len());
+        for group in src.chunks(block as usize) {
+            let (lo, hi) = group
+                .iter()
+                .fold((0f32, 0f32), |(lo, hi), w| (lo.min(*w), hi.max(*w)));
+
+            let scale = match opts.symmetric {
+                true => lo.abs().max(hi) / max,
+                false => (hi - lo) / (max - min),
+            };
+            let scale = if scale > 0.0 { scale } else { 1.0 };
+            let zero = match opts.symmetric {
+                true => 0.0,
+                false => (min - lo / scale).round(),
+            };
+
+            values.extend(
+                group
+                    .iter()
+                    .map(|w| ((w / scale).round() + zero).clamp(min, max) as i8),
+            );
+            scales.push(scale);
+            zeros.push(zero);
+        }
+
+        Self::from_parts(values, order, scales, zeros, opts.granularity)
+    }
+
+    /// Quantize a tensor, read back from the instance it lives on.
+    pub fn from_tensor(
+        tensor: &Param,
+        instance: &Instance,
+        opts: QuantOpts,
+    ) -> Result<Self, Error> {
+        Self::quantize(&tensor.to_vec(instance)?, tensor.order.clone(), opts)
+    }
+
+    /// Weights quantized elsewhere: one integer per weight, row-major `[output, input]`, then a
+    /// scale and zero point per group, row-major `[output, groups]`.
+    pub fn from_parts(
+        values: Vec<i8>,
+        order: TensorOrder,
+        scales: Vec<f32>,
+        zeros: Vec<f32>,
+        granularity: Granularity,
+    ) -> Result<Self, Error> {
+        let block = Self::block(&order, granularity)?;
+        let (min, max) = Self::range();
+        assert_eq!(
+            values.len(),
+            order.size() as usize,
+            "Mismatched tensor sizes."
+        );
+        assert!(
+            values.iter().all(|q| (min..=max).contains(&(*q as f32))),
+            "Integers out of range."
+        );
+
+        let dims = order.pull();
+        let groups = TensorOrder::new(vec![dims[0], dims[1] / block]);
+        assert!(
+            scales.len() == groups.size() as usize && zeros.len() == scales.len(),
+            "Mismatched tensor sizes."
+        );
+
+        let values =
+            Bundle::<Q>::bind_init(dims, pack(&values, Q::BITS)).map_err(|_| Error::Wgpu)?;
+
+        Ok(Self {
+            order,
+            granularity,
+            scales: Param::from_vec(scales, groups.clone()),
+            zeros: Param::from_vec(zeros, groups),
+            values: Arc::new(values),
+            integer: PhantomData,
+        })
+    }
+
+    /// Weights expanded back to floats, e.g. to measure the quantization error.
+    pub fn dequantize(&self) -> Param {
+        let bits = Q::BITS;
+        let block = self.group();
+        let expanded = self.scales.operand().derive(
+            self.order.pull(),
+            Shader::Dimensional(DimensionalType::Unpack { bits, block }),
+            self.operands(),
+        );
+
+        Param::from_operand(expanded).unwrap()
+    }
+
+    /// Kernel multiplying by these weights.
+    pub(crate) fn matmul(&self) -> Shader {
+        Shader::Dimensional(DimensionalType::PackedMatmul {
+            bits: Q::BITS,
+            block: self.group(),
+        })
+    }
+
+    /// Packed integers, scales and zero points, in the order kernels bind them.
+    pub(crate) fn operands(&self) -> Vec<Arc<dyn Operand>> {
+        vec![
+            self.values.clone(),
+            self.scales.operand(),
+            self.zeros.operand(),
+        ]
+    }
+
+    /// Inputs sharing a scale and zero point.
+    fn group(&self) -> u32 {
+        match self.granularity {
+            Granularity::Channel => self.order.pull()[1],
+            Granularity::Block(block) => block,
+        }
+    }
+
+    fn block(order: &TensorOrder, granularity: Granularity) -> Result<u32, Error> {
+        let dims = order.pull();
+        if dims.len() != 2 {
+            return Err(Error::Unsupported("quantizing anything but a matrix"));
+        }
+
+        match granularity {
+            Granularity::Channel => Ok(dims[1]),
+            Granularity::Block(block) if block > 0 && dims[1].is_multiple_of(block) => Ok(block),
+            Granularity::Block(_) => Err(Error::Unsupported(
+                "quantization blocks that don't divide the inputs",
+            )),
+        }
+    }
+
+    /// Smallest and largest integer of `Q`, as floats.
+    fn range() -> (f32, f32) {
+        let half = (1 << (Q::BITS - 1)) as f32;
+        (-half, half - 1.0)
+    }
+}
+
+/// Two's complement integers of `bits` packed into little-endian words, low lanes first.
+fn pack(values: &[i8], bits: u32) -> Vec<u8> {
+    let lanes = (32 / bits) as usize;
+    let mask = (1u32 << bits) - 1;
+
+    let mut words = vec![0u32; values.len().div_ceil(lanes)];
+    for (index, value) in values.iter().enumerate() {
+        words[index / lanes] |= (*value as u32 & mask) << ((index % lanes) as u32 * bits);
+    }
+
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
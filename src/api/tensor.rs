@@ -2,12 +2,26 @@
 
 use std::{
     fmt::{self, Debug, Display},
-    ops,
+    fs::File,
+    io::BufReader,
+    mem, ops,
+    path::Path,
+    sync::{atomic::Ordering, Arc},
 };
 
 use crate::{
-    core::Bundle,
-    types::{Component, Packet, SupportedPacket},
+    core::{
+        ops::{
+            depth, ActivationType, DimensionalType, ElementType, Scores, Shader, UnaryType, Window,
+        },
+        Bundle, Contents, Error, Instance, Operand, Operation, Tape,
+    },
+    types::{Component, Integer, Packet, SupportedPacket},
+};
+
+use super::{
+    io::{self, npy, safetensors, Safetensors},
+    quant::Quantized,
 };
 
 /// Denoting shape a.k.a. dimensions of a `Tensor`'s `TensorMeta`.
@@ -48,76 +62,105 @@ impl Display for TensorOrder {
     }
 }
 
-/// Source container of a `Tensor`'s data, either owned or referenced
-pub(crate) struct TensorMeta<'s, T, const N: usize>
+/// Source container of a `Tensor`'s data. Owned contents are handed to the `Bundle` as staged
+/// bytes and dropped once uploaded, so only borrowed sources are remembered here.
+pub(crate) struct TensorMeta<'s, T>
 where
     T: Component,
 {
     src: Option<&'s [T]>,
-    per: Option<Vec<T>>,
 }
 
-impl<'s, T, const N: usize> TensorMeta<'s, T, N>
+impl<'s, T> TensorMeta<'s, T>
 where
     T: Component,
 {
     #[inline]
     pub fn from_reference(_src: &'s [T]) -> Self {
-        Self {
-            src: Some(_src),
-            per: None,
-        }
+        Self { src: Some(_src) }
     }
 
+    /// Placeholder for tensors whose contents only live on the device, or are staged for it.
     #[inline]
-    pub fn from_persist(_src: [T; N]) -> Self {
-        Self {
-            src: None,
-            per: Some(Vec::from(_src)),
-        }
+    pub fn empty() -> Self {
+        Self { src: None }
     }
+}
 
-    #[inline]
-    pub fn slots(&self) -> (bool, bool) {
-        (self.src.is_some(), self.per.is_some())
+impl<'s, T> Clone for TensorMeta<'s, T>
+where
+    T: Component,
+{
+    fn clone(&self) -> Self {
+        Self { src: self.src }
     }
 }
 
 /// Most basic element in the toolkit, composing every model.
-pub struct Tensor<'s, T, U, const N: usize>
+pub struct Tensor<'s, T, U>
 where
     T: Component,
     Packet<U>: SupportedPacket,
 {
     pub order: TensorOrder,
 
-    bundle: Bundle<U>,
-    meta: TensorMeta<'s, T, N>,
+    bundle: Arc<Bundle<U>>,
+    meta: TensorMeta<'s, T>,
+}
+
+/// Clones share the same device contents and pending operations.
+impl<'s, T, U> Clone for Tensor<'s, T, U>
+where
+    T: Component,
+    Packet<U>: SupportedPacket,
+{
+    fn clone(&self) -> Self {
+        Self {
+            order: self.order.clone(),
+            bundle: self.bundle.clone(),
+            meta: self.meta.clone(),
+        }
+    }
 }
 
-impl<'s, T, U, const N: usize> Tensor<'s, T, U, N>
+impl<'s, T, U> Tensor<'s, T, U>
 where
     T: Component,
     Packet<U>: SupportedPacket,
 {
-    pub fn from_array(_src: [T; N], order: TensorOrder) -> Self {
-        let bundle = Bundle::bind_init(order.pull()).unwrap();
-        let meta = TensorMeta::from_persist(_src);
+    pub fn from_array<const N: usize>(_src: [T; N], order: TensorOrder) -> Self {
+        let contents = bytemuck::cast_slice::<T, u8>(&_src).to_vec();
+        let bundle = Bundle::bind_init(order.pull(), contents).unwrap();
+        let meta = TensorMeta::empty();
 
         Self {
             order,
-            bundle,
+            bundle: Arc::new(bundle),
+            meta,
+        }
+    }
+
+    /// Takes ownership of `_src` without copying it: it is staged for upload as is, and
+    /// released as soon as the tensor is first resolved.
+    pub fn from_vec(_src: Vec<T>, order: TensorOrder) -> Self {
+        let bundle = Bundle::bind_init(order.pull(), _src).unwrap();
+        let meta = TensorMeta::empty();
+
+        Self {
+            order,
+            bundle: Arc::new(bundle),
             meta,
         }
     }
 
     pub fn from_slice(_src: &'s [T], order: TensorOrder) -> Self {
-        let bundle = Bundle::bind_init(order.pull()).unwrap();
+        let contents = bytemuck::cast_slice::<T, u8>(_src).to_vec();
+        let bundle = Bundle::bind_init(order.pull(), contents).unwrap();
         let meta = TensorMeta::from_reference(_src);
 
         Self {
             order,
-            bundle,
+            bundle: Arc::new(bundle),
             meta,
         }
     }
@@ -134,33 +177,447 @@ where
     pub fn determinant(&self) {}
     pub fn inverse(&self) {}
 
+    /// Track gradients flowing into this tensor once `backward` is called on a result.
+    pub fn requires_grad(self) -> Self {
+        self.bundle.tracked.store(true, Ordering::Relaxed);
+        self
+    }
+
     /// Pull internal `Bundle` representation.
     pub(crate) fn fetch(&self) -> &Bundle<U> {
         &self.bundle
     }
+}
+
+impl<'s, T, U> Tensor<'s, T, U>
+where
+    T: Component,
+    Packet<U>: SupportedPacket,
+    Bundle<U>: Operand + 'static,
+{
+    /// Resolve if needed and copy the contents back to the host.
+    pub fn to_vec(&self, instance: &Instance) -> Result<Vec<T>, Error> {
+        let contents = instance.read(&self.operand())?;
+        let values = contents
+            .chunks_exact(mem::size_of::<T>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+
+        Ok(values)
+    }
+
+    /// Non-blocking counterpart of `to_vec`, for use from async executors.
+    pub async fn to_vec_async(&self, instance: &Instance) -> Result<Vec<T>, Error> {
+        let contents = instance.read_async(&self.operand()).await?;
+        let values = contents
+            .chunks_exact(mem::size_of::<T>())
+            .map(bytemuck::pod_read_unaligned)
+            .collect();
+
+        Ok(values)
+    }
+
+    /// Write the tensor to a safetensors file, as its only entry.
+    pub fn save(&self, instance: &Instance, path: impl AsRef<Path>) -> Result<(), Error> {
+        safetensors::save(path, instance, &[("tensor", self)])
+    }
+
+    /// Read a tensor back from a safetensors file holding a single one, whatever its name.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = Safetensors::open(path)?;
+        let names: Vec<_> = file.names().map(str::to_string).collect();
+        let [name] = names.as_slice() else {
+            return Err(Error::Format(
+                "expected a file holding a single tensor".into(),
+            ));
+        };
+
+        let (order, contents) = file.read::<U>(name)?;
+        Ok(Self::from_bytes(contents, order))
+    }
+
+    /// Read a NumPy `.npy` file, which must hold the dtype `U` packs; Fortran-ordered arrays
+    /// are reordered on the host.
+    pub fn from_npy(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        let (order, contents) = npy::read::<U>(&mut BufReader::new(file), size)?;
+
+        Ok(Self::from_bytes(contents, order))
+    }
+
+    /// Write the tensor to a NumPy `.npy` file, in C order.
+    pub fn to_npy(&self, instance: &Instance, path: impl AsRef<Path>) -> Result<(), Error> {
+        let dtype =
+            Packet::<U>::DTYPE.ok_or(Error::Unsupported("dtype without a numpy equivalent"))?;
+        let contents = instance.read(&self.operand())?;
+
+        io::replace(path.as_ref(), |file| {
+            npy::write(file, dtype, &self.order.pull(), &contents)
+        })
+    }
+
+    /// Instance this tensor is bound to, if it was created through one or resolved already.
+    pub fn instance(&self) -> Option<Instance> {
+        self.bundle.owner.get().cloned()
+    }
+
+    /// Same tensor bound to `instance`. Unbound tensors are simply claimed, while tensors
+    /// living on another instance are resolved there and copied over as a fresh leaf.
+    pub fn to(&self, instance: &Instance) -> Result<Self, Error> {
+        let owner = match self.bundle.owner.get() {
+            Some(owner) => owner,
+            None if self.bundle.claim(instance) => return Ok(self.clone()),
+            None => return self.to(instance),
+        };
+        if owner == instance {
+            return Ok(self.clone());
+        }
+
+        let contents = owner.read(&self.operand())?;
+        let bundle = Bundle::bind_init(self.order.pull(), contents).map_err(|_| Error::Wgpu)?;
+        bundle.claim(instance);
+
+        Ok(Self {
+            order: self.order.clone(),
+            bundle: Arc::new(bundle),
+            meta: TensorMeta::empty(),
+        })
+    }
+
+    /// Resolve on `instance` and return the result as a leaf, cut off from the operations that
+    /// produced it so they can be dropped.
+    pub fn detach(&self, instance: &Instance) -> Result<Self, Error> {
+        let operand = instance.detach(&self.operand())?;
+        Ok(Self::from_operand(operand).unwrap())
+    }
+
+    /// Matrix product over the two innermost axes, batching over leading ones.
+    pub fn matmul(&self, other: &Self) -> Self {
+        let (lhs, rhs) = (self.order.pull(), other.order.pull());
+        let rank = lhs.len();
+        assert!(
+            rank >= 2
+                && rhs.len() == rank
+                && lhs[..rank - 2] == rhs[..rank - 2]
+                && lhs[rank - 1] == rhs[rank - 2],
+            "Mismatched tensor shapes."
+        );
+
+        let mut dims = lhs.clone();
+        dims[rank - 1] = rhs[rank - 1];
+        self.derive(
+            dims,
+            Shader::Dimensional(DimensionalType::Matmul),
+            vec![self.operand(), other.operand()],
+        )
+    }
+
+    /// Product by the transpose of quantized weights, as `Linear` multiplies by its own,
+    /// batching over leading axes. Weights get unpacked within the reduction rather than
+    /// expanded in memory.
+    pub fn matmul_quantized<Q>(&self, weights: &Quantized<Q>) -> Self
+    where
+        Q: Integer + Send + Sync + 'static,
+        Packet<Q>: SupportedPacket,
+    {
+        let mut dims = self.order.pull();
+        let (outputs, inputs) = (weights.order.pull()[0], weights.order.pull()[1]);
+        assert_eq!(dims.last(), Some(&inputs), "Mismatched tensor shapes.");
+        *dims.last_mut().unwrap() = outputs;
+
+        let mut operands = vec![self.operand()];
+        operands.extend(weights.operands());
+        self.derive(dims, weights.matmul(), operands)
+    }
+
+    /// Swap the two innermost axes, batching over leading ones.
+    pub fn transpose(&self) -> Self {
+        let mut dims = self.order.pull();
+        assert!(dims.len() >= 2, "Only matrices can be transposed.");
+
+        let rank = dims.len();
+        dims.swap(rank - 2, rank - 1);
+        self.derive(
+            dims,
+            Shader::Dimensional(DimensionalType::Transpose),
+            vec![self.operand()],
+        )
+    }
+
+    /// Same elements laid out under another shape.
+    pub fn reshape(&self, order: TensorOrder) -> Self {
+        assert_eq!(self.order.size(), order.size(), "Mismatched tensor sizes.");
+
+        self.derive(
+            order.pull(),
+            Shader::Dimensional(DimensionalType::Reshape),
+            vec![self.operand()],
+        )
+    }
+
+    /// Slices of this table along its first axis picked by `indices`, appending the remaining
+    /// axes to their shape. Negative indices count from the last slice.
+    pub fn gather(&self, indices: &Self) -> Self {
+        let mut dims = indices.order.pull();
+        dims.extend_from_slice(&self.order.pull()[1..]);
+
+        self.derive(
+            dims,
+            Shader::Dimensional(DimensionalType::Gather),
+            vec![self.operand(), indices.operand()],
+        )
+    }
+
+    /// Patches of a `[batch, channels, height, width]` image as rows of
+    /// `[batch * patches, channels * kernel height * kernel width]`.
+    pub(crate) fn unfold(&self, window: Window) -> Self {
+        let dims = self.order.pull();
+        assert_eq!(dims.len(), 4, "Only images can be unfolded.");
+
+        let (oh, ow) = window.extent((dims[2], dims[3]));
+        self.derive(
+            vec![
+                dims[0] * oh * ow,
+                dims[1] * window.kernel.0 * window.kernel.1,
+            ],
+            Shader::Dimensional(DimensionalType::Unfold(window)),
+            vec![self.operand()],
+        )
+    }
+
+    /// Sum along `axis`, keeping it with a single element.
+    pub fn sum(&self, axis: usize) -> Self {
+        let mut dims = self.order.pull();
+        dims[axis] = 1;
+
+        self.derive(
+            dims,
+            Shader::Dimensional(DimensionalType::Sum),
+            vec![self.operand()],
+        )
+    }
+
+    /// Normalized exponentials along `axis`, each row's statistics computed once up front.
+    pub fn softmax(&self, axis: usize) -> Self {
+        self.normalize(axis, false, Scores::PLAIN)
+    }
 
-    /// Denotes a `TensorMeta` of valid slots, initialized.
-    fn ready(&self) -> bool {
-        let (src, per) = self.meta.slots();
-        src || per
+    /// Logarithm of `softmax`, without ever computing the exponentials it would cancel out.
+    pub fn log_softmax(&self, axis: usize) -> Self {
+        self.normalize(axis, true, Scores::PLAIN)
+    }
+
+    /// Softmax, or its logarithm, of the scores adjusted as `scores` asks.
+    pub(crate) fn normalize(&self, axis: usize, log: bool, scores: Scores) -> Self {
+        let axis = axis as u32;
+        let mut reduced = self.order.pull();
+        reduced[axis as usize] = 1;
+
+        let lse = self.derive(
+            reduced,
+            Shader::Dimensional(DimensionalType::LogSumExp { axis, scores }),
+            vec![self.operand()],
+        );
+        self.derive(
+            self.order.pull(),
+            Shader::Dimensional(DimensionalType::Softmax { axis, log, scores }),
+            vec![self.operand(), lse.operand()],
+        )
+    }
+
+    /// Maximum along `axis`, keeping it with a single element.
+    pub fn max(&self, axis: usize) -> Self {
+        let mut dims = self.order.pull();
+        dims[axis] = 1;
+
+        self.derive(
+            dims,
+            Shader::Dimensional(DimensionalType::Max),
+            vec![self.operand()],
+        )
+    }
+
+    /// Repeat along unit or missing leading axes up to `order`.
+    pub fn broadcast(&self, order: TensorOrder) -> Self {
+        self.derive(
+            order.pull(),
+            Shader::Dimensional(DimensionalType::Expand),
+            vec![self.operand()],
+        )
+    }
+
+    /// Multiply every element by `factor`.
+    pub fn scale(&self, factor: f32) -> Self {
+        self * &Self::full(self.order.clone(), factor)
+    }
+
+    /// Add `value` to every element.
+    pub fn offset(&self, value: f32) -> Self {
+        self + &Self::full(self.order.clone(), value)
+    }
+
+    pub fn abs(&self) -> Self {
+        self.unary(UnaryType::Abs)
+    }
+
+    pub fn exp(&self) -> Self {
+        self.unary(UnaryType::Exp)
+    }
+
+    pub fn log(&self) -> Self {
+        self.unary(UnaryType::Log)
+    }
+
+    pub fn neg(&self) -> Self {
+        self.unary(UnaryType::Neg)
+    }
+
+    pub fn sqrt(&self) -> Self {
+        self.unary(UnaryType::Sqrt)
+    }
+
+    pub fn relu(&self) -> Self {
+        self.activate(ActivationType::Relu)
+    }
+
+    pub fn sigmoid(&self) -> Self {
+        self.activate(ActivationType::Sigmoid)
+    }
+
+    pub fn tanh(&self) -> Self {
+        self.activate(ActivationType::Tanh)
+    }
+
+    pub fn gelu(&self) -> Self {
+        self.activate(ActivationType::Gelu)
+    }
+
+    /// Tensor of `order` with every element set to `value`, only materialized on the device.
+    pub fn full(order: TensorOrder, value: f32) -> Self {
+        let op = Operation::from_operands(vec![], Shader::Fill(value));
+        let bundle = Bundle::bind_future(order.pull(), op).unwrap();
+
+        Self {
+            order,
+            bundle: Arc::new(bundle),
+            meta: TensorMeta::empty(),
+        }
+    }
+
+    /// Propagate gradients from this tensor back to every tracked one it depends on.
+    pub fn backward(&self) -> Result<(), Error> {
+        let root = self.operand();
+        let seed = root.derive(self.order.pull(), Shader::Fill(1.0), vec![]);
+
+        Tape::record(&root).backward(seed)
+    }
+
+    /// Gradient accumulated by `backward`, pending until resolved.
+    pub fn grad(&self) -> Option<Self> {
+        let grad = self.bundle.grad.lock().unwrap().clone()?;
+        Self::from_operand(grad)
+    }
+
+    /// Longest chain of operations the tensor was derived through and still holds on to; 0 for
+    /// uploads and detached tensors. Handy to check a loop doesn't keep every step's graph.
+    pub fn depth(&self) -> usize {
+        depth(&self.operand())
+    }
+
+    /// Replace the contents with raw bytes, uploaded the next time the tensor is needed.
+    pub(crate) fn restage(&self, contents: impl Into<Contents>) {
+        self.bundle.holder().write().unwrap().release();
+        self.bundle.restage(contents.into());
+    }
+
+    /// Leaf holding raw bytes already laid out as `U`.
+    pub(crate) fn from_bytes(contents: impl Into<Contents>, order: TensorOrder) -> Self {
+        let bundle = Bundle::bind_init(order.pull(), contents).unwrap();
+
+        Self {
+            order,
+            bundle: Arc::new(bundle),
+            meta: TensorMeta::empty(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn operand(&self) -> Arc<dyn Operand> {
+        self.bundle.clone()
+    }
+
+    /// Wrap an operand back into a tensor, provided it shares this tensor's packet type.
+    pub(crate) fn from_operand(operand: Arc<dyn Operand>) -> Option<Self> {
+        let order = TensorOrder::new(operand.dims().pull());
+        let bundle = operand.erase().downcast::<Bundle<U>>().ok()?;
+
+        Some(Self {
+            order,
+            bundle,
+            meta: TensorMeta::empty(),
+        })
+    }
+
+    fn derive(&self, dims: Vec<u32>, ty: Shader, operands: Vec<Arc<dyn Operand>>) -> Self {
+        let op = Operation::from_operands(operands, ty);
+        let bundle = Bundle::bind_future(dims.clone(), op).unwrap();
+
+        Self {
+            order: TensorOrder::new(dims),
+            bundle: Arc::new(bundle),
+            meta: TensorMeta::empty(),
+        }
+    }
+
+    fn unary(&self, ty: UnaryType) -> Self {
+        self.derive(self.order.pull(), Shader::Unary(ty), vec![self.operand()])
+    }
+
+    fn activate(&self, ty: ActivationType) -> Self {
+        self.derive(
+            self.order.pull(),
+            Shader::Activation(ty),
+            vec![self.operand()],
+        )
+    }
+
+    /// Record an elementwise operation against `other`, deferred until resolved.
+    fn chain(&self, other: &Self, ty: ElementType) -> Self {
+        assert_eq!(self.order, other.order, "Mismatched tensor shapes.");
+
+        self.derive(
+            self.order.pull(),
+            Shader::Element(ty),
+            vec![self.operand(), other.operand()],
+        )
     }
 }
 
 macro_rules! impl_ops {
     ( $ ( $trait:ident $fn:ident, )* ) => {
         $ (
-            impl<'s, T, U, const M: usize> ops::$trait for Tensor<'s, T, U, M> where
+            impl<'s, T, U> ops::$trait for Tensor<'s, T, U> where
                 T: Component,
                 Packet<U>: SupportedPacket,
+                Bundle<U>: Operand + 'static,
             {
-                type Output = Tensor<'s, T, U, M>;
-
-                fn $fn<'t, V, W, const N: usize>(self, other: Tensor<'t, V, W, N>) -> Self::Output where
-                    V: Component,
-                    Packet<W>: SupportedPacket,
-                {
-                    let (lb, rb) = (self.fetch(), other.fetch());
-                    other
+                type Output = Tensor<'s, T, U>;
+
+                fn $fn(self, other: Self) -> Self::Output {
+                    self.chain(&other, ElementType::$trait)
+                }
+            }
+
+            impl<'s, T, U> ops::$trait for &Tensor<'s, T, U> where
+                T: Component,
+                Packet<U>: SupportedPacket,
+                Bundle<U>: Operand + 'static,
+            {
+                type Output = Tensor<'s, T, U>;
+
+                fn $fn(self, other: Self) -> Self::Output {
+                    self.chain(other, ElementType::$trait)
                 }
             }
         )*
@@ -181,32 +638,16 @@ macro_rules! tsr {
         {
             let _src = [$root $ (, $next )*];
             let order = TensorOrder::new(vec![_src.len() as u32]);
-            Tensor::from_array(_tensor, raw)
+            Tensor::from_array(_src, order)
         }
 
     };
 
     ( $ ( [ $root:literal $ (, $next:literal)* ] $(,)? )*  ) => {
         {
-            let (mut x, mut y) = (1, 0);
-            let mut depth = true;
-            let _src = [
-                $ (
-                    {
-                        y += 1;
-                        if y > 1 { depth = !depth; }
-                        $root
-                    },
-                    $ (
-                        {
-                            depth.then(|| x += 1);
-                            $next
-                        },
-                    )*
-                )*
-            ];
-
-            let order = TensorOrder::new(vec![x, y]);
+            let _src = [$ ( $root $ (, $next )* ),*];
+            let rows = [$ ( stringify!($root) ),*].len() as u32;
+            let order = TensorOrder::new(vec![rows, _src.len() as u32 / rows]);
             Tensor::from_array(_src, order)
         }
     };
@@ -0,0 +1,354 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use super::{
+    ops::{flows, DimensionalType, ElementType, GradientType, Shader, Side, UnaryType},
+    Error, Node, Operand, ResultTk,
+};
+
+#[inline]
+fn key(operand: &Arc<dyn Operand>) -> *const () {
+    Arc::as_ptr(operand) as *const ()
+}
+
+/// Operands reachable from a root that gradients flow through, in topological order.
+pub(crate) struct Tape {
+    nodes: Vec<Arc<dyn Operand>>,
+    /// Whether gradients flow through each operand met so far, by address.
+    flows: HashMap<*const (), bool>,
+}
+
+impl Tape {
+    pub fn record(root: &Arc<dyn Operand>) -> Self {
+        let mut tape = Self {
+            nodes: Vec::new(),
+            flows: HashMap::new(),
+        };
+        tape.visit(root, &mut HashSet::new());
+        tape
+    }
+
+    /// Post-order walk with an explicit stack, as graphs kept across a training loop grow too
+    /// deep to recurse on. An operand is pushed once every one of its sides is.
+    fn visit(&mut self, root: &Arc<dyn Operand>, seen: &mut HashSet<*const ()>) {
+        let mut stack = vec![(root.clone(), false)];
+
+        while let Some((operand, expanded)) = stack.pop() {
+            if expanded {
+                self.nodes.push(operand);
+                continue;
+            }
+            if !flows(&operand, &mut self.flows) || !seen.insert(key(&operand)) {
+                continue;
+            }
+
+            stack.push((operand.clone(), true));
+            if let Some(node) = operand.node() {
+                stack.extend(
+                    node.operands()
+                        .iter()
+                        .rev()
+                        .map(|side| (side.clone(), false)),
+                );
+            }
+        }
+    }
+
+    /// Walk the tape backwards from `seed`, the gradient of its root, accumulating into every
+    /// tracked operand. Gradients are recorded as operations too, left pending until resolved.
+    pub fn backward(&self, seed: Arc<dyn Operand>) -> ResultTk<()> {
+        let mut grads: HashMap<*const (), Arc<dyn Operand>> = HashMap::new();
+        if let Some(root) = self.nodes.last() {
+            grads.insert(key(root), seed);
+        }
+
+        for operand in self.nodes.iter().rev() {
+            let Some(grad) = grads.remove(&key(operand)) else {
+                continue;
+            };
+
+            if operand.tracked() {
+                accumulate(&mut operand.grad().lock().unwrap(), grad.clone());
+            }
+
+            let Some(node) = operand.node() else {
+                continue;
+            };
+            for (side, local) in node.operands().iter().zip(rule(operand, node, &grad)?) {
+                let Some(local) = local else {
+                    continue;
+                };
+                if self.flows.get(&key(side)).copied().unwrap_or(false) {
+                    let mut slot = grads.remove(&key(side));
+                    accumulate(&mut slot, local);
+                    grads.insert(key(side), slot.unwrap());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn accumulate(slot: &mut Option<Arc<dyn Operand>>, grad: Arc<dyn Operand>) {
+    *slot = Some(match slot.take() {
+        Some(prev) => prev.derive(
+            prev.dims().pull(),
+            Shader::Element(ElementType::Add),
+            vec![prev.clone(), grad],
+        ),
+        None => grad,
+    });
+}
+
+/// `grad` times `factor`, shaped like `x`.
+fn scaled(x: &Arc<dyn Operand>, grad: Arc<dyn Operand>, factor: f32) -> Arc<dyn Operand> {
+    if factor == 1.0 {
+        return grad;
+    }
+
+    let factor = x.derive(x.dims().pull(), Shader::Fill(factor), vec![]);
+    x.derive(
+        x.dims().pull(),
+        Shader::Element(ElementType::Mul),
+        vec![grad, factor],
+    )
+}
+
+/// Gradients with respect to each of `node`'s operands, given the gradient of its output;
+/// `None` for operands gradients can't flow into.
+fn rule(
+    output: &Arc<dyn Operand>,
+    node: &dyn Node,
+    grad: &Arc<dyn Operand>,
+) -> ResultTk<Vec<Option<Arc<dyn Operand>>>> {
+    let operands = node.operands();
+    // Gradient shaped like `x`, computed by `ty` from the upstream one alone.
+    let map = |x: &Arc<dyn Operand>, ty: DimensionalType| {
+        Some(x.derive(x.dims().pull(), Shader::Dimensional(ty), vec![grad.clone()]))
+    };
+    // Gradient shaped like `x`, computed elementwise by `ty` from `x` and the upstream one.
+    let local = |x: &Arc<dyn Operand>, ty: GradientType| {
+        Some(x.derive(
+            x.dims().pull(),
+            Shader::Gradient(ty),
+            vec![x.clone(), grad.clone()],
+        ))
+    };
+
+    let grads = match node.ty() {
+        Shader::Element(op) => {
+            let (a, b) = (&operands[0], &operands[1]);
+            [Side::Lhs, Side::Rhs]
+                .into_iter()
+                .zip([a, b])
+                .map(|(side, operand)| {
+                    Some(operand.derive(
+                        operand.dims().pull(),
+                        Shader::Gradient(GradientType::Element(op, side)),
+                        vec![a.clone(), b.clone(), grad.clone()],
+                    ))
+                })
+                .collect()
+        }
+        Shader::Activation(act) => vec![local(&operands[0], GradientType::Activation(act))],
+        Shader::Unary(op) => vec![local(&operands[0], GradientType::Unary(op))],
+        Shader::Dimensional(DimensionalType::Matmul) => {
+            let (a, b) = (&operands[0], &operands[1]);
+            let (ad, bd) = (a.dims().pull(), b.dims().pull());
+            let transpose = |operand: &Arc<dyn Operand>, dims: &[u32]| {
+                let mut dims = dims.to_vec();
+                let rank = dims.len();
+                dims.swap(rank - 2, rank - 1);
+                operand.derive(
+                    dims,
+                    Shader::Dimensional(DimensionalType::Transpose),
+                    vec![operand.clone()],
+                )
+            };
+
+            let matmul = Shader::Dimensional(DimensionalType::Matmul);
+            vec![
+                Some(a.derive(ad.clone(), matmul, vec![grad.clone(), transpose(b, &bd)])),
+                Some(b.derive(bd, matmul, vec![transpose(a, &ad), grad.clone()])),
+            ]
+        }
+        Shader::Dimensional(DimensionalType::Transpose) => {
+            vec![map(&operands[0], DimensionalType::Transpose)]
+        }
+        Shader::Dimensional(DimensionalType::Reshape) => {
+            vec![map(&operands[0], DimensionalType::Reshape)]
+        }
+        Shader::Dimensional(DimensionalType::Sum) => {
+            vec![map(&operands[0], DimensionalType::Expand)]
+        }
+        // `y (g - sum(g y))` for softmax, `g - exp(y) sum(g)` for its logarithm, scaled like the
+        // scores were; masked scores are constants. The row statistics get nothing, being a
+        // function of the scores already accounted for.
+        Shader::Dimensional(DimensionalType::Softmax { axis, log, scores }) => {
+            let x = &operands[0];
+            let dims = x.dims().pull();
+            let mut reduced = dims.clone();
+            reduced[axis as usize] = 1;
+
+            let element = |ty: ElementType, lhs: &Arc<dyn Operand>, rhs: &Arc<dyn Operand>| {
+                x.derive(
+                    dims.clone(),
+                    Shader::Element(ty),
+                    vec![lhs.clone(), rhs.clone()],
+                )
+            };
+            let total = |operand: &Arc<dyn Operand>| {
+                let sum = x.derive(
+                    reduced.clone(),
+                    Shader::Dimensional(DimensionalType::Sum),
+                    vec![operand.clone()],
+                );
+                x.derive(
+                    dims.clone(),
+                    Shader::Dimensional(DimensionalType::Expand),
+                    vec![sum],
+                )
+            };
+
+            let local = if log {
+                let probs = x.derive(
+                    dims.clone(),
+                    Shader::Unary(UnaryType::Exp),
+                    vec![output.clone()],
+                );
+                element(
+                    ElementType::Sub,
+                    grad,
+                    &element(ElementType::Mul, &probs, &total(grad)),
+                )
+            } else {
+                let weighted = total(&element(ElementType::Mul, grad, output));
+                element(
+                    ElementType::Mul,
+                    output,
+                    &element(ElementType::Sub, grad, &weighted),
+                )
+            };
+            vec![Some(scaled(x, local, scores.scale)), None]
+        }
+        // `softmax(x) g`, the gradient spread back over the row it reduced.
+        Shader::Dimensional(DimensionalType::LogSumExp { axis, scores }) => {
+            let x = &operands[0];
+            let dims = x.dims().pull();
+            let probs = x.derive(
+                dims.clone(),
+                Shader::Dimensional(DimensionalType::Softmax {
+                    axis,
+                    log: false,
+                    scores,
+                }),
+                vec![x.clone(), output.clone()],
+            );
+            let spread = x.derive(
+                dims.clone(),
+                Shader::Dimensional(DimensionalType::Expand),
+                vec![grad.clone()],
+            );
+            let local = x.derive(dims, Shader::Element(ElementType::Mul), vec![probs, spread]);
+            vec![Some(scaled(x, local, scores.scale))]
+        }
+        // Flows into every element equal to the maximum, ties included.
+        Shader::Dimensional(DimensionalType::Max) => {
+            let x = &operands[0];
+            let spread = |operand: &Arc<dyn Operand>| {
+                x.derive(
+                    x.dims().pull(),
+                    Shader::Dimensional(DimensionalType::Expand),
+                    vec![operand.clone()],
+                )
+            };
+            vec![Some(x.derive(
+                x.dims().pull(),
+                Shader::Gradient(GradientType::Max),
+                vec![x.clone(), spread(output), spread(grad)],
+            ))]
+        }
+        // Summing pads the operand's dimensions back up to the gradient's rank itself.
+        Shader::Dimensional(DimensionalType::Expand) => {
+            vec![map(&operands[0], DimensionalType::Sum)]
+        }
+        Shader::Dimensional(DimensionalType::Unfold(window)) => {
+            vec![map(&operands[0], DimensionalType::Fold(window))]
+        }
+        Shader::Dimensional(DimensionalType::Fold(window)) => {
+            vec![map(&operands[0], DimensionalType::Unfold(window))]
+        }
+        Shader::Dimensional(DimensionalType::Gather) => {
+            let (table, indices) = (&operands[0], &operands[1]);
+            vec![
+                Some(table.derive(
+                    table.dims().pull(),
+                    Shader::Dimensional(DimensionalType::Scatter),
+                    vec![indices.clone(), grad.clone()],
+                )),
+                None,
+            ]
+        }
+        Shader::Dimensional(DimensionalType::Scatter) => {
+            let (indices, rows) = (&operands[0], &operands[1]);
+            vec![
+                None,
+                Some(rows.derive(
+                    rows.dims().pull(),
+                    Shader::Dimensional(DimensionalType::Gather),
+                    vec![grad.clone(), indices.clone()],
+                )),
+            ]
+        }
+        // Packed words are data, not something to differentiate.
+        Shader::Dimensional(DimensionalType::Dequantize(_)) => vec![None],
+        // Quantized weights are frozen; only the activations they multiply get a gradient, the
+        // product by the expanded weights, over rows flattened like the forward pass does.
+        Shader::Dimensional(DimensionalType::PackedMatmul { bits, block }) => {
+            let (x, scales) = (&operands[0], &operands[2]);
+            let (xd, wd) = (x.dims().pull(), operands[1].dims().pull());
+            let rows = xd.iter().product::<u32>() / wd[1];
+
+            let weights = scales.derive(
+                wd.clone(),
+                Shader::Dimensional(DimensionalType::Unpack { bits, block }),
+                operands[1..].to_vec(),
+            );
+            let flat = grad.derive(
+                vec![rows, wd[0]],
+                Shader::Dimensional(DimensionalType::Reshape),
+                vec![grad.clone()],
+            );
+            let product = x.derive(
+                vec![rows, wd[1]],
+                Shader::Dimensional(DimensionalType::Matmul),
+                vec![flat, weights],
+            );
+
+            vec![
+                Some(x.derive(
+                    xd,
+                    Shader::Dimensional(DimensionalType::Reshape),
+                    vec![product],
+                )),
+                None,
+                None,
+                None,
+            ]
+        }
+        Shader::Dimensional(DimensionalType::Unpack { .. }) => vec![None, None, None],
+        Shader::Dimensional(DimensionalType::Determinant | DimensionalType::Inverse) => {
+            return Err(Error::Unsupported(
+                "no gradient for determinant and inverse",
+            ))
+        }
+        Shader::Gradient(_) | Shader::Fill(_) => {
+            return Err(Error::Unsupported("higher order gradients"))
+        }
+    };
+
+    Ok(grads)
+}
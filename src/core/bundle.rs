@@ -1,11 +1,15 @@
 use {
+    bytemuck::NoUninit,
     std::{
-        any,
+        any::Any,
         fmt::{self, Display, Formatter},
         marker::PhantomData,
-        mem::MaybeUninit,
+        mem::{self, MaybeUninit},
         ops::{Deref, DerefMut},
-        sync::atomic::{AtomicU32, Ordering},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex, OnceLock, RwLock,
+        },
     },
     wgpu,
 };
@@ -15,11 +19,12 @@ use crate::{
     types::{Packet, SupportedPacket},
 };
 
-use super::Operation;
+use super::{ops::Shader, Instance, Node, Operand, Operation};
 
 #[derive(Clone, Copy)]
 pub(crate) enum Binding {
     Assigned(u32),
+    #[allow(dead_code)]
     Hold,
 }
 
@@ -32,33 +37,71 @@ impl Binding {
     }
 }
 
-impl Default for Binding {
-    fn default() -> Self {
-        static TRACKER: AtomicU32 = AtomicU32::new(0);
-        let binding = TRACKER.fetch_add(1, Ordering::SeqCst);
-        Binding::Assigned(binding)
-    }
-}
-
-#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
 pub(crate) enum Dimensions {
     Sized(Vec<u32>),
     Unsized,
 }
 
-#[derive(Clone, Copy)]
+impl Dimensions {
+    #[inline]
+    pub fn pull(&self) -> Vec<u32> {
+        match self {
+            Dimensions::Sized(dims) => dims.clone(),
+            Dimensions::Unsized => Vec::new(),
+        }
+    }
+
+    /// Number of elements spanned.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        match self {
+            Dimensions::Sized(dims) => dims.iter().product(),
+            Dimensions::Unsized => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
 pub(crate) enum Group {
+    #[default]
     Base,
     Custom(u32),
 }
 
-impl Default for Group {
-    fn default() -> Group {
-        Group::Base
+/// Bindings of a generated kernel's operands, in the order they are declared: numbered from 0
+/// within a group, moving on to the next group once `per_group` are taken.
+#[derive(Clone, Copy)]
+pub(crate) struct Slots {
+    per_group: u32,
+}
+
+impl Slots {
+    pub fn new(limits: &wgpu::Limits) -> Self {
+        Self {
+            per_group: limits.max_bindings_per_bind_group.max(1),
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, slot: u32) -> (Group, Binding) {
+        let group = match slot / self.per_group {
+            0 => Group::Base,
+            group => Group::Custom(group),
+        };
+
+        (group, Binding::Assigned(slot % self.per_group))
+    }
+
+    /// Groups spanned by `count` operands.
+    #[inline]
+    pub fn groups(&self, count: u32) -> u32 {
+        count.div_ceil(self.per_group)
     }
 }
 
 #[derive(Clone, Copy, Default)]
+#[allow(dead_code)]
 pub(crate) enum Memory {
     #[default]
     Static,
@@ -66,6 +109,7 @@ pub(crate) enum Memory {
 }
 
 #[derive(Clone, Copy, Default)]
+#[allow(dead_code)]
 pub(crate) enum Relay {
     #[default]
     Skip,
@@ -74,6 +118,7 @@ pub(crate) enum Relay {
 }
 
 impl Relay {
+    #[allow(dead_code)]
     #[inline]
     fn exhausted(&self) -> bool {
         match self {
@@ -172,10 +217,9 @@ pub(crate) enum Layout {
     Dyn,
 }
 
+#[allow(dead_code)]
 pub(crate) struct Properties {
-    pub binding: Binding,
     pub dims: Dimensions,
-    pub group: Group,
     pub relay: Relay,
     pub storage: Storage,
 }
@@ -185,34 +229,28 @@ impl Properties {
     where
         Packet<T>: SupportedPacket,
     {
-        let props = match layout {
+        match layout {
             Layout::Init => Self {
                 dims: Dimensions::Sized(dims),
                 ..Default::default()
             },
             Layout::Future => Self {
-                binding: Binding::Hold,
                 dims: Dimensions::Sized(dims),
                 relay: Relay::Operate,
                 ..Default::default()
             },
             Layout::Dyn => Self {
-                binding: Binding::Hold,
                 storage: Storage::DyArray,
                 ..Default::default()
             },
-        };
-
-        props
+        }
     }
 }
 
 impl Default for Properties {
     fn default() -> Self {
         Self {
-            binding: Binding::default(),
             dims: Dimensions::Unsized,
-            group: Group::default(),
             relay: Relay::default(),
             storage: Storage::default(),
         }
@@ -233,9 +271,30 @@ impl BufferHolder {
     }
 
     pub fn initialize(&mut self, _buffer: Buffer) {
+        if self.init {
+            unsafe { self.buffer.assume_init_drop() };
+        }
+
         self.init = true;
         self.buffer.write(_buffer);
     }
+
+    /// Give the buffer back, leaving the holder uninitialized.
+    pub fn release(&mut self) {
+        if self.init {
+            self.init = false;
+            unsafe { self.buffer.assume_init_drop() };
+        }
+    }
+}
+
+impl Drop for BufferHolder {
+    /// Hands the buffer back to the pool once the owning bundle goes away.
+    fn drop(&mut self) {
+        if self.init {
+            unsafe { self.buffer.assume_init_drop() };
+        }
+    }
 }
 
 impl Deref for BufferHolder {
@@ -252,18 +311,66 @@ impl DerefMut for BufferHolder {
     }
 }
 
+/// Host bytes of a tensor, kept in the vector they came in. A `Vec<T>` can't be reinterpreted
+/// as a `Vec<u8>` in place, since it must be freed with `T`'s alignment, and copying it would
+/// double the peak memory of the largest uploads.
+#[derive(Clone)]
+pub(crate) struct Contents(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+struct Elements<T>(Vec<T>);
+
+impl<T: NoUninit> AsRef<[u8]> for Elements<T> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        bytemuck::cast_slice(&self.0)
+    }
+}
+
+impl<T> From<Vec<T>> for Contents
+where
+    T: NoUninit + Send + Sync,
+{
+    fn from(values: Vec<T>) -> Self {
+        Self(Arc::new(Elements(values)))
+    }
+}
+
+impl Contents {
+    /// Bytes held by something other than a vector, e.g. a region of a mapped file.
+    pub(crate) fn new(bytes: impl AsRef<[u8]> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(bytes))
+    }
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        (*self.0).as_ref()
+    }
+}
+
 /// Interface on top of the toolkit's wrapper for buffers, used for shader generation and extends
 /// to other api-related structures.
-
 pub(crate) struct Bundle<T>
 where
     Packet<T>: SupportedPacket,
 {
-    pub buffer: BufferHolder,
+    pub buffer: RwLock<BufferHolder>,
     pub layout: Layout,
     pub props: Properties,
     pub op: Option<Operation<T>>,
-
+    pub grad: Mutex<Option<Arc<dyn Operand>>>,
+    pub tracked: AtomicBool,
+    /// Written in place (e.g. optimizer state), so its contents can never be recomputed.
+    pub pinned: AtomicBool,
+    /// Instance whose device holds the contents, set once bound or first resolved.
+    pub owner: OnceLock<Instance>,
+
+    staged: Mutex<Option<Contents>>,
+    /// Host copy of the last upload, kept to survive the loss of the device.
+    mirror: Mutex<Option<Contents>>,
     target: PhantomData<T>,
 }
 
@@ -271,45 +378,70 @@ impl<T> Bundle<T>
 where
     Packet<T>: SupportedPacket,
 {
-    pub fn bind_init(dims: Vec<u32>) -> Result<Self, wgpu::Error> {
+    pub fn bind_init(dims: Vec<u32>, contents: impl Into<Contents>) -> Result<Self, wgpu::Error> {
         let layout = Layout::default();
         let props = Properties::construct::<T>(layout, dims);
 
         let bundle = Self {
-            buffer: BufferHolder::new(),
+            buffer: RwLock::new(BufferHolder::new()),
             layout,
             props,
             op: None,
+            grad: Mutex::new(None),
+            tracked: AtomicBool::new(false),
+            pinned: AtomicBool::new(false),
+            owner: OnceLock::new(),
+            staged: Mutex::new(Some(contents.into())),
+            mirror: Mutex::new(None),
             target: PhantomData,
         };
 
         Ok(bundle)
     }
 
+    /// Results live wherever their operands do; the first bound operand decides.
     pub fn bind_future(dims: Vec<u32>, op: Operation<T>) -> Result<Self, wgpu::Error> {
         let layout = Layout::Future;
         let props = Properties::construct::<T>(layout, dims);
 
+        let owner = OnceLock::new();
+        if let Some(instance) = op.operands.iter().find_map(|operand| operand.owner()) {
+            let _ = owner.set(instance.clone());
+        }
+
         let bundle = Self {
-            buffer: BufferHolder::new(),
+            buffer: RwLock::new(BufferHolder::new()),
             layout,
             props,
             op: Some(op),
+            grad: Mutex::new(None),
+            tracked: AtomicBool::new(false),
+            pinned: AtomicBool::new(false),
+            owner,
+            staged: Mutex::new(None),
+            mirror: Mutex::new(None),
             target: PhantomData,
         };
 
         Ok(bundle)
     }
 
+    #[allow(dead_code)]
     pub fn bind_dyn(dims: Vec<u32>) -> Result<Self, wgpu::Error> {
         let layout = Layout::Dyn;
         let props = Properties::construct::<T>(layout, dims);
 
         let bundle = Self {
-            buffer: BufferHolder::new(),
+            buffer: RwLock::new(BufferHolder::new()),
             layout,
             props,
             op: None,
+            grad: Mutex::new(None),
+            tracked: AtomicBool::new(false),
+            pinned: AtomicBool::new(false),
+            owner: OnceLock::new(),
+            staged: Mutex::new(None),
+            mirror: Mutex::new(None),
             target: PhantomData,
         };
 
@@ -318,22 +450,137 @@ where
 
     #[inline]
     pub fn ready(&self) -> bool {
-        self.buffer.init
+        self.buffer.read().unwrap().init
     }
 
+    /// Type shaders read the contents as.
     #[inline]
     pub fn typename(&self) -> &'static str {
-        any::type_name::<T>()
+        <Packet<T> as SupportedPacket>::SHADER
     }
 
     /// Map to CPU and update if requested.
+    #[allow(dead_code)]
     fn map(&self) {}
 
     /// Retrieve values if dynamic.
+    #[allow(dead_code)]
     fn poll(&mut self) {
-        match self.layout {
-            Layout::Dyn => {}
-            _ => {}
+        if let Layout::Dyn = self.layout {}
+    }
+}
+
+impl<T> Operand for Bundle<T>
+where
+    Packet<T>: SupportedPacket,
+    T: Send + Sync + 'static,
+{
+    #[inline]
+    fn dims(&self) -> &Dimensions {
+        &self.props.dims
+    }
+
+    #[inline]
+    fn holder(&self) -> &RwLock<BufferHolder> {
+        &self.buffer
+    }
+
+    #[inline]
+    fn node(&self) -> Option<&dyn Node> {
+        self.op.as_ref().map(|op| op as &dyn Node)
+    }
+
+    #[inline]
+    fn props(&self) -> &Properties {
+        &self.props
+    }
+
+    #[inline]
+    fn ready(&self) -> bool {
+        Bundle::ready(self)
+    }
+
+    #[inline]
+    fn stride(&self) -> u64 {
+        mem::size_of::<T>() as u64
+    }
+
+    #[inline]
+    fn typename(&self) -> &'static str {
+        Bundle::typename(self)
+    }
+
+    #[inline]
+    fn unstage(&self) -> Option<Contents> {
+        self.staged.lock().unwrap().take()
+    }
+
+    #[inline]
+    fn restage(&self, contents: Contents) {
+        *self.staged.lock().unwrap() = Some(contents);
+    }
+
+    #[inline]
+    fn staged(&self) -> bool {
+        self.staged.lock().unwrap().is_some()
+    }
+
+    #[inline]
+    fn mirror(&self) -> &Mutex<Option<Contents>> {
+        &self.mirror
+    }
+
+    fn derive(
+        &self,
+        dims: Vec<u32>,
+        ty: Shader,
+        operands: Vec<Arc<dyn Operand>>,
+    ) -> Arc<dyn Operand> {
+        let op = Operation::<T>::from_operands(operands, ty);
+        Arc::new(Bundle::bind_future(dims, op).unwrap())
+    }
+
+    fn stage(&self, contents: Contents) -> Arc<dyn Operand> {
+        let bundle = Bundle::<T>::bind_init(self.props.dims.pull(), contents).unwrap();
+        if let Some(instance) = self.owner.get() {
+            let _ = bundle.owner.set(instance.clone());
         }
+
+        Arc::new(bundle)
+    }
+
+    #[inline]
+    fn erase(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+
+    #[inline]
+    fn grad(&self) -> &Mutex<Option<Arc<dyn Operand>>> {
+        &self.grad
+    }
+
+    #[inline]
+    fn tracked(&self) -> bool {
+        self.tracked.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn owner(&self) -> Option<&Instance> {
+        self.owner.get()
+    }
+
+    #[inline]
+    fn claim(&self, instance: &Instance) -> bool {
+        self.owner.get_or_init(|| instance.clone()) == instance
+    }
+
+    #[inline]
+    fn pin(&self) {
+        self.pinned.store(true, Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn pinned(&self) -> bool {
+        self.pinned.load(Ordering::Relaxed)
     }
 }
@@ -1,11 +1,86 @@
 // wip custom error type, still unsure whether to standarize it
 
-use std::{fmt, result};
+use std::{error, fmt, io, result};
+
+use wgpu;
 
 #[derive(Clone, Debug)]
-pub(crate) enum Error {
+pub enum Error {
+    /// An allocation would push device memory past the instance's budget.
+    OutOfMemory {
+        requested: u64,
+        budget: u64,
+    },
+    /// The device rejected a command; a bug in the generated kernels or in their inputs.
+    Validation(String),
+    /// The device itself ran out of memory, regardless of any budget.
+    DeviceOutOfMemory,
+    /// No adapter is available, or the driver refused to create a device on it.
+    Device(String),
+    /// The device went away, e.g. after a driver reset; see `Instance::recover`.
+    DeviceLost,
+    /// Tensors bound to different instances were combined, or resolved on the wrong one.
+    Mismatch,
+    /// Reading or writing a file failed.
+    Io(String),
+    /// A file is malformed or doesn't hold what was asked for.
+    Format(String),
+    /// Operators of an imported graph that have no counterpart among tensor operations, by
+    /// type, or by node for operators only some forms of which are supported.
+    Unmapped(Vec<String>),
+    /// An imported graph is inconsistent, or was run without one of its inputs.
+    Graph(String),
     Toolkit,
+    Unsupported(&'static str),
     Wgpu,
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::OutOfMemory { requested, budget } => write!(
+                f,
+                "out of device memory: {} bytes requested over a {} bytes budget",
+                requested, budget
+            ),
+            Error::Validation(description) => write!(f, "validation error: {}", description),
+            Error::DeviceOutOfMemory => write!(f, "device out of memory"),
+            Error::Device(reason) => write!(f, "no device: {}", reason),
+            Error::DeviceLost => write!(f, "device lost"),
+            Error::Mismatch => write!(f, "tensors belong to different instances"),
+            Error::Io(reason) => write!(f, "i/o error: {}", reason),
+            Error::Format(reason) => write!(f, "malformed file: {}", reason),
+            Error::Unmapped(operators) => {
+                write!(f, "unsupported operators: {}", operators.join(", "))
+            }
+            Error::Graph(reason) => write!(f, "invalid graph: {}", reason),
+            Error::Toolkit => write!(f, "zelkova internal error"),
+            Error::Unsupported(what) => write!(f, "unsupported operation: {}", what),
+            Error::Wgpu => write!(f, "wgpu error"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error.to_string())
+    }
+}
+
+impl From<wgpu::Error> for Error {
+    fn from(error: wgpu::Error) -> Self {
+        match error {
+            wgpu::Error::OutOfMemory { .. } => Error::DeviceOutOfMemory,
+            wgpu::Error::Validation { description, .. }
+                if description.contains("device is lost") =>
+            {
+                Error::DeviceLost
+            }
+            wgpu::Error::Validation { description, .. } => Error::Validation(description),
+        }
+    }
+}
+
 pub(crate) type ResultTk<T> = result::Result<T, Error>;
@@ -0,0 +1,157 @@
+// Data parallelism over several devices, each holding a full replica of the parameters.
+
+use crate::{
+    api::{Tensor, TensorOrder},
+    types::{Component, Packet, SupportedPacket},
+};
+
+use super::{instance::InstanceOpts, Bundle, Error, Instance, Operand, ResultTk};
+
+/// Instances driven in lockstep for data-parallel training: every member gets a slice of the
+/// batch and its own copy of the parameters, gradients being averaged across members after
+/// each backward pass so the replicas stay identical.
+///
+/// Members need not sit on distinct adapters; several instances on the same adapter each get a
+/// device of their own, which allows running a group on a single (software) adapter.
+#[derive(Clone)]
+pub struct InstanceGroup {
+    members: Vec<Instance>,
+}
+
+impl InstanceGroup {
+    /// Group spanning every adapter available.
+    pub fn init() -> Result<Self, Error> {
+        Self::with_opts(InstanceOpts::default())
+    }
+
+    pub fn with_opts(opts: InstanceOpts) -> Result<Self, Error> {
+        let members = Instance::enumerate(opts)?;
+        Self::from_instances(members)
+    }
+
+    /// Group over instances set up by hand, in the order batches get sharded over.
+    pub fn from_instances(members: Vec<Instance>) -> Result<Self, Error> {
+        if members.is_empty() {
+            return Err(Error::Unsupported("instance group without instances"));
+        }
+
+        Ok(Self { members })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    #[inline]
+    pub fn instances(&self) -> &[Instance] {
+        &self.members
+    }
+
+    /// Split a batch along its leading axis, one contiguous shard per member. Shards differ by at
+    /// most a row when the batch doesn't divide evenly; `src` must hold exactly what `order`
+    /// describes.
+    pub fn shard<T, U>(&self, src: &[T], order: TensorOrder) -> ResultTk<Vec<Tensor<'static, T, U>>>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        if src.len() != order.size() as usize {
+            return Err(Error::Mismatch);
+        }
+
+        let dims = order.pull();
+        let rows = *dims
+            .first()
+            .ok_or(Error::Unsupported("sharding a scalar"))? as usize;
+        if rows < self.len() {
+            return Err(Error::Unsupported(
+                "fewer rows than instances to shard over",
+            ));
+        }
+
+        let stride = src.len() / rows;
+        let (base, extra) = (rows / self.len(), rows % self.len());
+        let mut start = 0;
+
+        let shards = self
+            .members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| {
+                let count = base + usize::from(i < extra);
+                let rows = &src[start * stride..(start + count) * stride];
+                start += count;
+
+                let mut dims = dims.clone();
+                dims[0] = count as u32;
+                member.tensor(rows.to_vec(), TensorOrder::new(dims))
+            })
+            .collect();
+
+        Ok(shards)
+    }
+
+    /// Copy of `src` bound to every member, in member order; typically parameters, which then
+    /// each get their own optimizer.
+    pub fn replicate<T, U>(&self, src: &[T], order: TensorOrder) -> Vec<Tensor<'static, T, U>>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        self.members
+            .iter()
+            .map(|member| member.tensor(src.to_vec(), order.clone()))
+            .collect()
+    }
+
+    /// Average the gradients of a replicated tensor across members, one replica per member in
+    /// member order. Gradients are gathered on the first member through the host, reduced there
+    /// and broadcast back the same way; replicas left without a gradient count as zero.
+    pub fn all_reduce<T, U>(&self, replicas: &[Tensor<'_, T, U>]) -> ResultTk<()>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        if replicas.len() != self.len() {
+            return Err(Error::Mismatch);
+        }
+
+        let root = &self.members[0];
+        let mut total: Option<Tensor<'_, T, U>> = None;
+        for (replica, member) in replicas.iter().zip(self.members.iter()) {
+            if replica.instance().is_some_and(|owner| owner != *member) {
+                return Err(Error::Mismatch);
+            }
+            let Some(grad) = replica.grad() else {
+                continue;
+            };
+
+            let grad = grad.to(root)?;
+            total = Some(match total {
+                Some(total) => &total + &grad,
+                None => grad,
+            });
+        }
+
+        let Some(total) = total else {
+            return Ok(());
+        };
+        let mean = total.scale(1.0 / self.len() as f32);
+
+        for (replica, member) in replicas.iter().zip(self.members.iter()) {
+            let grad = mean.to(member)?;
+            *replica.fetch().grad.lock().unwrap() = Some(grad.operand());
+        }
+
+        Ok(())
+    }
+}
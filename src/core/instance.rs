@@ -1,25 +1,1021 @@
-use {std::default::Default, wgpu};
+use {
+    std::{
+        borrow::Cow,
+        collections::{HashMap, HashSet},
+        default::Default,
+        iter, mem,
+        ops::{Deref, DerefMut},
+        ptr,
+        sync::{Arc, Mutex, MutexGuard, RwLock, Weak},
+        time::Instant,
+    },
+    wgpu,
+};
 
-use crate::internals::Handler;
+use crate::{
+    api::{Tensor, TensorOrder},
+    internals::{
+        buffer::BufferMeta, pool::Pool, AdapterInfo, Buffer, BufferType, Handler, MemoryStats,
+        PoolStats,
+    },
+    shaders::{builder::ShaderCore, Fusion, Kernel, Module, Update},
+    types::{Component, Packet, SupportedPacket},
+};
 
-pub struct InstanceOpts;
+use super::{
+    bundle::{Fetch, Slots},
+    ops::Workgroup,
+    profile::{Clock, Profile},
+    Bundle, Error, Node, Operand, ResultTk,
+};
+
+#[derive(Clone)]
+pub struct InstanceOpts {
+    /// Upper bound in bytes on the device memory the instance may hold, pool cache included.
+    pub budget: Option<u64>,
+    /// Once over budget, free the least recently used tensors before failing: intermediates are
+    /// recomputed on demand, while uploaded data and in-place state are offloaded to host RAM
+    /// and uploaded again when next read.
+    pub evict: bool,
+    /// Benchmark candidate workgroup sizes the first time a kind of kernel runs on a given
+    /// size of tensor, instead of going by a fixed heuristic. Needs timestamp queries.
+    pub tune: bool,
+    /// Keep a host copy of every upload, so `recover` can restore tensors after the device is
+    /// lost; costs as much host memory as the uploads themselves.
+    pub mirror: bool,
+}
 
 impl Default for InstanceOpts {
     fn default() -> Self {
-        Self
+        Self {
+            budget: None,
+            evict: true,
+            tune: false,
+            mirror: false,
+        }
     }
 }
 
+/// Resolved operand known to the instance, along with the last time a kernel read it.
+struct Resident {
+    operand: Weak<dyn Operand>,
+    used: u64,
+}
+
+/// Scheduling state shared by every clone of an `Instance`, only touched under its lock.
+#[derive(Default)]
+struct Engine {
+    pipelines: HashMap<String, Arc<wgpu::ComputePipeline>>,
+    /// Tuned workgroup sizes by kind of kernel and power-of-two size bucket. Like compiled
+    /// pipelines, they live with the instance and hence are specific to its adapter.
+    tuned: HashMap<(String, u32), Workgroup>,
+    /// Candidates for eviction, ordered by `used` when picking one.
+    resident: Vec<Resident>,
+    /// Operands the kernels being prepared read from, which must stay on the device.
+    holding: Vec<usize>,
+    clock: u64,
+    /// Timings of the resolve being profiled, if any.
+    profiling: Option<Profile>,
+}
+
+struct Shared {
+    /// Swapped for a fresh one by `recover` once the device is lost.
+    handler: RwLock<Arc<Handler>>,
+    opts: InstanceOpts,
+    engine: Mutex<Engine>,
+}
+
+/// Handle on a device. Clones are cheap and share the same device, caches and memory, and can
+/// be used from any thread: resolving is serialized per device, while reading results back only
+/// waits on the copy being read.
+///
+/// Tensors get bound to the instance they are created through or first resolved on, and can
+/// only be combined with tensors of the same one; `Tensor::to` copies them across.
+#[derive(Clone)]
 pub struct Instance {
-    handler: Handler,
+    shared: Arc<Shared>,
+}
+
+/// Instances compare equal when they are clones of one another.
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
 }
 
+impl Eq for Instance {}
+
+// Instances are meant to be handed around threads and tasks.
+const _: fn() = || {
+    fn shareable<T: Clone + Send + Sync>() {}
+    shareable::<Instance>();
+};
+
 impl Instance {
-    pub fn init() -> Result<Self, wgpu::Error> {
-        let handler = Handler::request()?;
+    pub fn init() -> Result<Self, Error> {
+        Self::with_opts(InstanceOpts::default())
+    }
+
+    pub fn with_opts(opts: InstanceOpts) -> Result<Self, Error> {
+        Ok(Self::from_handler(Handler::request()?, opts))
+    }
+
+    pub async fn init_async() -> Result<Self, Error> {
+        Self::with_opts_async(InstanceOpts::default()).await
+    }
+
+    pub async fn with_opts_async(opts: InstanceOpts) -> Result<Self, Error> {
+        Ok(Self::from_handler(Handler::request_async().await?, opts))
+    }
+
+    /// One instance per adapter available, e.g. for an `InstanceGroup`.
+    pub fn enumerate(opts: InstanceOpts) -> Result<Vec<Self>, Error> {
+        let instances = Handler::request_all()?
+            .into_iter()
+            .map(|handler| Self::from_handler(handler, opts.clone()))
+            .collect();
+
+        Ok(instances)
+    }
 
-        let instance = Self { handler };
+    /// Instance on a specific adapter, by its position in `zelkova::adapters()`.
+    pub fn on_adapter(index: usize, opts: InstanceOpts) -> Result<Self, Error> {
+        let handler =
+            Handler::request_nth(index).ok_or(Error::Unsupported("no adapter at this index"))??;
 
-        Ok(instance)
+        Ok(Self::from_handler(handler, opts))
     }
+
+    fn from_handler(handler: Handler, opts: InstanceOpts) -> Self {
+        let shared = Shared {
+            handler: RwLock::new(Arc::new(handler)),
+            opts,
+            engine: Mutex::default(),
+        };
+
+        Self {
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// New tensor bound to this instance, uploaded the first time it is resolved. Uploads
+    /// larger than a staging chunk are streamed in pieces, but the tensor still has to fit in a
+    /// single device buffer: resolving one past `max_buffer_size` or the storage binding limit
+    /// fails with `Error::Unsupported`.
+    pub fn tensor<T, U>(&self, src: Vec<T>, order: TensorOrder) -> Tensor<'static, T, U>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let tensor = Tensor::from_vec(src, order);
+        tensor.fetch().claim(self);
+        tensor
+    }
+
+    /// Run every pending operation `tensor` depends on, leaving its result on the device.
+    pub fn resolve<T, U>(&self, tensor: &Tensor<'_, T, U>) -> Result<(), Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        self.scoped(|session| {
+            session.materialize(tensor.fetch())?;
+            session.touch(&tensor.operand());
+
+            Ok(())
+        })
+    }
+
+    /// Same as `resolve`, timing every kernel it dispatches along the way. Timestamp queries are
+    /// used when the device supports them, the host clock otherwise.
+    pub fn resolve_profiled<T, U>(&self, tensor: &Tensor<'_, T, U>) -> Result<Profile, Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        self.scoped(|session| {
+            session.profiling = Some(Profile::begin());
+            let resolved = session.materialize(tensor.fetch());
+            let profile = session.profiling.take().unwrap();
+            resolved?;
+
+            session.touch(&tensor.operand());
+            Ok(profile)
+        })
+    }
+
+    /// Same as `resolve`, then waits for the device to be done without blocking the thread.
+    /// Large uploads stream in chunk by chunk before anything is recorded, and recording kernels
+    /// never waits on the device, except to tune them, or to evict tensors under a memory
+    /// budget.
+    pub async fn resolve_async<T, U>(&self, tensor: &Tensor<'_, T, U>) -> Result<(), Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        let operand = tensor.operand();
+        self.prefetch(&operand).await?;
+        self.scoped_async(|session| {
+            session.materialize(operand.as_ref())?;
+            session.touch(&operand);
+
+            Ok(())
+        })
+        .await?;
+        self.handler().idle().await?;
+
+        Ok(())
+    }
+
+    /// Move the contents of a resolved tensor to host RAM, freeing its device memory. The tensor
+    /// is uploaded again the next time it is needed.
+    pub fn offload<T, U>(&self, tensor: &Tensor<'_, T, U>) -> Result<(), Error>
+    where
+        T: Component,
+        Packet<U>: SupportedPacket,
+        Bundle<U>: Operand + 'static,
+    {
+        self.scoped(|session| session.spill(tensor.fetch()))
+    }
+
+    /// Adapter the instance runs on.
+    pub fn info(&self) -> AdapterInfo {
+        self.handler().info()
+    }
+
+    /// Allocation statistics of the device buffer pool.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.handler().pool_stats()
+    }
+
+    /// Device memory currently held, per element type and usage.
+    pub fn memory_stats(&self) -> MemoryStats {
+        MemoryStats {
+            budget: self.shared.opts.budget,
+            ..self.handler().memory()
+        }
+    }
+
+    /// Free every buffer cached by the pool and the upload staging ring; live tensors are left
+    /// untouched.
+    pub fn trim(&self) {
+        self.handler().trim()
+    }
+
+    /// Copy the contents of a resolved `Operand` back to the host.
+    pub(crate) fn read(&self, operand: &Arc<dyn Operand>) -> ResultTk<Vec<u8>> {
+        // Holding on to the buffer keeps it from being evicted once the scheduler is released.
+        let holder = self.scoped(|session| {
+            session.materialize(operand.as_ref())?;
+            session.touch(operand);
+            Ok(operand.holder().read().unwrap())
+        })?;
+
+        Ok(holder.read(&self.handler())?)
+    }
+
+    /// Resolve `operand` and hand its buffer over to a fresh leaf without any history. The
+    /// leaf is pinned, its contents being impossible to recompute; `operand` itself is simply
+    /// recomputed should it be needed again.
+    pub(crate) fn detach(&self, operand: &Arc<dyn Operand>) -> ResultTk<Arc<dyn Operand>> {
+        if operand.node().is_none() {
+            return Ok(operand.clone());
+        }
+
+        self.scoped(|session| {
+            session.materialize(operand.as_ref())?;
+
+            // Staging is the only way to get a leaf of the same packet type; nothing is uploaded.
+            let leaf = operand.stage(Vec::<u8>::new().into());
+            leaf.unstage();
+            mem::swap(
+                &mut *operand.holder().write().unwrap(),
+                &mut *leaf.holder().write().unwrap(),
+            );
+            leaf.pin();
+            session.touch(&leaf);
+
+            Ok(leaf)
+        })
+    }
+
+    /// Non-blocking counterpart of `read`.
+    pub(crate) async fn read_async(&self, operand: &Arc<dyn Operand>) -> ResultTk<Vec<u8>> {
+        // The copy is queued before the locks go, so nothing has to be held across the wait.
+        let handler = self.handler();
+        self.prefetch(operand).await?;
+        let pending = self
+            .scoped_async(|session| {
+                session.materialize(operand.as_ref())?;
+                session.touch(operand);
+
+                let holder = operand.holder().read().unwrap();
+                Ok(holder.read_async(&handler))
+            })
+            .await?;
+
+        Ok(pending.await?)
+    }
+
+    /// Upload the leaves `root` depends on that are large enough to stream through the staging
+    /// ring, awaiting each chunk rather than blocking on it. Buffers are set up under the
+    /// scheduler, but filled without holding it; a leaf someone else uploaded in the meantime
+    /// keeps theirs.
+    async fn prefetch(&self, root: &Arc<dyn Operand>) -> ResultTk<()> {
+        let handler = self.handler();
+        let uploads = self
+            .scoped_async(|session| {
+                let mut uploads = Vec::new();
+                for leaf in pending_uploads(root) {
+                    let contents = leaf.unstage().ok_or(Error::Toolkit)?;
+                    leaf.restage(contents.clone());
+
+                    session.reserve(contents.len() as u64)?;
+                    let buffer = Buffer::bind_raw(
+                        &session.handler,
+                        BufferType::Init,
+                        leaf.typename(),
+                        None,
+                        Some(contents.len() as u64),
+                    )
+                    .map_err(|_| Error::Wgpu)?;
+                    uploads.push((leaf, contents, buffer));
+                }
+
+                Ok(uploads)
+            })
+            .await?;
+        if uploads.is_empty() {
+            return Ok(());
+        }
+
+        for (_, contents, buffer) in uploads.iter() {
+            buffer.write_chunked(&handler, contents).await?;
+        }
+
+        self.scoped_async(|session| {
+            for (leaf, contents, buffer) in uploads {
+                if leaf.ready() || leaf.unstage().is_none() {
+                    continue;
+                }
+                leaf.holder().write().unwrap().initialize(buffer);
+                if session.opts.mirror {
+                    *leaf.mirror().lock().unwrap() = Some(contents);
+                }
+                session.touch(&leaf);
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Apply an optimizer rule in place to every parameter, given its hyperparameters. Gradients
+    /// still pending may read parameters updated earlier on, so all of them are resolved before
+    /// the first one is written.
+    pub(crate) fn step(&self, updates: &[Update], scalars: &[f32]) -> ResultTk<()> {
+        self.scoped(|session| {
+            let grads: Vec<&dyn Operand> = updates.iter().map(|update| update.grad).collect();
+
+            let depth = session.hold(&grads);
+            let stepped = grads
+                .iter()
+                .try_for_each(|grad| session.materialize(*grad))
+                .and_then(|_| {
+                    updates
+                        .iter()
+                        .try_for_each(|update| session.step(update, scalars))
+                });
+            session.holding.truncate(depth);
+
+            stepped
+        })
+    }
+
+    /// Whether the device went away; every call fails with `Error::DeviceLost` until `recover`.
+    pub fn lost(&self) -> bool {
+        self.handler().lost()
+    }
+
+    /// Replace a lost device with a fresh one on the same adapter, or the default one if it is
+    /// gone too. Tensors are restored lazily the next time they are needed: intermediates get
+    /// recomputed, uploads come back from their host copy when `InstanceOpts::mirror` is set,
+    /// and in-place state from the last `checkpoint`. Anything else fails to resolve with
+    /// `Error::DeviceLost`.
+    pub fn recover(&self) -> Result<(), Error> {
+        let mut engine = self.shared.engine.lock().unwrap();
+        let handler = Handler::request_like(&self.handler().info())?;
+
+        // Compiled pipelines belong to the old device; tuned sizes still hold for the adapter.
+        engine.pipelines.clear();
+        engine.resident.clear();
+        *self.shared.handler.write().unwrap() = Arc::new(handler);
+
+        Ok(())
+    }
+
+    /// Refresh the host copy of every tensor updated in place, e.g. by optimizers, so `recover`
+    /// restores them as of now. Only meaningful with `InstanceOpts::mirror` set.
+    pub fn checkpoint(&self) -> Result<(), Error> {
+        self.scoped(|session| {
+            let pinned: Vec<_> = session
+                .resident
+                .iter()
+                .filter_map(|resident| resident.operand.upgrade())
+                .filter(|operand| operand.pinned() && operand.ready())
+                .collect();
+
+            for operand in pinned {
+                let contents = operand.holder().read().unwrap().read(&session.handler)?;
+                *operand.mirror().lock().unwrap() = Some(contents.into());
+            }
+
+            Ok(())
+        })
+    }
+
+    #[inline]
+    fn handler(&self) -> Arc<Handler> {
+        self.shared.handler.read().unwrap().clone()
+    }
+
+    /// Run `call` on the scheduler with device errors captured, so they come back as errors
+    /// rather than aborting the process.
+    fn scoped<'i, R>(&'i self, call: impl FnOnce(&mut Session<'i>) -> ResultTk<R>) -> ResultTk<R> {
+        let mut session = self.session();
+        if session.handler.lost() {
+            return Err(Error::DeviceLost);
+        }
+
+        session.handler.scope();
+        let result = call(&mut session);
+
+        match session.handler.unscope() {
+            Some(error) => Err(error.into()),
+            None => result,
+        }
+    }
+
+    /// Non-blocking counterpart of `scoped`. Captured errors are awaited once the scheduler is
+    /// released, the scopes themselves being popped while it is still held.
+    async fn scoped_async<'i, R>(
+        &'i self,
+        call: impl FnOnce(&mut Session<'i>) -> ResultTk<R>,
+    ) -> ResultTk<R> {
+        let handler = self.handler();
+        let (result, errors) = {
+            let mut session = self.session();
+            if session.handler.lost() {
+                return Err(Error::DeviceLost);
+            }
+
+            session.handler.scope();
+            let result = call(&mut session);
+            (result, handler.unscope_async())
+        };
+
+        match errors.await {
+            Some(error) => Err(error.into()),
+            None => result,
+        }
+    }
+
+    fn session(&self) -> Session<'_> {
+        Session {
+            instance: self,
+            handler: self.handler(),
+            opts: &self.shared.opts,
+            engine: self.shared.engine.lock().unwrap(),
+        }
+    }
+}
+
+/// Exclusive use of an instance's scheduler for the duration of a call.
+struct Session<'i> {
+    instance: &'i Instance,
+    handler: Arc<Handler>,
+    opts: &'i InstanceOpts,
+    engine: MutexGuard<'i, Engine>,
+}
+
+impl Deref for Session<'_> {
+    type Target = Engine;
+
+    fn deref(&self) -> &Self::Target {
+        &self.engine
+    }
+}
+
+impl DerefMut for Session<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.engine
+    }
+}
+
+impl Session<'_> {
+    fn materialize(&mut self, operand: &dyn Operand) -> ResultTk<()> {
+        if !operand.claim(self.instance) {
+            return Err(Error::Mismatch);
+        }
+        if operand.ready() {
+            if operand.holder().read().unwrap().on(&self.handler) {
+                return Ok(());
+            }
+            self.revive(operand)?;
+        }
+
+        // Offloaded contents win over recomputing, which in-place state could not survive.
+        if operand.staged() || operand.node().is_none() {
+            return self.upload(operand);
+        }
+
+        let slots = Slots::new(self.handler.limits());
+
+        if let Some(fusion) = Fusion::trace(operand) {
+            let depth = self.hold(&fusion.inputs);
+            let prepared = fusion
+                .inputs
+                .iter()
+                .try_for_each(|input| self.materialize(*input))
+                .and_then(|_| self.allocate(operand));
+            self.holding.truncate(depth);
+            prepared?;
+
+            self.dispatch(operand, &fusion.inputs, &|workgroup| {
+                let mut module = Module::new(slots);
+                module.insert_fusion(&fusion, workgroup);
+                module
+            })
+        } else if let Some(kernel) = Kernel::trace(operand) {
+            let depth = self.hold(&kernel.inputs);
+            let prepared = kernel
+                .inputs
+                .iter()
+                .try_for_each(|input| self.materialize(*input))
+                .and_then(|_| self.allocate(operand));
+            self.holding.truncate(depth);
+            prepared?;
+
+            self.dispatch(operand, &kernel.inputs, &|workgroup| {
+                let mut module = Module::new(slots);
+                module.insert_kernel(&kernel, workgroup);
+                module
+            })
+        } else {
+            Err(Error::Unsupported("operation has no kernel"))
+        }
+    }
+
+    fn upload(&mut self, operand: &dyn Operand) -> ResultTk<()> {
+        let contents = operand.unstage().ok_or(Error::Toolkit)?;
+        if let Err(err) = self.reserve(contents.len() as u64) {
+            operand.restage(contents);
+            return Err(err);
+        }
+
+        let buffer = Buffer::bind_raw(
+            &self.handler,
+            BufferType::Init,
+            operand.typename(),
+            Some(&contents),
+            None,
+        )
+        .map_err(|_| Error::Wgpu)?;
+
+        operand.holder().write().unwrap().initialize(buffer);
+
+        // Unless mirrored, the staged bytes go out of scope here: from now on the device holds
+        // the only copy.
+        if self.opts.mirror {
+            *operand.mirror().lock().unwrap() = Some(contents);
+        }
+
+        Ok(())
+    }
+
+    /// Drop a buffer left over from a lost device, staging the host copy back if the contents
+    /// cannot be recomputed.
+    fn revive(&mut self, operand: &dyn Operand) -> ResultTk<()> {
+        operand.holder().write().unwrap().release();
+        if operand.node().is_some() && !operand.pinned() {
+            return Ok(());
+        }
+
+        let mirror = operand.mirror().lock().unwrap().clone();
+        operand.restage(mirror.ok_or(Error::DeviceLost)?);
+
+        Ok(())
+    }
+
+    /// Output buffer of a kernel; the only one a fused chain gets.
+    fn allocate(&mut self, root: &dyn Operand) -> ResultTk<()> {
+        let size = root.dims().size() as u64 * root.stride();
+        self.reserve(size)?;
+
+        let buffer = Buffer::bind_raw(
+            &self.handler,
+            BufferType::Storage,
+            root.typename(),
+            None,
+            Some(size),
+        )
+        .map_err(|_| Error::Wgpu)?;
+
+        root.holder().write().unwrap().initialize(buffer);
+
+        if let Some(node) = root.node() {
+            self.track(node);
+        }
+
+        Ok(())
+    }
+
+    /// Keep `inputs` on the device until the holding stack is truncated back to the returned
+    /// depth.
+    fn hold(&mut self, inputs: &[&dyn Operand]) -> usize {
+        let depth = self.holding.len();
+        self.holding.extend(
+            inputs
+                .iter()
+                .map(|input| *input as *const dyn Operand as *const () as usize),
+        );
+
+        depth
+    }
+
+    /// Make room for `size` more bytes: a tensor lives in a single device buffer the device caps
+    /// in size, as kernels bind every operand whole, and the whole instance stays under its budget by first dropping the pool cache,
+    /// then evicting the least recently used tensors not currently held. Both are checked
+    /// against the pool bucket actually allocated, not the bytes asked for.
+    fn reserve(&mut self, size: u64) -> ResultTk<()> {
+        let limits = self.handler.limits();
+        let requested = Pool::bucket(size, limits.max_buffer_size);
+        if requested > limits.max_buffer_size {
+            return Err(Error::Unsupported(
+                "tensor exceeds the device's maximum buffer size, and can't be split across buffers",
+            ));
+        }
+        if size > limits.max_storage_buffer_binding_size as u64 {
+            return Err(Error::Unsupported(
+                "tensor exceeds the device's maximum storage binding size",
+            ));
+        }
+
+        let Some(budget) = self.opts.budget else {
+            return Ok(());
+        };
+
+        loop {
+            let stats = self.handler.pool_stats();
+            if stats.live + stats.cached + requested <= budget {
+                return Ok(());
+            }
+
+            if stats.cached > 0 {
+                self.handler.trim();
+            } else if !(self.opts.evict && self.evict()?) {
+                return Err(Error::OutOfMemory { requested, budget });
+            }
+        }
+    }
+
+    /// Free the least recently used resident tensor, if any can go.
+    fn evict(&mut self) -> ResultTk<bool> {
+        self.resident
+            .retain(|resident| resident.operand.strong_count() > 0);
+
+        let victim = self
+            .resident
+            .iter()
+            .enumerate()
+            .filter_map(|(index, resident)| {
+                let operand = resident.operand.upgrade()?;
+                let address = Arc::as_ptr(&operand) as *const () as usize;
+                (operand.ready() && !self.holding.contains(&address))
+                    .then_some((resident.used, index))
+            })
+            .min()
+            .map(|(_, index)| index);
+
+        let Some(operand) = victim.and_then(|index| self.resident.remove(index).operand.upgrade())
+        else {
+            return Ok(false);
+        };
+
+        if operand.node().is_some() && !operand.pinned() {
+            operand.holder().write().unwrap().release();
+        } else {
+            self.spill(operand.as_ref())?;
+        }
+
+        Ok(true)
+    }
+
+    /// Copy a resolved operand to the host and give its buffer back.
+    fn spill(&mut self, operand: &dyn Operand) -> ResultTk<()> {
+        if !operand.ready() {
+            return Ok(());
+        }
+
+        let contents = {
+            let holder = operand.holder().read().unwrap();
+            holder.read(&self.handler)?
+        };
+        operand.restage(contents.into());
+        operand.holder().write().unwrap().release();
+
+        Ok(())
+    }
+
+    /// Mark the resolved operands `node` reads from as just used; fused operands are not
+    /// resolved themselves, so the walk goes through them.
+    fn track(&mut self, node: &dyn Node) {
+        // Fused chains read their intermediates many times over, so each is looked through once.
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&Arc<dyn Operand>> = node.operands().iter().rev().collect();
+
+        while let Some(operand) = stack.pop() {
+            if !seen.insert(Arc::as_ptr(operand) as *const ()) {
+                continue;
+            }
+            match operand.node() {
+                Some(inner) if !operand.ready() => stack.extend(inner.operands().iter().rev()),
+                _ if operand.ready() => self.touch(operand),
+                _ => {}
+            }
+        }
+    }
+
+    fn touch(&mut self, operand: &Arc<dyn Operand>) {
+        self.clock += 1;
+
+        let used = self.clock;
+        let known = self
+            .resident
+            .iter_mut()
+            .find(|resident| ptr::addr_eq(resident.operand.as_ptr(), Arc::as_ptr(operand)));
+
+        match known {
+            Some(resident) => resident.used = used,
+            None => self.resident.push(Resident {
+                operand: Arc::downgrade(operand),
+                used,
+            }),
+        }
+    }
+
+    /// Compile and run a generated kernel writing into `root`, `build` laying it out for a
+    /// given workgroup size.
+    fn dispatch(
+        &mut self,
+        root: &dyn Operand,
+        inputs: &[&dyn Operand],
+        build: &dyn Fn(&Workgroup) -> Module,
+    ) -> ResultTk<()> {
+        let operands: Vec<&dyn Operand> = inputs.iter().copied().chain(iter::once(root)).collect();
+        let invocations = root.dims().size();
+
+        let kind = format!("{:?}/{}", root.node().unwrap().ty(), inputs.len());
+        let workgroup = self.workgroup(kind.clone(), build, &operands, invocations)?;
+
+        self.launch(
+            &kind,
+            build(&workgroup),
+            &operands,
+            &[],
+            invocations,
+            workgroup.collapse(),
+        )
+    }
+
+    /// Workgroup size for a kernel of the given `kind`: the heuristic fit, or when tuning, the
+    /// fastest candidate as measured on the device the first time it is asked for.
+    fn workgroup(
+        &mut self,
+        kind: String,
+        build: &dyn Fn(&Workgroup) -> Module,
+        operands: &[&dyn Operand],
+        invocations: u32,
+    ) -> ResultTk<Workgroup> {
+        let limits = self.handler.limits().clone();
+        let fit = Workgroup::fit(invocations, &limits);
+        if !self.opts.tune || !self.handler.timestamps() {
+            return Ok(fit);
+        }
+
+        let key = (kind, invocations.next_power_of_two());
+        if let Some(workgroup) = self.tuned.get(&key) {
+            return Ok(*workgroup);
+        }
+
+        let mut best = (f64::INFINITY, fit);
+        for candidate in Workgroup::candidates(&limits) {
+            let pipeline = self.pipeline(build(&candidate))?;
+            let bindgroups = self.bind(&pipeline, operands, &[])?;
+            let grid = candidate.plan(invocations, &limits)?;
+
+            // The first run pays for warming caches up; only the second one counts.
+            self.handler.time(&pipeline, &bindgroups, grid);
+            if let Some(elapsed) = self.handler.time(&pipeline, &bindgroups, grid) {
+                if elapsed < best.0 {
+                    best = (elapsed, candidate);
+                }
+            }
+        }
+
+        self.tuned.insert(key, best.1);
+        Ok(best.1)
+    }
+
+    fn step(&mut self, update: &Update, scalars: &[f32]) -> ResultTk<()> {
+        let operands: Vec<&dyn Operand> = [update.param, update.grad]
+            .into_iter()
+            .chain(update.states.iter().copied())
+            .collect();
+
+        let depth = self.hold(&operands);
+        let prepared = operands
+            .iter()
+            .try_for_each(|operand| self.materialize(*operand));
+        self.holding.truncate(depth);
+        prepared?;
+
+        // Everything written in place, so `checkpoint` keeps its host copy current.
+        for operand in [update.param].iter().chain(update.states.iter()) {
+            operand.pin();
+        }
+
+        let scalars = Buffer::bind(&self.handler, BufferType::Init, Some(scalars), None)
+            .map_err(|_| Error::Wgpu)?;
+
+        // Updates write in place, so they are sized by the heuristic rather than benchmarked.
+        let size = update.param.dims().size();
+        let workgroup = Workgroup::fit(size, self.handler.limits());
+
+        let mut module = Module::new(Slots::new(self.handler.limits()));
+        module.insert_update(update, &workgroup);
+
+        self.launch(
+            "Update",
+            module,
+            &operands,
+            &[&scalars],
+            size,
+            workgroup.collapse(),
+        )
+    }
+
+    /// Bind every operand and extra buffer to a compiled `module`, then dispatch enough
+    /// workgroups to cover `invocations`. While profiling, waits for the kernel to be done and
+    /// records it under `name`.
+    fn launch(
+        &mut self,
+        name: &str,
+        module: Module,
+        operands: &[&dyn Operand],
+        extra: &[&Buffer],
+        invocations: u32,
+        lanes: u32,
+    ) -> ResultTk<()> {
+        let bound = operands.len() + extra.len();
+        if bound > self.handler.limits().max_storage_buffers_per_shader_stage as usize {
+            return Err(Error::Unsupported(
+                "kernel binds more storage buffers than the device allows",
+            ));
+        }
+
+        let pipeline = self.pipeline(module)?;
+        let bindgroups = self.bind(&pipeline, operands, extra)?;
+
+        let grid = Workgroup::Single(lanes).plan(invocations, self.handler.limits())?;
+        if self.profiling.is_none() {
+            self.submit(&pipeline, &bindgroups, grid);
+            return Ok(());
+        }
+
+        let issued = Instant::now();
+        let (duration, clock) = match self.handler.time(&pipeline, &bindgroups, grid) {
+            Some(elapsed) => (elapsed, Clock::Gpu),
+            None => {
+                self.submit(&pipeline, &bindgroups, grid);
+                self.handler.wait()?;
+                (issued.elapsed().as_nanos() as f64, Clock::Cpu)
+            }
+        };
+
+        if let Some(profile) = self.profiling.as_mut() {
+            profile.record(name, issued, duration, clock, grid);
+        }
+
+        Ok(())
+    }
+
+    fn submit(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        bindgroups: &[wgpu::BindGroup],
+        [x, y, z]: [u32; 3],
+    ) {
+        let mut encoder = self.handler.encoder();
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(pipeline);
+            for (group, bindgroup) in bindgroups.iter().enumerate() {
+                pass.set_bind_group(group as u32, bindgroup, &[]);
+            }
+            pass.dispatch_workgroups(x, y, z);
+        }
+        self.handler.submit(encoder);
+    }
+
+    /// Bind groups of `pipeline` over every operand then extra buffer, slotted the same way
+    /// the module declared them.
+    fn bind(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        operands: &[&dyn Operand],
+        extra: &[&Buffer],
+    ) -> ResultTk<Vec<wgpu::BindGroup>> {
+        let slots = Slots::new(self.handler.limits());
+        let count = (operands.len() + extra.len()) as u32;
+        if slots.groups(count) > self.handler.limits().max_bind_groups {
+            return Err(Error::Unsupported(
+                "kernel binds more buffers than the device's bind groups hold",
+            ));
+        }
+
+        let holders: Vec<_> = operands
+            .iter()
+            .map(|operand| operand.holder().read().unwrap())
+            .collect();
+        let buffers = holders
+            .iter()
+            .map(|holder| &***holder)
+            .chain(extra.iter().copied());
+
+        let mut groups: Vec<Vec<wgpu::BindGroupEntry>> = Vec::new();
+        for (slot, buffer) in buffers.enumerate() {
+            let (group, binding) = slots.at(slot as u32);
+            let meta = BufferMeta::from_buffer(binding.fetch(), buffer).map_err(|_| Error::Wgpu)?;
+
+            let group = group.fetch() as usize;
+            groups.resize_with(groups.len().max(group + 1), Vec::new);
+            groups[group].push(meta.group());
+        }
+
+        groups
+            .iter()
+            .enumerate()
+            .map(|(group, entries)| {
+                self.handler
+                    .bind_group(pipeline, group as u32, entries)
+                    .map_err(|_| Error::Wgpu)
+            })
+            .collect()
+    }
+
+    /// Compiled pipeline of `module`, reused across resolves as long as the source matches.
+    fn pipeline(&mut self, module: Module) -> ResultTk<Arc<wgpu::ComputePipeline>> {
+        let source = module.wrap().into_owned();
+        if let Some(pipeline) = self.pipelines.get(&source) {
+            return Ok(pipeline.clone());
+        }
+
+        let (_, pipeline) = self
+            .handler
+            .load_module(Cow::from(source.as_str()))
+            .map_err(|_| Error::Wgpu)?;
+        let pipeline = Arc::new(pipeline);
+        self.pipelines.insert(source, pipeline.clone());
+
+        Ok(pipeline)
+    }
+}
+
+/// Staged leaves `root` depends on that are large enough to be streamed in chunks. Resolved
+/// operands cut the walk short, as nothing behind them has to run.
+fn pending_uploads(root: &Arc<dyn Operand>) -> Vec<Arc<dyn Operand>> {
+    let mut pending = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![root.clone()];
+
+    while let Some(operand) = stack.pop() {
+        if !seen.insert(Arc::as_ptr(&operand) as *const () as usize) || operand.ready() {
+            continue;
+        }
+        if operand.staged() {
+            let size = operand.dims().size() as u64 * operand.stride();
+            if Handler::chunked(size) {
+                pending.push(operand);
+            }
+        } else if let Some(node) = operand.node() {
+            stack.extend(node.operands().iter().cloned());
+        }
+    }
+
+    pending
 }
@@ -1,8 +1,13 @@
-//pub(crate) mod error;
+pub(crate) mod autograd;
 pub(crate) mod bundle;
+pub(crate) mod error;
+pub(crate) mod group;
 pub(crate) mod instance;
 pub(crate) mod ops;
+pub(crate) mod profile;
 
-pub(crate) use bundle::Bundle;
+pub(crate) use autograd::Tape;
+pub(crate) use bundle::{Bundle, Contents};
+pub(crate) use error::{Error, ResultTk};
 pub(crate) use instance::Instance;
-pub(crate) use ops::Operation;
+pub(crate) use ops::{Node, Operand, Operation};
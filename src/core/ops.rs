@@ -1,54 +1,275 @@
-use std::marker::PhantomData;
+use std::{
+    any::Any,
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex, RwLock},
+};
 
-use super::Bundle;
+use super::{
+    bundle::{BufferHolder, Contents},
+    Bundle, Error, Instance, ResultTk,
+};
 
-use crate::types::{Packet, SupportedPacket};
+use crate::{
+    core::bundle::{Dimensions, Properties},
+    shaders::{BundleShader, OperationShader},
+    types::{Packet, SupportedPacket},
+};
 
 #[derive(Clone, Copy, Default)]
 pub(crate) enum State {
     #[default]
     Pending,
+    #[allow(dead_code)]
     Done,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum ElementType {
     Add,
     Sub,
     Mul,
     Div,
+    #[allow(dead_code)]
     Exp,
+    #[allow(dead_code)]
     Rot,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ActivationType {
+    Relu,
+    Sigmoid,
+    Tanh,
+    Gelu,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum UnaryType {
+    Abs,
+    Exp,
+    Log,
+    Neg,
+    Sqrt,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Side {
+    Lhs,
+    Rhs,
+}
+
+/// Local derivatives of elementwise operations; operands are the forward ones followed by the
+/// upstream gradient.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum GradientType {
+    Element(ElementType, Side),
+    Activation(ActivationType),
+    Unary(UnaryType),
+    /// Operands are the input, its reduced maximum and the upstream gradient, both broadcast
+    /// back to the input's dimensions.
+    Max,
+}
+
+/// Sliding window over the two innermost axes of an image, as `(y, x)` pairs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Window {
+    pub kernel: (u32, u32),
+    pub stride: (u32, u32),
+    pub padding: (u32, u32),
+}
+
+impl Window {
+    /// Output extent along both axes for an input of `(height, width)`.
+    #[inline]
+    pub fn extent(&self, (height, width): (u32, u32)) -> (u32, u32) {
+        (
+            (height + 2 * self.padding.0 - self.kernel.0) / self.stride.0 + 1,
+            (width + 2 * self.padding.1 - self.kernel.1) / self.stride.1 + 1,
+        )
+    }
+}
+
+/// Adjustment of the scores a softmax normalizes: scaled, then, when `causal`, masked over the
+/// two innermost axes so no query attends to a later key, the last query lining up with the
+/// last key. Masked scores stay finite so a fully masked row can't turn the normalizer into NaN.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Scores {
+    pub scale: f32,
+    pub causal: bool,
+}
+
+impl Scores {
+    pub const PLAIN: Self = Self {
+        scale: 1.0,
+        causal: false,
+    };
+}
+
+/// Packed layouts weights ship in, as found in GGUF files. Quantized ones store blocks of
+/// `block` elements behind f16 scales, and offsets for the `_1` variants.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Quant {
+    F16,
+    BF16,
+    Q4_0,
+    Q4_1,
+    Q5_0,
+    Q5_1,
+    Q8_0,
+}
+
+impl Quant {
+    /// Elements per block.
+    #[inline]
+    pub fn block(&self) -> u32 {
+        match self {
+            Quant::F16 | Quant::BF16 => 1,
+            _ => 32,
+        }
+    }
+
+    /// Bytes per block.
+    #[inline]
+    pub fn bytes(&self) -> u32 {
+        match self {
+            Quant::F16 | Quant::BF16 => 2,
+            Quant::Q4_0 => 18,
+            Quant::Q4_1 => 20,
+            Quant::Q5_0 => 22,
+            Quant::Q5_1 => 24,
+            Quant::Q8_0 => 34,
+        }
+    }
+}
+
+/// Operations whose output isn't laid out element by element with their operands; reductions
+/// and broadcasts infer their axes from the operand and output dimensions.
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum DimensionalType {
     Sum,
+    /// Unpack elements stored in a packed layout, the operand being its raw words.
+    Dequantize(Quant),
+    #[allow(dead_code)]
     Determinant,
+    Expand,
+    /// Inverse of `Unfold`, summing overlapping patches back into an image.
+    Fold(Window),
+    /// Rows of a table picked by an index operand.
+    Gather,
+    #[allow(dead_code)]
     Inverse,
+    Matmul,
+    Max,
+    /// Product of the left-hand side by the transpose of `[output, input]` integer weights of
+    /// `bits`, unpacked within the reduction; scales and zero points follow the weights, one
+    /// pair per `block` inputs of every output.
+    PackedMatmul {
+        bits: u32,
+        block: u32,
+    },
+    Reshape,
+    /// Inverse of `Gather`, summing rows back into the table they were picked from.
+    Scatter,
+    /// Logarithm of the summed exponentials of the adjusted scores along `axis`, reduced to a
+    /// single element in one online pass per row.
+    LogSumExp {
+        axis: u32,
+        scores: Scores,
+    },
+    /// Normalized exponentials of the adjusted scores along `axis`, or their logarithm; operands
+    /// are the scores and their `LogSumExp`.
+    Softmax {
+        axis: u32,
+        log: bool,
+        scores: Scores,
+    },
     Transpose,
+    /// Patches of an image laid out as rows, for convolutions to run as a matmul.
+    Unfold(Window),
+    /// Integer weights, scales and zero points as for `PackedMatmul`, expanded back to
+    /// floats of the same `[output, input]` layout.
+    Unpack {
+        bits: u32,
+        block: u32,
+    },
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Copy)]
 enum BoundCPU {}
 
+#[allow(dead_code)]
 #[derive(Clone, Copy)]
 enum BoundGPU {}
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum Shader {
     Element(ElementType),
     Dimensional(DimensionalType),
+    Activation(ActivationType),
+    Gradient(GradientType),
+    Unary(UnaryType),
+    Fill(f32),
 }
 
-#[derive(Clone, Copy)]
+impl Shader {
+    /// Whether the output maps one to one onto its operands' elements, hence fusible.
+    #[inline]
+    pub fn elementwise(&self) -> bool {
+        !matches!(self, Shader::Dimensional(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum Workgroup {
     Single(u32),
+    #[allow(dead_code)]
     Duplet(u32, u32),
+    #[allow(dead_code)]
     Triplet(u32, u32, u32),
 }
 
+impl Default for Workgroup {
+    fn default() -> Self {
+        Workgroup::Single(64)
+    }
+}
+
+/// Lane counts tried when tuning, smallest first.
+const LANES: [u32; 4] = [32, 64, 128, 256];
+
 impl Workgroup {
+    /// Sizes the device accepts, for tuning to pick from.
+    pub fn candidates(limits: &wgpu::Limits) -> impl Iterator<Item = Workgroup> + '_ {
+        LANES
+            .into_iter()
+            .filter(|lanes| {
+                *lanes <= limits.max_compute_workgroup_size_x
+                    && *lanes <= limits.max_compute_invocations_per_workgroup
+            })
+            .map(Workgroup::Single)
+    }
+
+    /// Size picked without measuring: wide workgroups once there are plenty of them, narrower
+    /// ones for small tensors so the work still spreads over every compute unit.
+    pub fn fit(invocations: u32, limits: &wgpu::Limits) -> Workgroup {
+        let lanes = match invocations {
+            n if n >= 1 << 20 => 256,
+            n if n >= 1 << 16 => 128,
+            _ => 64,
+        };
+
+        Self::candidates(limits)
+            .take_while(|candidate| candidate.collapse() <= lanes)
+            .last()
+            .unwrap_or(Workgroup::Single(
+                limits
+                    .max_compute_workgroup_size_x
+                    .min(limits.max_compute_invocations_per_workgroup),
+            ))
+    }
+
     pub fn collapse(&self) -> u32 {
         match self {
             Workgroup::Single(x) => *x,
@@ -56,6 +277,154 @@ impl Workgroup {
             Workgroup::Triplet(x, y, z) => x * y * z,
         }
     }
+
+    /// Grid of workgroups covering `invocations`. Shaders lay a workgroup out as a single row
+    /// of `collapse()` lanes, and flatten the grid back into a linear index, so whenever a
+    /// single dimension is not enough the remainder spills over the next ones.
+    pub fn plan(&self, invocations: u32, limits: &wgpu::Limits) -> ResultTk<[u32; 3]> {
+        let lanes = self.collapse();
+        if lanes > limits.max_compute_workgroup_size_x
+            || lanes > limits.max_compute_invocations_per_workgroup
+        {
+            return Err(Error::Unsupported(
+                "workgroup size exceeds the device's limits",
+            ));
+        }
+
+        let max = limits.max_compute_workgroups_per_dimension;
+        let groups = invocations.div_ceil(lanes).max(1);
+
+        let x = groups.min(max);
+        let y = groups.div_ceil(x);
+        let (y, z) = match y > max {
+            true => (max, y.div_ceil(max)),
+            false => (y, 1),
+        };
+
+        if z > max {
+            return Err(Error::Unsupported(
+                "dispatch exceeds the device's workgroup grid",
+            ));
+        }
+
+        Ok([x, y, z])
+    }
+}
+
+/// Type-erased view over a `Bundle`, letting an `Operation` hold operands of any packet type.
+pub(crate) trait Operand: BundleShader + Send + Sync {
+    fn dims(&self) -> &Dimensions;
+    fn holder(&self) -> &RwLock<BufferHolder>;
+    fn node(&self) -> Option<&dyn Node>;
+    #[allow(dead_code)]
+    fn props(&self) -> &Properties;
+    fn ready(&self) -> bool;
+    /// Size in bytes of a single element.
+    fn stride(&self) -> u64;
+    fn typename(&self) -> &'static str;
+    /// Take the host contents awaiting upload, if any.
+    fn unstage(&self) -> Option<Contents>;
+    /// Put host contents back for upload, e.g. once offloaded from the device.
+    fn restage(&self, contents: Contents);
+    fn staged(&self) -> bool;
+    /// Host copy of the last upload, only kept when the instance mirrors uploads.
+    fn mirror(&self) -> &Mutex<Option<Contents>>;
+
+    /// Record a new operation whose output shares this operand's packet type.
+    fn derive(
+        &self,
+        dims: Vec<u32>,
+        ty: Shader,
+        operands: Vec<Arc<dyn Operand>>,
+    ) -> Arc<dyn Operand>;
+    /// New operand sharing this one's packet type and dimensions, holding `contents`.
+    fn stage(&self, contents: Contents) -> Arc<dyn Operand>;
+    fn erase(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+
+    /// Accumulated gradient, only kept for tracked operands.
+    fn grad(&self) -> &Mutex<Option<Arc<dyn Operand>>>;
+    fn tracked(&self) -> bool;
+    /// Instance the operand is bound to, if any.
+    fn owner(&self) -> Option<&Instance>;
+    /// Bind to `instance` unless already bound; whether it ends up bound to it.
+    fn claim(&self, instance: &Instance) -> bool;
+    /// Mark the contents as impossible to recompute, keeping them off the eviction list.
+    fn pin(&self);
+    fn pinned(&self) -> bool;
+}
+
+/// Whether gradients have to flow through an operand, either tracked or derived from one. Cached
+/// on each node's address so shared subgraphs are only walked once, with an explicit stack like
+/// `depth`.
+pub(crate) fn flows(operand: &Arc<dyn Operand>, memo: &mut HashMap<*const (), bool>) -> bool {
+    let mut stack = vec![(operand.clone(), false)];
+
+    while let Some((operand, expanded)) = stack.pop() {
+        let key = Arc::as_ptr(&operand) as *const ();
+        if memo.contains_key(&key) {
+            continue;
+        }
+        let node = match operand.node() {
+            Some(node) if !operand.tracked() => node,
+            _ => {
+                memo.insert(key, operand.tracked());
+                continue;
+            }
+        };
+
+        if expanded {
+            let result = node
+                .operands()
+                .iter()
+                .any(|side| memo[&(Arc::as_ptr(side) as *const ())]);
+            memo.insert(key, result);
+        } else {
+            stack.push((operand.clone(), true));
+            stack.extend(node.operands().iter().map(|side| (side.clone(), false)));
+        }
+    }
+
+    memo[&(Arc::as_ptr(operand) as *const ())]
+}
+
+/// Longest chain of operations `operand` was derived through, counting every one it still
+/// holds on to, resolved or not. Walked with an explicit stack, as chains grow with every step
+/// of a training loop that keeps them.
+pub(crate) fn depth(operand: &Arc<dyn Operand>) -> usize {
+    let mut depths: HashMap<*const (), usize> = HashMap::new();
+    let mut stack = vec![(operand.clone(), false)];
+
+    while let Some((operand, expanded)) = stack.pop() {
+        let key = Arc::as_ptr(&operand) as *const ();
+        if depths.contains_key(&key) {
+            continue;
+        }
+        let Some(node) = operand.node() else {
+            depths.insert(key, 0);
+            continue;
+        };
+
+        if expanded {
+            let deepest = node
+                .operands()
+                .iter()
+                .map(|side| depths[&(Arc::as_ptr(side) as *const ())])
+                .max()
+                .unwrap_or(0);
+            depths.insert(key, deepest + 1);
+        } else {
+            stack.push((operand.clone(), true));
+            stack.extend(node.operands().iter().map(|side| (side.clone(), false)));
+        }
+    }
+
+    depths[&(Arc::as_ptr(operand) as *const ())]
+}
+
+/// Type-erased view over an `Operation`, walked when scheduling and generating kernels.
+pub(crate) trait Node: OperationShader + Send + Sync {
+    fn operands(&self) -> &[Arc<dyn Operand>];
+    fn ty(&self) -> Shader;
 }
 
 pub(crate) struct Operation<T>
@@ -65,6 +434,7 @@ where
     pub state: State,
     pub workgroup: Workgroup,
     pub ty: Shader,
+    pub operands: Vec<Arc<dyn Operand>>,
 
     target: PhantomData<T>,
 }
@@ -73,13 +443,28 @@ impl<T> Operation<T>
 where
     Packet<T>: SupportedPacket,
 {
-    pub fn new<L, R, Lhs, Rhs>(lhs: &Bundle<L>, rhs: &Bundle<R>, ty: Shader)
+    #[allow(dead_code)]
+    pub fn new<L, R>(lhs: &Arc<Bundle<L>>, rhs: &Arc<Bundle<R>>, ty: Shader) -> Self
     where
         Packet<L>: SupportedPacket,
         Packet<R>: SupportedPacket,
+        Bundle<L>: Operand + 'static,
+        Bundle<R>: Operand + 'static,
     {
+        Self::from_operands(vec![lhs.clone(), rhs.clone()], ty)
     }
 
+    pub fn from_operands(operands: Vec<Arc<dyn Operand>>, ty: Shader) -> Self {
+        Self {
+            state: State::default(),
+            workgroup: Workgroup::default(),
+            ty,
+            operands,
+            target: PhantomData,
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn resolved(&self) -> bool {
         match self.state {
             State::Pending => false,
@@ -87,3 +472,19 @@ where
         }
     }
 }
+
+impl<T> Node for Operation<T>
+where
+    Packet<T>: SupportedPacket,
+    T: Send + Sync,
+{
+    #[inline]
+    fn operands(&self) -> &[Arc<dyn Operand>] {
+        &self.operands
+    }
+
+    #[inline]
+    fn ty(&self) -> Shader {
+        self.ty
+    }
+}
@@ -0,0 +1,162 @@
+// Per-kernel timings of a resolve, for finding out where device time goes.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Write},
+    time::Instant,
+};
+
+/// How a span was measured.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Clock {
+    /// Timestamp queries around the dispatch, i.e. device time only.
+    Gpu,
+    /// Host wall clock from submission until the device is idle again, used when the device
+    /// cannot write timestamps; includes submission overhead.
+    Cpu,
+}
+
+/// A single dispatched kernel.
+#[derive(Clone, Debug)]
+pub struct Span {
+    /// Kind of kernel, e.g. the fused or dimensional operation it was generated for.
+    pub name: String,
+    /// Host time the dispatch was issued at, in nanoseconds since the profile started.
+    pub start: f64,
+    /// Time the kernel took, in nanoseconds.
+    pub duration: f64,
+    pub clock: Clock,
+    /// Workgroups dispatched along each axis.
+    pub grid: [u32; 3],
+}
+
+/// Every kernel a resolve dispatched, in order. Kernels run one at a time while profiling, so
+/// spans never overlap, and the overall resolve gets slower than it otherwise would.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub spans: Vec<Span>,
+    origin: Instant,
+}
+
+impl Profile {
+    pub(crate) fn begin() -> Self {
+        Self {
+            spans: Vec::new(),
+            origin: Instant::now(),
+        }
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        name: &str,
+        issued: Instant,
+        duration: f64,
+        clock: Clock,
+        grid: [u32; 3],
+    ) {
+        self.spans.push(Span {
+            name: name.to_string(),
+            start: issued.duration_since(self.origin).as_nanos() as f64,
+            duration,
+            clock,
+            grid,
+        });
+    }
+
+    /// Time spent in kernels overall, in nanoseconds.
+    pub fn total(&self) -> f64 {
+        self.spans.iter().map(|span| span.duration).sum()
+    }
+
+    /// Number of dispatches and time spent per kind of kernel, slowest first.
+    pub fn summary(&self) -> Vec<(String, usize, f64)> {
+        let mut kinds: HashMap<&str, (usize, f64)> = HashMap::new();
+        for span in self.spans.iter() {
+            let kind = kinds.entry(&span.name).or_default();
+            kind.0 += 1;
+            kind.1 += span.duration;
+        }
+
+        let mut summary: Vec<_> = kinds
+            .into_iter()
+            .map(|(name, (count, time))| (name.to_string(), count, time))
+            .collect();
+        summary.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        summary
+    }
+
+    /// Spans as Chrome trace event JSON, to load in `chrome://tracing` or Perfetto.
+    pub fn to_chrome_trace(&self) -> String {
+        let mut trace = String::from("{\"traceEvents\":[");
+
+        for (index, span) in self.spans.iter().enumerate() {
+            if index > 0 {
+                trace.push(',');
+            }
+            let [x, y, z] = span.grid;
+            let _ = write!(
+                trace,
+                "{{\"name\":\"{}\",\"cat\":\"kernel\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\
+                 \"pid\":0,\"tid\":0,\"args\":{{\"clock\":\"{:?}\",\"grid\":[{},{},{}]}}}}",
+                escape(&span.name),
+                span.start / 1e3,
+                span.duration / 1e3,
+                span.clock,
+                x,
+                y,
+                z,
+            );
+        }
+
+        trace.push_str("],\"displayTimeUnit\":\"ns\"}");
+        trace
+    }
+}
+
+/// Kernels by total time, slowest first.
+impl Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        writeln!(
+            f,
+            "{:<32} {:>6} {:>12} {:>7}",
+            "kernel", "calls", "time (us)", "share"
+        )?;
+
+        for (name, count, time) in self.summary() {
+            let share = if total > 0.0 { time / total } else { 0.0 };
+            writeln!(
+                f,
+                "{:<32} {:>6} {:>12.3} {:>6.1}%",
+                name,
+                count,
+                time / 1e3,
+                share * 1e2
+            )?;
+        }
+
+        write!(
+            f,
+            "{:<32} {:>6} {:>12.3}",
+            "total",
+            self.spans.len(),
+            total / 1e3
+        )
+    }
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
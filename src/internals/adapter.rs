@@ -0,0 +1,108 @@
+use {std::sync::OnceLock, wgpu};
+
+/// Optional capabilities kernels can take advantage of.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Capabilities {
+    /// Half precision arithmetic in shaders.
+    pub f16: bool,
+    /// Double precision arithmetic in shaders.
+    pub f64: bool,
+    /// Timestamp queries, used to tune and profile kernels.
+    pub timestamps: bool,
+    /// Subgroup operations; not exposed by the wgpu release in use, hence always unset.
+    pub subgroups: bool,
+}
+
+impl Capabilities {
+    fn from_features(features: wgpu::Features) -> Self {
+        Self {
+            f16: features.contains(wgpu::Features::SHADER_F16),
+            f64: features.contains(wgpu::Features::SHADER_F64),
+            timestamps: features.contains(wgpu::Features::TIMESTAMP_QUERY),
+            subgroups: false,
+        }
+    }
+}
+
+/// Description of an adapter, as reported by its driver.
+#[derive(Clone, Debug)]
+pub struct AdapterInfo {
+    pub name: String,
+    /// PCI vendor and device ids, zero where the backend doesn't report them.
+    pub vendor: usize,
+    pub device: usize,
+    pub backend: wgpu::Backend,
+    pub device_type: wgpu::DeviceType,
+    pub driver: String,
+    pub driver_info: String,
+    /// What the adapter supports, regardless of what an instance ends up enabling.
+    pub features: Capabilities,
+    pub limits: wgpu::Limits,
+}
+
+impl AdapterInfo {
+    pub(crate) fn new(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+
+        Self {
+            name: info.name,
+            vendor: info.vendor,
+            device: info.device,
+            backend: info.backend,
+            device_type: info.device_type,
+            driver: info.driver,
+            driver_info: info.driver_info,
+            features: Capabilities::from_features(adapter.features()),
+            limits: adapter.limits(),
+        }
+    }
+}
+
+/// Adapters of the shared instance, listed once.
+struct Adapters {
+    listed: Vec<wgpu::Adapter>,
+    /// Position of the one wgpu picks when asked for a default.
+    default: Option<usize>,
+}
+
+/// Every adapter the platform exposes across backends, always listed in the same order.
+pub(crate) fn enumerate() -> &'static [wgpu::Adapter] {
+    &adapters().listed
+}
+
+/// Adapter wgpu picks when nothing in particular is asked for.
+pub(crate) fn default() -> Option<&'static wgpu::Adapter> {
+    let adapters = adapters();
+    adapters.default.map(|index| &adapters.listed[index])
+}
+
+/// Adapters are only listed the first time they're needed, and every device is created from
+/// those. GL backends hand all adapters listed from an instance the same context, each behind
+/// a lock of its own: listing again while a device works on the context would make it
+/// current on two threads at once.
+fn adapters() -> &'static Adapters {
+    static ADAPTERS: OnceLock<Adapters> = OnceLock::new();
+    ADAPTERS.get_or_init(|| {
+        let listed: Vec<_> = shared().enumerate_adapters(wgpu::Backends::all()).collect();
+        let requested =
+            pollster::block_on(shared().request_adapter(&wgpu::RequestAdapterOptions::default()));
+        let default = requested.and_then(|requested| {
+            let wanted = requested.get_info();
+            listed.iter().position(|adapter| {
+                let info = adapter.get_info();
+                (&info.name, info.backend, info.device)
+                    == (&wanted.name, wanted.backend, wanted.device)
+            })
+        });
+
+        Adapters { listed, default }
+    })
+}
+
+/// Process-wide wgpu instance every adapter is requested from. Backends such as GL keep
+/// global driver state (the EGL display), which a second instance would tear down on drop
+/// while devices from the first are still in use.
+fn shared() -> &'static wgpu::Instance {
+    static INSTANCE: OnceLock<wgpu::Instance> = OnceLock::new();
+    INSTANCE.get_or_init(wgpu::Instance::default)
+}
@@ -1,28 +1,70 @@
-use {bytemuck, std::mem, wgpu};
+use {
+    bytemuck,
+    std::{
+        any,
+        future::Future,
+        mem::{self, ManuallyDrop},
+        sync::{Arc, Mutex},
+    },
+    wgpu,
+};
 
 use crate::types::Component;
 
-use super::Handler;
+use super::{
+    pool::{Class, Pool, Usage},
+    Handler,
+};
 
 /// Identifiers for `Buffer`s
 #[derive(Clone, Copy, Default)]
 pub(crate) enum BufferType {
     #[default]
     Init,
+    #[allow(dead_code)]
     Map,
+    Storage,
 }
 
-/// Abstraction layer for wgpu::Buffer.
+impl BufferType {
+    #[inline]
+    fn class(&self) -> Class {
+        match self {
+            BufferType::Map => Class::Staging,
+            BufferType::Init | BufferType::Storage => Class::Storage,
+        }
+    }
+
+    /// Ledger entry the buffer is charged to. Staging buffers have none, the pool accounting
+    /// for them by class.
+    #[inline]
+    fn usage(&self) -> Option<Usage> {
+        match self {
+            BufferType::Init => Some(Usage::Input),
+            BufferType::Map => None,
+            BufferType::Storage => Some(Usage::Intermediate),
+        }
+    }
+}
+
+/// Abstraction layer for wgpu::Buffer. The underlying allocation comes from the handler's pool
+/// and may be larger than requested; `len` is the logical size every binding and copy sees.
 pub(crate) struct Buffer {
     pub ty: BufferType,
 
     #[doc(hidden)]
-    _buffer: wgpu::Buffer,
+    _buffer: ManuallyDrop<wgpu::Buffer>,
+    #[doc(hidden)]
+    _len: u64,
+    #[doc(hidden)]
+    _pool: Arc<Mutex<Pool>>,
+    #[doc(hidden)]
+    _dtype: &'static str,
 }
 
 impl Buffer {
     pub fn bind<T>(
-        handler: Handler,
+        handler: &Handler,
         ty: BufferType,
         _content: Option<&[T]>,
         _size: Option<u64>,
@@ -30,47 +72,93 @@ impl Buffer {
     where
         T: Component,
     {
+        let _size = _size.or(Some(mem::size_of::<T>() as u64));
+        let _content = _content.map(bytemuck::cast_slice::<T, u8>);
+
+        Self::bind_raw(handler, ty, any::type_name::<T>(), _content, _size)
+    }
+
+    /// Untyped counterpart of `bind`, for contents already laid out as bytes; `_dtype` only
+    /// serves memory accounting.
+    pub fn bind_raw(
+        handler: &Handler,
+        ty: BufferType,
+        _dtype: &'static str,
+        _content: Option<&[u8]>,
+        _size: Option<u64>,
+    ) -> Result<Self, wgpu::Error> {
         let _buffer = {
             match ty {
-                BufferType::Init => handler.alloc_buffer_init({
-                    if let Some(content) = _content {
-                        bytemuck::cast_slice::<T, u8>(content)
+                BufferType::Init => match _content {
+                    Some(content) => handler.alloc_buffer_init(content)?,
+                    // Contents streamed in afterwards with `write_chunked`.
+                    None => handler.alloc_buffer_upload({
+                        if let Some(size) = _size {
+                            size
+                        } else {
+                            panic!()
+                        }
+                    })?,
+                },
+                BufferType::Map => handler.alloc_buffer_map({
+                    if let Some(size) = _size {
+                        size
                     } else {
                         panic!()
                     }
                 })?,
-                BufferType::Map => handler.alloc_buffer_map({
+                BufferType::Storage => handler.alloc_buffer_storage({
                     if let Some(size) = _size {
                         size
                     } else {
-                        mem::size_of::<T> as u64
+                        panic!()
                     }
                 })?,
             }
         };
 
-        let entry = Self { _buffer, ty };
+        let _len = match (_content, _size) {
+            (Some(content), _) => content.len() as u64,
+            (None, Some(size)) => size,
+            (None, None) => panic!(),
+        };
+
+        let _pool = handler.pool();
+        if let Some(usage) = ty.usage() {
+            _pool.lock().unwrap().charge(_dtype, usage, _buffer.size());
+        }
+
+        let entry = Self {
+            ty,
+            _buffer: ManuallyDrop::new(_buffer),
+            _len,
+            _pool,
+            _dtype,
+        };
 
         Ok(entry)
     }
 
+    #[allow(dead_code)]
     #[inline]
     pub fn bits(&self) -> u32 {
         self._buffer.usage().bits()
     }
 
+    #[allow(dead_code)]
     #[inline]
     pub fn contains(&self, bits: u32) -> bool {
         let flags = wgpu::BufferUsages::from_bits(bits).unwrap();
         self._buffer.usage().contains(flags)
     }
 
+    #[allow(dead_code)]
     #[inline]
     pub fn free(&self) {
         drop(self._buffer.slice(..).get_mapped_range());
     }
 
-    /// Generic typing to be made clear.
+    // Generic typing to be made clear.
     /*
     #[inline]
     pub fn id(&self) -> wgpu::Id<wgpu_> {
@@ -88,13 +176,73 @@ impl Buffer {
         self._buffer.usage().contains(wgpu::BufferUsages::UNIFORM)
     }
 
+    /// Whether the buffer was allocated by `handler`'s device, as opposed to one since lost.
+    #[inline]
+    pub fn on(&self, handler: &Handler) -> bool {
+        Arc::ptr_eq(&self._pool, &handler.pool())
+    }
+
+    /// Copy the contents back to the host, blocking until done.
     #[inline]
-    pub fn resource(&self) -> wgpu::BindingResource {
-        self._buffer.as_entire_binding()
+    pub fn read(&self, handler: &Handler) -> Result<Vec<u8>, wgpu::Error> {
+        handler.read_buffer(&self._buffer, self._len)
+    }
+
+    /// Stream `contents` into the buffer without blocking, chunk by chunk through the
+    /// handler's staging ring.
+    #[inline]
+    pub async fn write_chunked(
+        &self,
+        handler: &Handler,
+        contents: &[u8],
+    ) -> Result<(), wgpu::Error> {
+        handler.write_chunked(&self._buffer, contents).await
+    }
+
+    /// Non-blocking counterpart of `read`; the copy is queued before returning.
+    #[inline]
+    pub fn read_async<'h>(
+        &self,
+        handler: &'h Handler,
+    ) -> impl Future<Output = Result<Vec<u8>, wgpu::Error>> + Send + 'h {
+        handler.read_buffer_async(&self._buffer, self._len)
+    }
+
+    #[inline]
+    pub fn resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self._buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(wgpu::util::align_to(
+                self._len,
+                wgpu::COPY_BUFFER_ALIGNMENT,
+            )),
+        })
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn size(&self) -> u64 {
+        self._len
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        // SAFETY: the field is never touched again after this point.
+        let buffer = unsafe { ManuallyDrop::take(&mut self._buffer) };
+
+        if let Ok(mut pool) = self._pool.lock() {
+            if let Some(usage) = self.ty.usage() {
+                pool.refund(self._dtype, usage, buffer.size());
+            }
+            pool.release(self.ty.class(), buffer);
+        }
     }
 }
 
 pub(crate) struct BufferMeta<'b> {
+    #[allow(dead_code)]
     pub buffer: &'b Buffer,
 
     #[doc(hidden)]
@@ -141,4 +289,15 @@ impl<'b> BufferMeta<'b> {
 
         Ok(meta)
     }
+
+    #[inline]
+    pub fn group(&self) -> wgpu::BindGroupEntry<'b> {
+        self._group.clone()
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn layout(&self) -> wgpu::BindGroupLayoutEntry {
+        self._layout
+    }
 }
@@ -1,3 +1,5 @@
+#![allow(dead_code, unused_imports)]
+
 use {std::borrow::Cow, wgpu};
 
 use super::Handler;
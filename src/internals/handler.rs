@@ -1,52 +1,243 @@
 use {
     pollster,
-    std::borrow::Cow,
-    wgpu::{self, util::DeviceExt},
+    std::{
+        borrow::Cow,
+        error,
+        future::Future,
+        mem,
+        panic::{self, AssertUnwindSafe},
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Condvar, Mutex, OnceLock,
+        },
+        task::{Context, Poll, Waker},
+        thread,
+    },
+    wgpu,
+};
+
+use crate::core::Error;
+
+use super::{
+    adapter::{self, AdapterInfo},
+    pool::{Class, MemoryStats, Pool, PoolStats},
 };
 
 /// Generic label for every allocated zelkova-owned resource.
 static label: Option<&'static str> = Some("Zelkova owned resource");
 
-// Core interface to handle wgpu internals
+/// Uploads above this size stream through the staging ring instead of a single queue write.
+const STAGING_CHUNK: u64 = 16 << 20;
+
+/// Number of staging buffers cycled through by chunked uploads.
+const STAGING_SLOTS: usize = 2;
+
+/// What wgpu reports once the device went away, e.g. after a driver reset.
+const LOST: &str = "Parent device is lost";
+
+// Core interface to handle wgpu internals. Only takes `&self`, so it can be shared across
+// threads: every call records into its own encoder and submits to the device's queue.
 pub(crate) struct Handler {
-    adapter: wgpu::Adapter,
-    device: wgpu::Device,
-    encoder: wgpu::CommandEncoder,
+    adapter: &'static wgpu::Adapter,
+    device: Arc<wgpu::Device>,
+    limits: wgpu::Limits,
+    /// Set once the device is known to be gone; nothing it holds can be used anymore.
+    lost: Arc<AtomicBool>,
+    poller: Poller,
+    pool: Arc<Mutex<Pool>>,
     queue: wgpu::Queue,
+    ring: Mutex<Vec<wgpu::Buffer>>,
+    /// Last error raised outside of any scope, reported by the next `unscope`.
+    stray: Arc<Mutex<Option<wgpu::Error>>>,
 }
 
 impl Handler {
-    pub fn request() -> Result<Self, wgpu::Error> {
-        pollster::block_on(async move {
-            let adapter = wgpu::Instance::default()
-                .request_adapter(&wgpu::RequestAdapterOptions::default())
-                .await
-                .unwrap();
-
-            let (device, queue) = adapter
-                .request_device(&wgpu::DeviceDescriptor::default(), None)
-                .await
-                .unwrap();
-
-            let encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
-
-            let handler = Self {
-                adapter,
-                device,
-                encoder,
-                queue,
-            };
-
-            Ok(handler)
+    pub fn request() -> Result<Self, Error> {
+        pollster::block_on(Self::request_async())
+    }
+
+    pub async fn request_async() -> Result<Self, Error> {
+        let adapter =
+            adapter::default().ok_or_else(|| Error::Device("no adapter available".to_string()))?;
+
+        Self::from_adapter(adapter)
+    }
+
+    /// One handler per adapter the platform exposes, across every backend.
+    pub fn request_all() -> Result<Vec<Self>, Error> {
+        adapter::enumerate()
+            .iter()
+            .map(Self::from_adapter)
+            .collect()
+    }
+
+    /// Handler on the adapter matching `info`, typically to replace a lost device; falls back
+    /// to the default adapter when it is gone.
+    pub fn request_like(info: &AdapterInfo) -> Result<Self, Error> {
+        let found = adapter::enumerate().iter().find(|adapter| {
+            let candidate = adapter.get_info();
+            candidate.name == info.name && candidate.backend == info.backend
+        });
+
+        match found {
+            Some(adapter) => Self::from_adapter(adapter),
+            None => Self::request(),
+        }
+    }
+
+    /// Handler on the adapter at `index` among those listed by `adapter::enumerate`.
+    pub fn request_nth(index: usize) -> Option<Result<Self, Error>> {
+        let adapter = adapter::enumerate().get(index)?;
+        Some(Self::from_adapter(adapter))
+    }
+
+    fn from_adapter(adapter: &'static wgpu::Adapter) -> Result<Self, Error> {
+        // Ask for everything the adapter offers so large weights fit in a single buffer.
+        // Timestamps are optional, only used to tune and profile kernels when available.
+        let descriptor = wgpu::DeviceDescriptor {
+            features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+            limits: adapter.limits(),
+            ..Default::default()
+        };
+
+        // Some drivers panic rather than fail when they can't hand out another device, e.g.
+        // GL through EGL; either way the caller gets an error it can act on.
+        let request = panic::catch_unwind(AssertUnwindSafe(|| {
+            pollster::block_on(adapter.request_device(&descriptor, None))
+        }));
+        let (device, queue) = match request {
+            Ok(Ok(device)) => device,
+            Ok(Err(error)) => return Err(Error::Device(error.to_string())),
+            Err(_) => {
+                return Err(Error::Device(
+                    "driver failed to create a device".to_string(),
+                ))
+            }
+        };
+
+        // Errors escaping every scope would abort through wgpu's default handler; they are
+        // kept for the next scope to report instead.
+        let lost = Arc::new(AtomicBool::new(false));
+        let stray = Arc::new(Mutex::new(None));
+        device.on_uncaptured_error(Box::new({
+            let (lost, stray) = (lost.clone(), stray.clone());
+            move |error: wgpu::Error| {
+                if Self::fatal(&error) {
+                    lost.store(true, Ordering::Relaxed);
+                }
+                *stray.lock().unwrap() = Some(error);
+            }
+        }));
+
+        let device = Arc::new(device);
+        let handler = Self {
+            adapter,
+            poller: Poller::new(device.clone(), lost.clone()),
+            device,
+            pool: Arc::new(Mutex::new(Pool::new(descriptor.limits.max_buffer_size))),
+            limits: descriptor.limits,
+            lost,
+            queue,
+            ring: Mutex::default(),
+            stray,
+        };
+
+        Ok(handler)
+    }
+
+    /// Capture validation and out-of-memory errors raised until the matching `unscope`. Scopes
+    /// are per device rather than per thread, so callers keep them from interleaving.
+    pub fn scope(&self) {
+        self.device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+    }
+
+    /// First error captured since `scope`, flagging the device as lost if that is the cause.
+    pub fn unscope(&self) -> Option<wgpu::Error> {
+        pollster::block_on(self.unscope_async())
+    }
+
+    /// Non-blocking counterpart of `unscope`. Both scopes are popped before returning, so the
+    /// caller is free to let others scope the device again while the future is pending.
+    pub fn unscope_async(&self) -> impl Future<Output = Option<wgpu::Error>> + Send + '_ {
+        let validation = self.device.pop_error_scope();
+        let memory = self.device.pop_error_scope();
+
+        async move {
+            let validation = validation.await;
+            let memory = memory.await;
+            let stray = self.stray.lock().unwrap().take();
+
+            let error = validation.or(memory).or(stray)?;
+            if Self::fatal(&error) {
+                self.lost.store(true, Ordering::Relaxed);
+            }
+
+            Some(error)
+        }
+    }
+
+    #[inline]
+    pub fn lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn fatal(error: &wgpu::Error) -> bool {
+        error.to_string().contains(LOST)
+    }
+
+    /// Flag the device as lost, wrapping `source` into the error reporting it.
+    fn lose(&self, source: Box<dyn error::Error + Send + 'static>) -> wgpu::Error {
+        self.lost.store(true, Ordering::Relaxed);
+
+        wgpu::Error::Validation {
+            source,
+            description: LOST.to_string(),
+        }
+    }
+
+    /// Block until every submission is done. wgpu gives up with a panic when the device is
+    /// gone, which is turned back into an error here.
+    pub fn wait(&self) -> Result<(), wgpu::Error> {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            self.device.poll(wgpu::Maintain::Wait);
+        }))
+        .map_err(|_| {
+            let source: Box<dyn error::Error + Send + Sync> = "device poll failed".into();
+            self.lose(source)
         })
     }
 
-    pub fn start_pass(&mut self) -> Result<wgpu::ComputePass, wgpu::Error> {
-        let pass = self
-            .encoder
-            .begin_compute_pass(&wgpu::ComputePassDescriptor { label });
+    /// Fresh encoder for a single call's worth of commands.
+    #[inline]
+    pub fn encoder(&self) -> wgpu::CommandEncoder {
+        self.device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label })
+    }
+
+    /// Queue recorded commands; submissions from every thread run in the order they land.
+    #[inline]
+    pub fn submit(&self, encoder: wgpu::CommandEncoder) {
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    pub fn bind_group(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        group: u32,
+        entries: &[wgpu::BindGroupEntry],
+    ) -> Result<wgpu::BindGroup, wgpu::Error> {
+        let layout = pipeline.get_bind_group_layout(group);
+
+        let bindgroup = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label,
+            layout: &layout,
+            entries,
+        });
 
-        Ok(pass)
+        Ok(bindgroup)
     }
 
     pub fn load_module(
@@ -73,31 +264,479 @@ impl Handler {
     }
 
     pub fn alloc_buffer_init(&self, contents: &[u8]) -> Result<wgpu::Buffer, wgpu::Error> {
-        let usage = wgpu::BufferUsages::STORAGE
-            | wgpu::BufferUsages::COPY_SRC
-            | wgpu::BufferUsages::COPY_DST;
+        let buffer = self.acquire(Class::Storage, contents.len() as u64);
 
-        let buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label,
-                contents,
-                usage,
-            });
+        if Self::chunked(contents.len() as u64) {
+            pollster::block_on(self.write_chunked(&buffer, contents))?;
+            return Ok(buffer);
+        }
+
+        // Writes must cover whole words, so odd-sized contents get padded.
+        match contents.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize {
+            0 => self.queue.write_buffer(&buffer, 0, contents),
+            rem => {
+                let mut padded = contents.to_vec();
+                padded.resize(
+                    contents.len() + wgpu::COPY_BUFFER_ALIGNMENT as usize - rem,
+                    0,
+                );
+                self.queue.write_buffer(&buffer, 0, &padded);
+            }
+        }
 
         Ok(buffer)
     }
 
+    /// Limits the device was created with.
+    #[inline]
+    pub fn limits(&self) -> &wgpu::Limits {
+        &self.limits
+    }
+
+    pub fn info(&self) -> AdapterInfo {
+        AdapterInfo::new(self.adapter)
+    }
+
     pub fn alloc_buffer_map(&self, size: u64) -> Result<wgpu::Buffer, wgpu::Error> {
-        let usage = wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST;
+        Ok(self.acquire(Class::Staging, size))
+    }
+
+    pub fn alloc_buffer_storage(&self, size: u64) -> Result<wgpu::Buffer, wgpu::Error> {
+        Ok(self.acquire(Class::Storage, size))
+    }
 
-        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+    /// Shared handle on the buffer pool, held by every `Buffer` so it can give itself back.
+    #[inline]
+    pub fn pool(&self) -> Arc<Mutex<Pool>> {
+        self.pool.clone()
+    }
+
+    #[inline]
+    pub fn pool_stats(&self) -> PoolStats {
+        self.pool.lock().unwrap().stats()
+    }
+
+    #[inline]
+    pub fn memory(&self) -> MemoryStats {
+        self.pool.lock().unwrap().memory()
+    }
+
+    /// Release every cached buffer back to the device, along with the upload ring.
+    pub fn trim(&self) {
+        let ring = mem::take(&mut *self.ring.lock().unwrap());
+        let mut pool = self.pool.lock().unwrap();
+        for slot in ring {
+            pool.disown(slot.size());
+        }
+        pool.trim()
+    }
+
+    /// Take a buffer from the pool, or allocate a fresh one sized to the request's bucket.
+    fn acquire(&self, class: Class, size: u64) -> wgpu::Buffer {
+        let mut pool = self.pool.lock().unwrap();
+
+        if let Some(buffer) = pool.acquire(class, size) {
+            return buffer;
+        }
+
+        let usage = match class {
+            Class::Storage => {
+                wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST
+            }
+            Class::Staging => wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        };
+
+        let size = Pool::bucket(size, pool.ceiling());
+        pool.record(class, size);
+
+        self.device.create_buffer(&wgpu::BufferDescriptor {
             label,
             size,
             usage,
             mapped_at_creation: false,
+        })
+    }
+
+    #[inline]
+    pub fn timestamps(&self) -> bool {
+        self.device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+    }
+
+    /// Run a single dispatch on its own and measure it on the device, in nanoseconds. `None`
+    /// when the device cannot write timestamps.
+    pub fn time(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        bindgroups: &[wgpu::BindGroup],
+        [x, y, z]: [u32; 3],
+    ) -> Option<f64> {
+        if !self.timestamps() {
+            return None;
+        }
+
+        let queries = self.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label,
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolved = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label,
+            size: 16,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
         });
 
-        Ok(buffer)
+        let mut encoder = self.encoder();
+        encoder.write_timestamp(&queries, 0);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label });
+            pass.set_pipeline(pipeline);
+            for (group, bindgroup) in bindgroups.iter().enumerate() {
+                pass.set_bind_group(group as u32, bindgroup, &[]);
+            }
+            pass.dispatch_workgroups(x, y, z);
+        }
+        encoder.write_timestamp(&queries, 1);
+        encoder.resolve_query_set(&queries, 0..2, &resolved, 0);
+        self.submit(encoder);
+
+        let bytes = self.read_buffer(&resolved, 16).ok()?;
+        let tick = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let ticks = tick(8).saturating_sub(tick(0));
+
+        Some(ticks as f64 * self.queue.get_timestamp_period() as f64)
+    }
+
+    /// Whether an upload of `size` bytes streams through the staging ring.
+    #[inline]
+    pub fn chunked(size: u64) -> bool {
+        size > STAGING_CHUNK
+    }
+
+    /// Storage buffer for `size` bytes of contents to be written with `write_chunked`.
+    pub fn alloc_buffer_upload(&self, size: u64) -> Result<wgpu::Buffer, wgpu::Error> {
+        Ok(self.acquire(Class::Storage, size))
+    }
+
+    /// Stream `contents` into `dst` through a small ring of mappable staging buffers, so the
+    /// transient host-visible memory stays bounded by `STAGING_CHUNK * STAGING_SLOTS` per
+    /// upload however large it is. Slots are taken out of the ring for the duration, so uploads
+    /// running side by side never share one.
+    pub async fn write_chunked(
+        &self,
+        dst: &wgpu::Buffer,
+        contents: &[u8],
+    ) -> Result<(), wgpu::Error> {
+        let mut slots = {
+            let mut ring = self.ring.lock().unwrap();
+            let kept = ring.len().saturating_sub(STAGING_SLOTS);
+            ring.split_off(kept)
+        };
+        while slots.len() < STAGING_SLOTS {
+            self.pool
+                .lock()
+                .unwrap()
+                .record(Class::Staging, STAGING_CHUNK);
+            slots.push(self.device.create_buffer(&wgpu::BufferDescriptor {
+                label,
+                size: STAGING_CHUNK,
+                usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }));
+        }
+
+        for (index, chunk) in contents.chunks(STAGING_CHUNK as usize).enumerate() {
+            let staging = &slots[index % STAGING_SLOTS];
+            let size = wgpu::util::align_to(chunk.len() as u64, wgpu::COPY_BUFFER_ALIGNMENT);
+            let slice = staging.slice(..size);
+
+            // Mapping waits for the copy that last used this slot to retire.
+            let signal = Signal::default();
+            let sender = signal.clone();
+            slice.map_async(wgpu::MapMode::Write, move |result| sender.fire(result));
+            self.settle(signal).await?;
+
+            slice.get_mapped_range_mut()[..chunk.len()].copy_from_slice(chunk);
+            staging.unmap();
+
+            let mut encoder = self.encoder();
+            encoder.copy_buffer_to_buffer(staging, 0, dst, index as u64 * STAGING_CHUNK, size);
+            self.submit(encoder);
+        }
+
+        // Only a full ring's worth is kept around; slots made by concurrent uploads go.
+        let mut ring = self.ring.lock().unwrap();
+        let room = STAGING_SLOTS.saturating_sub(ring.len());
+        let extra = slots.split_off(room.min(slots.len()));
+        ring.extend(slots);
+        drop(ring);
+
+        let mut pool = self.pool.lock().unwrap();
+        for slot in extra {
+            pool.disown(slot.size());
+        }
+
+        Ok(())
+    }
+
+    /// Copy the first `len` bytes of `src` into a mappable buffer and block until they reach
+    /// the host.
+    pub fn read_buffer(&self, src: &wgpu::Buffer, len: u64) -> Result<Vec<u8>, wgpu::Error> {
+        let staging = self.stage_read(src, len)?;
+
+        let (sender, receiver) = mpsc::channel();
+        staging
+            .slice(..Self::aligned(len))
+            .map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).unwrap();
+            });
+        self.wait()?;
+        receiver
+            .recv()
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(|err| self.lose(Box::new(err)))?;
+
+        Ok(self.collect(staging, len))
+    }
+
+    /// Non-blocking counterpart of `read_buffer`. The copy is queued right away, so `src` is
+    /// free to go before the returned future is awaited.
+    pub fn read_buffer_async(
+        &self,
+        src: &wgpu::Buffer,
+        len: u64,
+    ) -> impl Future<Output = Result<Vec<u8>, wgpu::Error>> + Send + '_ {
+        let staging = self.stage_read(src, len);
+
+        async move {
+            let staging = staging?;
+
+            let signal = Signal::default();
+            let sender = signal.clone();
+            staging
+                .slice(..Self::aligned(len))
+                .map_async(wgpu::MapMode::Read, move |result| sender.fire(result));
+            self.settle(signal).await?;
+
+            Ok(self.collect(staging, len))
+        }
+    }
+
+    /// Resolves once the device is done with everything submitted so far.
+    pub async fn idle(&self) -> Result<(), wgpu::Error> {
+        let signal = Signal::default();
+        let sender = signal.clone();
+        self.queue
+            .on_submitted_work_done(move || sender.fire(Ok(())));
+
+        self.settle(signal).await
+    }
+
+    /// Wait for a mapping callback to report, the device being lost when it never will.
+    async fn settle(
+        &self,
+        signal: Signal<Result<(), wgpu::BufferAsyncError>>,
+    ) -> Result<(), wgpu::Error> {
+        signal
+            .wait(&self.poller)
+            .await
+            .unwrap_or(Err(wgpu::BufferAsyncError))
+            .map_err(|err| self.lose(Box::new(err)))
+    }
+
+    #[inline]
+    fn aligned(len: u64) -> u64 {
+        wgpu::util::align_to(len, wgpu::COPY_BUFFER_ALIGNMENT)
+    }
+
+    /// Queue a copy of the first `len` bytes of `src` into a fresh staging buffer.
+    fn stage_read(&self, src: &wgpu::Buffer, len: u64) -> Result<wgpu::Buffer, wgpu::Error> {
+        let size = Self::aligned(len);
+        let staging = self.alloc_buffer_map(size)?;
+
+        let mut encoder = self.encoder();
+        encoder.copy_buffer_to_buffer(src, 0, &staging, 0, size);
+        self.submit(encoder);
+
+        Ok(staging)
+    }
+
+    /// Take the contents out of a mapped staging buffer and give it back to the pool.
+    fn collect(&self, staging: wgpu::Buffer, len: u64) -> Vec<u8> {
+        let contents =
+            staging.slice(..Self::aligned(len)).get_mapped_range()[..len as usize].to_vec();
+        staging.unmap();
+        self.pool.lock().unwrap().release(Class::Staging, staging);
+
+        contents
+    }
+}
+
+/// One-shot value set from a wgpu callback, waking whoever awaits it.
+struct Signal<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> Default for Signal<T> {
+    fn default() -> Self {
+        Self {
+            slot: Arc::new(Mutex::new(Slot {
+                value: None,
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl<T> Clone for Signal<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> Signal<T> {
+    fn fire(&self, value: T) {
+        let waker = {
+            let mut slot = self.slot.lock().unwrap();
+            slot.value = Some(value);
+            slot.waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Callbacks only run while the device is polled, which `poller` takes care of for as long
+    /// as the returned future is pending. It resolves to `None` if the device could not be
+    /// polled anymore.
+    fn wait(self, poller: &Poller) -> Wait<'_, T> {
+        Wait {
+            poller,
+            slot: self.slot,
+        }
+    }
+}
+
+struct Wait<'p, T> {
+    poller: &'p Poller,
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Future for Wait<'_, T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        {
+            let mut slot = self.slot.lock().unwrap();
+            if let Some(value) = slot.value.take() {
+                return Poll::Ready(Some(value));
+            }
+            slot.waker = Some(cx.waker().clone());
+        }
+
+        match self.poller.request(cx.waker()) {
+            true => Poll::Pending,
+            false => Poll::Ready(None),
+        }
+    }
+}
+
+/// Polls the device from a thread of its own while futures wait on it, so they sleep until
+/// their callback fires rather than keep an executor thread busy. The thread starts with the
+/// first wait and stops with the handler.
+struct Poller {
+    device: Arc<wgpu::Device>,
+    lost: Arc<AtomicBool>,
+    state: Arc<(Mutex<Polling>, Condvar)>,
+    thread: OnceLock<()>,
+}
+
+#[derive(Default)]
+struct Polling {
+    /// Futures to wake once the device was polled, whether or not their callback fired.
+    waiting: Vec<Waker>,
+    /// Polling panicked, which wgpu does once the device is gone.
+    failed: bool,
+    closed: bool,
+}
+
+impl Poller {
+    fn new(device: Arc<wgpu::Device>, lost: Arc<AtomicBool>) -> Self {
+        Self {
+            device,
+            lost,
+            state: Arc::default(),
+            thread: OnceLock::new(),
+        }
+    }
+
+    /// Have the device polled, then `waker` woken. False once the device can't be polled.
+    fn request(&self, waker: &Waker) -> bool {
+        self.thread.get_or_init(|| {
+            let (device, lost, state) =
+                (self.device.clone(), self.lost.clone(), self.state.clone());
+            thread::Builder::new()
+                .name("zelkova-poller".into())
+                .spawn(move || Self::run(&device, &lost, &state))
+                .expect("failed to spawn the device poller");
+        });
+
+        let (lock, ready) = &*self.state;
+        let mut polling = lock.lock().unwrap();
+        if polling.failed {
+            return false;
+        }
+
+        polling.waiting.push(waker.clone());
+        ready.notify_one();
+        true
+    }
+
+    fn run(device: &wgpu::Device, lost: &AtomicBool, state: &(Mutex<Polling>, Condvar)) {
+        let (lock, ready) = state;
+        let mut polling = lock.lock().unwrap();
+
+        loop {
+            while polling.waiting.is_empty() && !polling.closed {
+                polling = ready.wait(polling).unwrap();
+            }
+            if polling.closed {
+                return;
+            }
+
+            let waiting = mem::take(&mut polling.waiting);
+            drop(polling);
+
+            let polled = panic::catch_unwind(AssertUnwindSafe(|| {
+                device.poll(wgpu::Maintain::Wait);
+            }));
+            if polled.is_err() {
+                lost.store(true, Ordering::Relaxed);
+                lock.lock().unwrap().failed = true;
+            }
+            waiting.into_iter().for_each(Waker::wake);
+
+            polling = lock.lock().unwrap();
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        let (lock, ready) = &*self.state;
+        lock.lock().unwrap().closed = true;
+        ready.notify_one();
     }
 }
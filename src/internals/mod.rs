@@ -1,7 +1,10 @@
+pub(crate) mod adapter;
 pub(crate) mod buffer;
 pub(crate) mod context;
 pub(crate) mod handler;
+pub(crate) mod pool;
 
+pub use adapter::{AdapterInfo, Capabilities};
 pub(crate) use buffer::{Buffer, BufferType};
-pub(crate) use context::ComputeContext;
 pub(crate) use handler::Handler;
+pub use pool::{MemoryStats, PoolStats, Usage};
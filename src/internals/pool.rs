@@ -0,0 +1,206 @@
+use {
+    std::collections::{BTreeMap, HashMap},
+    wgpu,
+};
+
+/// Smallest bucket handed out by the pool, in bytes.
+const MIN_BUCKET: u64 = 256;
+
+/// Largest bucket rounded up to a power of two; above it, buckets come in `STEPS` classes per
+/// doubling, wasting at most an eighth of a buffer rather than half of it.
+const COARSE_BUCKET: u64 = 1 << 20;
+
+const STEPS: u64 = 8;
+
+/// Usage classes buffers are recycled within; a buffer never changes class.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum Class {
+    Storage,
+    Staging,
+}
+
+/// What a device buffer is used for, as reported by `MemoryStats`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Usage {
+    /// Contents uploaded from the host.
+    Input,
+    /// Outputs of kernels, including optimizer state.
+    Intermediate,
+    /// Mappable buffers moving data between host and device, including the ones kept around for
+    /// the next transfer.
+    Staging,
+}
+
+/// Device memory held by an instance, broken down by element type and usage, in bytes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Live plus cached bytes, i.e. everything currently requested from the device.
+    pub total: u64,
+    /// Storage bytes kept by the pool for reuse; counted in `total` but in neither breakdown.
+    pub cached: u64,
+    /// Budget the instance enforces, if any.
+    pub budget: Option<u64>,
+    /// Bytes of tensors by element type; staging memory holds no particular type and is only
+    /// found in `by_usage`.
+    pub by_dtype: BTreeMap<&'static str, u64>,
+    pub by_usage: BTreeMap<Usage, u64>,
+}
+
+/// Allocation statistics of the device memory pool, all sizes in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Total bytes ever requested from the device.
+    pub allocated: u64,
+    /// Bytes currently handed out to live buffers.
+    pub live: u64,
+    /// High-water mark of `live`.
+    pub peak: u64,
+    /// Bytes sitting in the free lists, ready for reuse.
+    pub cached: u64,
+    /// Requests served from the free lists.
+    pub hits: u64,
+    /// Requests that had to allocate a fresh buffer.
+    pub misses: u64,
+}
+
+/// Size-bucketed free lists of device buffers. Requests are rounded up to their bucket so a
+/// released buffer can serve any later request falling in the same one.
+pub(crate) struct Pool {
+    free: HashMap<(Class, u64), Vec<wgpu::Buffer>>,
+    ledger: HashMap<(&'static str, Usage), u64>,
+    stats: PoolStats,
+    /// Bytes of staging buffers handed out, whether for a read or to the upload ring.
+    staging: u64,
+    /// Largest buffer the device creates, which no bucket goes past.
+    ceiling: u64,
+}
+
+impl Pool {
+    pub fn new(ceiling: u64) -> Self {
+        Self {
+            free: HashMap::new(),
+            ledger: HashMap::new(),
+            stats: PoolStats::default(),
+            staging: 0,
+            ceiling,
+        }
+    }
+
+    /// Size of the buffer serving a request for `size` bytes: the next power of two for small
+    /// requests, the next of `STEPS` classes per doubling for larger ones, and never more than
+    /// `ceiling` when the request itself fits under it.
+    pub fn bucket(size: u64, ceiling: u64) -> u64 {
+        let size = size.max(MIN_BUCKET);
+        let bucket = match size.checked_next_power_of_two() {
+            Some(bucket) if size <= COARSE_BUCKET => bucket,
+            Some(bucket) => {
+                let step = bucket / 2 / STEPS;
+                size.div_ceil(step) * step
+            }
+            None => size,
+        };
+
+        match size <= ceiling {
+            true => bucket.min(ceiling),
+            false => bucket,
+        }
+    }
+
+    #[inline]
+    pub fn ceiling(&self) -> u64 {
+        self.ceiling
+    }
+
+    /// Pop a cached buffer able to hold `size` bytes, if any.
+    pub fn acquire(&mut self, class: Class, size: u64) -> Option<wgpu::Buffer> {
+        let bucket = Self::bucket(size, self.ceiling);
+        let buffer = self.free.get_mut(&(class, bucket))?.pop()?;
+
+        self.stats.hits += 1;
+        self.stats.cached -= bucket;
+        self.lend(class, bucket);
+
+        Some(buffer)
+    }
+
+    /// Account for a buffer freshly created by the device.
+    pub fn record(&mut self, class: Class, bucket: u64) {
+        self.stats.misses += 1;
+        self.stats.allocated += bucket;
+        self.lend(class, bucket);
+    }
+
+    /// Forget a staging buffer that goes back to the device rather than to the free lists.
+    pub fn disown(&mut self, bytes: u64) {
+        self.stats.live -= bytes;
+        self.staging -= bytes;
+    }
+
+    /// Hand a buffer back for reuse.
+    pub fn release(&mut self, class: Class, buffer: wgpu::Buffer) {
+        let bucket = buffer.size();
+
+        self.stats.live -= bucket;
+        self.stats.cached += bucket;
+        if class == Class::Staging {
+            self.staging -= bucket;
+        }
+        self.free.entry((class, bucket)).or_default().push(buffer);
+    }
+
+    /// Attribute `bytes` of live memory to an element type and usage.
+    pub fn charge(&mut self, dtype: &'static str, usage: Usage, bytes: u64) {
+        *self.ledger.entry((dtype, usage)).or_default() += bytes;
+    }
+
+    pub fn refund(&mut self, dtype: &'static str, usage: Usage, bytes: u64) {
+        if let Some(held) = self.ledger.get_mut(&(dtype, usage)) {
+            *held -= bytes;
+        }
+    }
+
+    #[inline]
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
+
+    pub fn memory(&self) -> MemoryStats {
+        // Cached staging buffers are only ever used for staging, so they're reported as such.
+        let idle: u64 = self
+            .free
+            .iter()
+            .filter(|((class, _), _)| *class == Class::Staging)
+            .flat_map(|(_, buffers)| buffers.iter().map(wgpu::Buffer::size))
+            .sum();
+
+        let mut memory = MemoryStats {
+            total: self.stats.live + self.stats.cached,
+            cached: self.stats.cached - idle,
+            ..Default::default()
+        };
+        if self.staging + idle > 0 {
+            memory.by_usage.insert(Usage::Staging, self.staging + idle);
+        }
+
+        for (&(dtype, usage), &bytes) in self.ledger.iter().filter(|(_, bytes)| **bytes > 0) {
+            *memory.by_dtype.entry(dtype).or_default() += bytes;
+            *memory.by_usage.entry(usage).or_default() += bytes;
+        }
+
+        memory
+    }
+
+    /// Drop every cached buffer, giving the memory back to the device.
+    pub fn trim(&mut self) {
+        self.free.clear();
+        self.stats.cached = 0;
+    }
+
+    fn lend(&mut self, class: Class, bucket: u64) {
+        if class == Class::Staging {
+            self.staging += bucket;
+        }
+        self.stats.live += bucket;
+        self.stats.peak = self.stats.peak.max(self.stats.live);
+    }
+}
@@ -1,7 +1,11 @@
 //! A vendor-neutral GPU library that aims to provide a simple and straightforward alternative to
 //! modern frameworks for machine learning and AI-related workload.
 
-#![allow(non_upper_case_globals)]
+#![allow(
+    non_upper_case_globals,
+    private_bounds,
+    clippy::single_component_path_imports
+)]
 
 pub mod api;
 pub(crate) mod core;
@@ -9,8 +13,31 @@ pub(crate) mod internals;
 pub(crate) mod shaders;
 pub(crate) mod types;
 
-pub use self::api::{Tensor, TensorOrder};
+pub use self::{
+    api::{io, loss, nn, optim, quant, Tensor, TensorOrder},
+    core::error::Error,
+    internals::{AdapterInfo, Capabilities, MemoryStats, PoolStats, Usage},
+    types::i4::i4,
+};
+
+pub use self::core::{
+    group::InstanceGroup,
+    instance::{Instance, InstanceOpts},
+    profile::{Clock, Profile, Span},
+};
+
+/// Every adapter available, in the order `Instance::on_adapter` indexes them.
+pub fn adapters() -> Vec<AdapterInfo> {
+    internals::adapter::enumerate()
+        .iter()
+        .map(AdapterInfo::new)
+        .collect()
+}
 
 pub fn init() -> core::Instance {
     core::Instance::init().unwrap()
 }
+
+pub async fn init_async() -> core::Instance {
+    core::Instance::init_async().await.unwrap()
+}
@@ -6,8 +6,8 @@ use std::{
     mem,
 };
 
-use super::{BundleShader, OperationShader};
-use crate::core::{Bundle, Operation};
+use super::{BundleShader, Fusion, Kernel, OperationShader, Update};
+use crate::core::{bundle::Slots, ops::Workgroup, Bundle, Operation};
 
 #[derive(Clone, Copy, Default)]
 pub(crate) enum Phase {
@@ -20,14 +20,16 @@ pub(crate) enum Phase {
 pub(crate) struct Module {
     content: String,
     phase: Phase,
+    slots: Slots,
 }
 
 impl Module {
     #[inline]
-    pub fn new() -> Self {
+    pub fn new(slots: Slots) -> Self {
         Self {
             content: String::new(),
             phase: Phase::default(),
+            slots,
         }
     }
 
@@ -38,30 +40,124 @@ impl Module {
 
     #[inline]
     pub fn write<S: AsRef<str>>(&mut self, input: S) {
-        writeln!(&mut self.content, "{}", input.as_ref());
+        let _ = writeln!(&mut self.content, "{}", input.as_ref());
     }
 }
 
+#[allow(dead_code)]
 #[derive(Clone, Copy)]
 pub(crate) enum Directive {
     F16,
 }
 
 pub(crate) trait ShaderCore {
+    #[allow(dead_code)]
     fn insert_directive(&mut self, directive: Directive);
-    fn insert_header(&mut self, elements: &dyn BundleShader);
+    fn insert_header(&mut self, elements: &dyn BundleShader, slot: u32);
     fn insert_compute(&mut self, op: &dyn OperationShader);
+    fn insert_fusion(&mut self, fusion: &Fusion, workgroup: &Workgroup);
+    fn insert_kernel(&mut self, kernel: &Kernel, workgroup: &Workgroup);
+    fn insert_update(&mut self, update: &Update, workgroup: &Workgroup);
 }
 
 #[cfg(feature = "wsgl")]
 impl ShaderCore for Module {
     fn insert_directive(&mut self, directive: Directive) {
         let extension = match directive {
-            Directive::F16 => "shader-f16",
+            Directive::F16 => "enable f16;",
         };
         self.write(extension);
     }
-    fn insert_header(&mut self, bundle: &dyn BundleShader) {}
 
-    fn insert_compute(&mut self, op: &dyn OperationShader) {}
+    fn insert_header(&mut self, bundle: &dyn BundleShader, slot: u32) {
+        let (group, binding) = self.slots.at(slot);
+        self.write(format!(
+            "@group({}) @binding({}) {} {}: {};",
+            group,
+            binding,
+            bundle.var(),
+            bundle.alias(slot),
+            bundle.storage(),
+        ));
+    }
+
+    fn insert_compute(&mut self, op: &dyn OperationShader) {
+        self.phase = Phase::Compute;
+        self.write(format!("@compute {}", op.workgroup()));
+        self.write(
+            "fn main(@builtin(global_invocation_id) gid: vec3<u32>, \
+             @builtin(num_workgroups) groups: vec3<u32>) {",
+        );
+        // Large dispatches spill over a 2-D or 3-D grid of workgroups; flatten it back.
+        self.write(format!(
+            "    let idx = gid.x + (gid.y + gid.z * groups.y) * groups.x * {}u;",
+            op.lanes()
+        ));
+    }
+
+    fn insert_fusion(&mut self, fusion: &Fusion, workgroup: &Workgroup) {
+        for (slot, input) in fusion.inputs.iter().enumerate() {
+            self.insert_header(*input, slot as u32);
+        }
+        let root = fusion.inputs.len() as u32;
+        self.insert_header(fusion.root, root);
+
+        self.insert_compute(workgroup);
+        self.write(format!(
+            "    if (idx >= {}u) {{ return; }}",
+            fusion.root.dims().size()
+        ));
+        for line in fusion.lets.iter() {
+            self.write(format!("    {}", line));
+        }
+        self.write(format!(
+            "    {}[idx] = {};",
+            fusion.root.alias(root),
+            fusion.expr
+        ));
+        self.write("}");
+        self.phase = Phase::Ready;
+    }
+
+    fn insert_kernel(&mut self, kernel: &Kernel, workgroup: &Workgroup) {
+        for (slot, input) in kernel.inputs.iter().enumerate() {
+            self.insert_header(*input, slot as u32);
+        }
+        self.insert_header(kernel.root, kernel.inputs.len() as u32);
+
+        self.insert_compute(workgroup);
+        self.write(format!(
+            "    if (idx >= {}u) {{ return; }}",
+            kernel.root.dims().size()
+        ));
+        for line in kernel.body.iter() {
+            self.write(format!("    {}", line));
+        }
+        self.write("}");
+        self.phase = Phase::Ready;
+    }
+
+    fn insert_update(&mut self, update: &Update, workgroup: &Workgroup) {
+        self.insert_header(update.param, 0);
+        self.insert_header(update.grad, 1);
+        for (slot, state) in update.states.iter().enumerate() {
+            self.insert_header(*state, 2 + slot as u32);
+        }
+        let (group, binding) = self.slots.at(update.slot);
+        self.write(format!(
+            "@group({}) @binding({}) var<storage, read_write> {}: array<f32>;",
+            group, binding, update.scalars
+        ));
+
+        self.insert_compute(workgroup);
+        self.write(format!(
+            "    if (idx >= {}u) {{ return; }}",
+            update.param.dims().size()
+        ));
+        for line in update.body.iter() {
+            self.write(format!("    {}", line));
+        }
+        self.write("}");
+        self.phase = Phase::Ready;
+    }
 }
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc};
+
+use super::interface::ElementShader;
+use crate::core::Operand;
+
+#[inline]
+fn key(operand: &dyn Operand) -> *const () {
+    operand as *const dyn Operand as *const ()
+}
+
+/// Chain of elementwise operations traced from a root `Operand`, collapsed into a single compute
+/// body so intermediates never get a buffer of their own.
+pub(crate) struct Fusion<'g> {
+    pub root: &'g dyn Operand,
+    pub inputs: Vec<&'g dyn Operand>,
+    /// Intermediates read more than once, bound with `let` ahead of the store so they're
+    /// computed a single time.
+    pub lets: Vec<String>,
+    pub expr: String,
+    leaves: Vec<&'g dyn Operand>,
+    uses: HashMap<*const (), u32>,
+    named: HashMap<*const (), String>,
+}
+
+impl<'g> Fusion<'g> {
+    /// Returns `None` when the root isn't an elementwise operation.
+    pub fn trace(root: &'g dyn Operand) -> Option<Self> {
+        if !root.node()?.ty().elementwise() {
+            return None;
+        }
+
+        let mut fusion = Self {
+            root,
+            inputs: Vec::new(),
+            lets: Vec::new(),
+            expr: String::new(),
+            leaves: Vec::new(),
+            uses: HashMap::new(),
+            named: HashMap::new(),
+        };
+        fusion.count(root);
+        fusion.expr = fusion.walk(root);
+        fusion.bind();
+
+        Some(fusion)
+    }
+
+    fn fusible(&self, operand: &dyn Operand) -> bool {
+        let elementwise = operand.node().is_some_and(|node| node.ty().elementwise());
+        elementwise && !operand.ready() && operand.dims() == self.root.dims()
+    }
+
+    /// How many times each fused operand is read within the chain, every node visited once.
+    fn count(&mut self, root: &'g dyn Operand) {
+        let mut stack = vec![root];
+
+        while let Some(operand) = stack.pop() {
+            for side in operand.node().unwrap().operands() {
+                let side = side.as_ref();
+                if !self.fusible(side) {
+                    continue;
+                }
+                let uses = self.uses.entry(key(side)).or_insert(0);
+                *uses += 1;
+                if *uses == 1 {
+                    stack.push(side);
+                }
+            }
+        }
+    }
+
+    /// Expression of the chain ending at `root`, built bottom up with an explicit stack since
+    /// chains can be arbitrarily long. Shared intermediates get a `let` once complete, after the
+    /// ones they read.
+    fn walk(&mut self, root: &'g dyn Operand) -> String {
+        let mut exprs: HashMap<*const (), String> = HashMap::new();
+        let mut stack = vec![(root, false)];
+
+        while let Some((operand, expanded)) = stack.pop() {
+            if self.named.contains_key(&key(operand)) {
+                continue;
+            }
+            let node = operand.node().unwrap();
+
+            if !expanded {
+                stack.push((operand, true));
+                for side in node.operands().iter().rev() {
+                    let side = side.as_ref();
+                    if self.fusible(side) && !self.named.contains_key(&key(side)) {
+                        stack.push((side, false));
+                    }
+                }
+                continue;
+            }
+
+            let sides: Vec<String> = node
+                .operands()
+                .iter()
+                .map(|side: &'g Arc<dyn Operand>| {
+                    let side = side.as_ref();
+                    if !self.fusible(side) {
+                        self.leaf(side)
+                    } else if self.shared(side) {
+                        self.named[&key(side)].clone()
+                    } else {
+                        exprs.remove(&key(side)).unwrap()
+                    }
+                })
+                .collect();
+            let expr = node.ty().expr(self.root.typename(), &sides);
+
+            if self.shared(operand) {
+                let name = format!("v{}", self.lets.len());
+                self.lets.push(format!("let {} = {};", name, expr));
+                self.named.insert(key(operand), name);
+            } else {
+                exprs.insert(key(operand), expr);
+            }
+        }
+
+        exprs.remove(&key(root)).unwrap()
+    }
+
+    /// Whether an intermediate is read more than once, and so held in a `let`.
+    fn shared(&self, operand: &dyn Operand) -> bool {
+        self.uses.get(&key(operand)).is_some_and(|uses| *uses > 1)
+    }
+
+    /// Placeholder for an operand read by the chain, bound once the text is complete.
+    fn leaf(&mut self, operand: &'g dyn Operand) -> String {
+        let leaf = match self
+            .leaves
+            .iter()
+            .position(|leaf| std::ptr::addr_eq(*leaf, operand))
+        {
+            Some(leaf) => leaf,
+            None => {
+                self.leaves.push(operand);
+                self.leaves.len() - 1
+            }
+        };
+
+        format!("${}$", leaf)
+    }
+
+    /// Operands are bound in the order they are first read, the root coming last. Some
+    /// expressions ignore operands they're handed, and those must not get a binding the
+    /// compiled pipeline would drop.
+    fn bind(&mut self) {
+        let mut slots: Vec<Option<usize>> = vec![None; self.leaves.len()];
+
+        for text in self.lets.iter_mut().chain(std::iter::once(&mut self.expr)) {
+            let mut bound = String::with_capacity(text.len());
+            let mut rest = text.as_str();
+            while let Some(start) = rest.find('$') {
+                let end = start + 1 + rest[start + 1..].find('$').unwrap();
+                let leaf: usize = rest[start + 1..end].parse().unwrap();
+                let operand = self.leaves[leaf];
+                let slot = *slots[leaf].get_or_insert_with(|| {
+                    self.inputs.push(operand);
+                    self.inputs.len() - 1
+                });
+
+                bound.push_str(&rest[..start]);
+                bound.push_str(&format!("{}[idx]", operand.alias(slot as u32)));
+                rest = &rest[end + 1..];
+            }
+            bound.push_str(rest);
+            *text = bound;
+        }
+    }
+}
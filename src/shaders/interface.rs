@@ -1,7 +1,8 @@
-use std::any::type_name;
-
 use crate::{
-    core::{Bundle, Operation},
+    core::{
+        ops::{ActivationType, ElementType, GradientType, Shader, Side, UnaryType, Workgroup},
+        Bundle, Operation,
+    },
     types::{Packet, SupportedPacket},
 };
 
@@ -20,9 +21,8 @@ impl ShaderAbstraction {
 }*/
 
 pub(crate) trait BundleShader {
-    fn alias(&self) -> String;
-    fn binding(&self) -> String;
-    fn group(&self) -> String;
+    /// Name of the operand bound at `slot` of a generated kernel.
+    fn alias(&self, slot: u32) -> String;
     fn storage(&self) -> String;
     fn var(&self) -> String;
 }
@@ -33,23 +33,18 @@ where
     Packet<T>: SupportedPacket,
 {
     #[inline]
-    fn alias(&self) -> String {
-        format!("tsr_{}_{}", self.typename(), self.props.binding)
-    }
-
-    #[inline]
-    fn binding(&self) -> String {
-        format!("binding({})", self.props.binding)
-    }
-
-    #[inline]
-    fn group(&self) -> String {
-        format!("group({})", self.props.group)
+    fn alias(&self, slot: u32) -> String {
+        format!("tsr_{}_{}", self.typename(), slot)
     }
 
     fn storage(&self) -> String {
         if self.ready() {
-            format!("array<{}, {}>", self.typename(), self.props.dims)
+            let len = self
+                .props
+                .dims
+                .size()
+                .div_ceil(<Packet<T> as SupportedPacket>::LANES);
+            format!("array<{}, {}>", self.typename(), len)
         } else {
             format!("array<{}>", self.typename())
         }
@@ -57,7 +52,7 @@ where
 
     fn var(&self) -> String {
         let (space, mode) = {
-            if self.buffer.is_uniform() {
+            if self.ready() && self.buffer.read().unwrap().is_uniform() {
                 ("uniform", "read")
             } else {
                 ("storage", "read_write")
@@ -68,11 +63,14 @@ where
 }
 
 // will be moved to `core` module
+#[allow(dead_code)]
 trait SupportedComponents {}
 
 pub(crate) trait OperationShader {
     //fn alias(&self) -> String {}
     fn workgroup(&self) -> String;
+    /// Invocations per workgroup, as laid out by `workgroup`.
+    fn lanes(&self) -> u32;
 }
 
 #[cfg(feature = "wsgl")]
@@ -82,11 +80,163 @@ where
 {
     #[inline]
     fn workgroup(&self) -> String {
-        format!("@workgroup_size({})", self.workgroup.collapse())
+        self.workgroup.workgroup()
+    }
+
+    #[inline]
+    fn lanes(&self) -> u32 {
+        self.workgroup.collapse()
+    }
+}
+
+#[cfg(feature = "wsgl")]
+impl OperationShader for Workgroup {
+    #[inline]
+    fn workgroup(&self) -> String {
+        format!("@workgroup_size({})", self.collapse())
+    }
+
+    #[inline]
+    fn lanes(&self) -> u32 {
+        self.collapse()
+    }
+}
+
+pub(crate) trait ElementShader {
+    /// Expression for a single element, given those of its operands.
+    fn expr(&self, ty: &str, args: &[String]) -> String;
+}
+
+#[cfg(feature = "wsgl")]
+impl ElementShader for ElementType {
+    fn expr(&self, _ty: &str, args: &[String]) -> String {
+        let (a, b) = (&args[0], &args[1]);
+        match self {
+            ElementType::Add => format!("({} + {})", a, b),
+            ElementType::Sub => format!("({} - {})", a, b),
+            ElementType::Mul => format!("({} * {})", a, b),
+            ElementType::Div => format!("({} / {})", a, b),
+            ElementType::Exp => format!("pow({}, {})", a, b),
+            ElementType::Rot => format!("pow({}, 1.0 / {})", a, b),
+        }
+    }
+}
+
+#[cfg(feature = "wsgl")]
+impl ElementShader for ActivationType {
+    fn expr(&self, _ty: &str, args: &[String]) -> String {
+        let x = &args[0];
+        match self {
+            ActivationType::Relu => format!("max({}, 0.0)", x),
+            ActivationType::Sigmoid => format!("(1.0 / (1.0 + exp(-{})))", x),
+            ActivationType::Tanh => format!("tanh({})", x),
+            ActivationType::Gelu => format!(
+                "(0.5 * {x} * (1.0 + tanh(0.7978846 * ({x} + 0.044715 * {x} * {x} * {x}))))",
+                x = x
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "wsgl")]
+impl ElementShader for UnaryType {
+    fn expr(&self, _ty: &str, args: &[String]) -> String {
+        let x = &args[0];
+        match self {
+            UnaryType::Abs => format!("abs({})", x),
+            UnaryType::Exp => format!("exp({})", x),
+            UnaryType::Log => format!("log({})", x),
+            UnaryType::Neg => format!("(-{})", x),
+            UnaryType::Sqrt => format!("sqrt({})", x),
+        }
+    }
+}
+
+#[cfg(feature = "wsgl")]
+impl ElementShader for GradientType {
+    fn expr(&self, ty: &str, args: &[String]) -> String {
+        match self {
+            GradientType::Element(op, side) => {
+                let (a, b, g) = (&args[0], &args[1], &args[2]);
+                match (op, side) {
+                    (ElementType::Add, _) => g.clone(),
+                    (ElementType::Sub, Side::Lhs) => g.clone(),
+                    (ElementType::Sub, Side::Rhs) => format!("(-{})", g),
+                    (ElementType::Mul, Side::Lhs) => format!("({} * {})", g, b),
+                    (ElementType::Mul, Side::Rhs) => format!("({} * {})", g, a),
+                    (ElementType::Div, Side::Lhs) => format!("({} / {})", g, b),
+                    (ElementType::Div, Side::Rhs) => {
+                        format!("(-{} * {} / ({} * {}))", g, a, b, b)
+                    }
+                    (ElementType::Exp, Side::Lhs) => {
+                        format!("({} * {} * pow({}, {} - 1.0))", g, b, a, b)
+                    }
+                    (ElementType::Exp, Side::Rhs) => {
+                        format!("({} * pow({}, {}) * log({}))", g, a, b, a)
+                    }
+                    (ElementType::Rot, Side::Lhs) => {
+                        format!("({} * pow({}, 1.0 / {}) / ({} * {}))", g, a, b, b, a)
+                    }
+                    (ElementType::Rot, Side::Rhs) => format!(
+                        "(-{} * pow({}, 1.0 / {}) * log({}) / ({} * {}))",
+                        g, a, b, a, b, b
+                    ),
+                }
+            }
+            GradientType::Activation(act) => {
+                let (x, g) = (&args[0], &args[1]);
+                match act {
+                    ActivationType::Relu => format!("select(0.0, {}, {} > 0.0)", g, x),
+                    ActivationType::Sigmoid => {
+                        let s = act.expr(ty, &args[..1]);
+                        format!("({} * {} * (1.0 - {}))", g, s, s)
+                    }
+                    ActivationType::Tanh => {
+                        format!("({} * (1.0 - tanh({}) * tanh({})))", g, x, x)
+                    }
+                    ActivationType::Gelu => format!(
+                        "({g} * (0.5 * (1.0 + tanh(0.7978846 * ({x} + 0.044715 * {x} * {x} * {x}))) \
+                         + 0.5 * {x} * (1.0 - pow(tanh(0.7978846 * ({x} + 0.044715 * {x} * {x} * {x})), 2.0)) \
+                         * 0.7978846 * (1.0 + 0.134145 * {x} * {x})))",
+                        g = g,
+                        x = x
+                    ),
+                }
+            }
+            GradientType::Max => {
+                let (x, m, g) = (&args[0], &args[1], &args[2]);
+                format!("select(0.0, {}, {} == {})", g, x, m)
+            }
+            GradientType::Unary(op) => {
+                let (x, g) = (&args[0], &args[1]);
+                match op {
+                    UnaryType::Abs => format!("({} * sign({}))", g, x),
+                    UnaryType::Exp => format!("({} * exp({}))", g, x),
+                    UnaryType::Log => format!("({} / {})", g, x),
+                    UnaryType::Neg => format!("(-{})", g),
+                    UnaryType::Sqrt => format!("({} * 0.5 / sqrt({}))", g, x),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wsgl")]
+impl ElementShader for Shader {
+    fn expr(&self, ty: &str, args: &[String]) -> String {
+        match self {
+            Shader::Element(op) => op.expr(ty, args),
+            Shader::Activation(act) => act.expr(ty, args),
+            Shader::Gradient(grad) => grad.expr(ty, args),
+            Shader::Unary(op) => op.expr(ty, args),
+            Shader::Fill(value) => format!("{}({:?})", ty, value),
+            Shader::Dimensional(_) => unreachable!(),
+        }
     }
 }
 
 #[cfg(feature = "wsgl")]
+#[allow(unused_macros)]
 macro_rules! impl_arithmetic {
     ($($op:ident, $fn:ident, )*) => {$(
         impl OperationShader for Operation {
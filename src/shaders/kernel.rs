@@ -0,0 +1,523 @@
+use crate::core::{
+    ops::{DimensionalType, Quant, Scores, Shader},
+    Operand,
+};
+
+/// Standalone compute body for an operation that can't be fused, reading its operands directly.
+pub(crate) struct Kernel<'g> {
+    pub root: &'g dyn Operand,
+    pub inputs: Vec<&'g dyn Operand>,
+    pub body: Vec<String>,
+}
+
+/// Row-major strides of `dims`.
+fn strides(dims: &[u32]) -> Vec<u32> {
+    let mut strides = vec![1; dims.len()];
+    for axis in (0..dims.len().saturating_sub(1)).rev() {
+        strides[axis] = strides[axis + 1] * dims[axis + 1];
+    }
+    strides
+}
+
+/// Left-pad `dims` with unit axes up to `rank`.
+fn pad(dims: Vec<u32>, rank: usize) -> Vec<u32> {
+    let mut padded = vec![1; rank.saturating_sub(dims.len())];
+    padded.extend(dims);
+    padded
+}
+
+/// Byte at `offset` of a buffer of packed little-endian words.
+fn byte(src: &str, offset: &str) -> String {
+    format!(
+        "(({src}[({o}) / 4u] >> ((({o}) % 4u) * 8u)) & 0xffu)",
+        src = src,
+        o = offset
+    )
+}
+
+/// f16 made of the two bytes at `offset`, widened to f32.
+fn half(src: &str, offset: &str) -> String {
+    format!(
+        "unpack2x16float({} | ({} << 8u)).x",
+        byte(src, offset),
+        byte(src, &format!("{} + 1u", offset))
+    )
+}
+
+/// Little-endian word made of the four, possibly unaligned, bytes at `offset`.
+fn word(src: &str, offset: &str) -> String {
+    (0..4)
+        .map(|at| {
+            format!(
+                "({} << {}u)",
+                byte(src, &format!("{} + {}u", offset, at)),
+                at * 8
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Signed integer of `bits` at element `offset` of a buffer of packed words, low lanes first.
+fn lane(src: &str, offset: &str, bits: u32) -> String {
+    let lanes = 32 / bits;
+    format!(
+        "(bitcast<i32>({src}[({o}) / {l}u] << (({l}u - 1u - ({o}) % {l}u) * {b}u)) >> {r}u)",
+        src = src,
+        o = offset,
+        l = lanes,
+        b = bits,
+        r = 32 - bits
+    )
+}
+
+/// Element at flat `index` of a row-major `src` of `dims`, adjusted as `scores` asks.
+fn score(src: &str, index: &str, dims: &[u32], scores: Scores, ty: &str) -> String {
+    let mut value = format!("{}[{}]", src, index);
+    if scores.scale != 1.0 {
+        value = format!("{} * {}({:?})", value, ty, scores.scale);
+    }
+    if !scores.causal {
+        return value;
+    }
+
+    let rank = dims.len();
+    let (queries, keys) = (dims[rank - 2], dims[rank - 1]);
+    format!(
+        "select({v}, {ty}(-3.40282347e38), i32(({i}) % {k}u) > i32((({i}) / {k}u) % {q}u) + {shift})",
+        v = value,
+        ty = ty,
+        i = index,
+        k = keys,
+        q = queries,
+        shift = keys as i64 - queries as i64
+    )
+}
+
+impl<'g> Kernel<'g> {
+    /// Returns `None` when the root's operation has no kernel to generate.
+    pub fn trace(root: &'g dyn Operand) -> Option<Self> {
+        let node = root.node()?;
+        let ty = match node.ty() {
+            Shader::Dimensional(ty) => ty,
+            _ => return None,
+        };
+
+        let inputs: Vec<&'g dyn Operand> =
+            node.operands().iter().map(|side| side.as_ref()).collect();
+        let out = root.dims().pull();
+        let dims: Vec<Vec<u32>> = inputs.iter().map(|input| input.dims().pull()).collect();
+
+        // The same operand may be read on several sides, yet only bound once.
+        let mut unique: Vec<&'g dyn Operand> = Vec::new();
+        let names: Vec<String> = inputs
+            .iter()
+            .map(|input| {
+                let slot = match unique
+                    .iter()
+                    .position(|seen| std::ptr::addr_eq(*seen, *input))
+                {
+                    Some(slot) => slot,
+                    None => {
+                        unique.push(*input);
+                        unique.len() - 1
+                    }
+                };
+                input.alias(slot as u32)
+            })
+            .collect();
+        let (dst, ty_name) = (root.alias(unique.len() as u32), root.typename());
+
+        let body = match ty {
+            DimensionalType::Matmul => {
+                // Leading axes are batched over, matching on both sides.
+                let rank = dims[0].len();
+                let (m, k, n) = (dims[0][rank - 2], dims[0][rank - 1], dims[1][rank - 1]);
+                vec![
+                    format!("let b = idx / {}u;", m * n),
+                    format!("let i = (idx % {}u) / {}u;", m * n, n),
+                    format!("let j = idx % {}u;", n),
+                    format!("var acc = {}(0);", ty_name),
+                    format!("for (var t = 0u; t < {}u; t = t + 1u) {{", k),
+                    format!(
+                        "    acc = acc + {}[b * {}u + i * {}u + t] * {}[b * {}u + t * {}u + j];",
+                        names[0],
+                        m * k,
+                        k,
+                        names[1],
+                        k * n,
+                        n
+                    ),
+                    "}".to_string(),
+                    format!("{}[idx] = acc;", dst),
+                ]
+            }
+            DimensionalType::PackedMatmul { bits, block } => {
+                // Rows are every leading axis of the left-hand side; weights are unpacked a
+                // block at a time, scaled once the block is summed.
+                let (n, k) = (dims[1][0], dims[1][1]);
+                let groups = k / block;
+                vec![
+                    format!("let i = idx / {}u;", n),
+                    format!("let j = idx % {}u;", n),
+                    format!("var acc = {}(0);", ty_name),
+                    format!("for (var g = 0u; g < {}u; g = g + 1u) {{", groups),
+                    format!("    let s = {}[j * {}u + g];", names[2], groups),
+                    format!("    let z = {}[j * {}u + g];", names[3], groups),
+                    format!("    var part = {}(0);", ty_name),
+                    format!(
+                        "    for (var t = g * {b}u; t < g * {b}u + {b}u; t = t + 1u) {{",
+                        b = block
+                    ),
+                    format!(
+                        "        let w = f32({}) - z;",
+                        lane(&names[1], &format!("j * {}u + t", k), bits)
+                    ),
+                    format!(
+                        "        part = part + {}[i * {}u + t] * {}(w);",
+                        names[0], k, ty_name
+                    ),
+                    "    }".to_string(),
+                    format!("    acc = acc + part * {}(s);", ty_name),
+                    "}".to_string(),
+                    format!("{}[idx] = acc;", dst),
+                ]
+            }
+            DimensionalType::Unpack { bits, block } => {
+                let k = dims[0][1];
+                let groups = k / block;
+                vec![
+                    format!(
+                        "let pair = (idx / {}u) * {}u + (idx % {}u) / {}u;",
+                        k, groups, k, block
+                    ),
+                    format!(
+                        "{}[idx] = {}((f32({}) - {}[pair]) * {}[pair]);",
+                        dst,
+                        ty_name,
+                        lane(&names[0], "idx", bits),
+                        names[2],
+                        names[1]
+                    ),
+                ]
+            }
+            DimensionalType::Transpose => {
+                // Swaps the two innermost axes, leading ones being batched over.
+                let rank = dims[0].len();
+                let (r, c) = (dims[0][rank - 2], dims[0][rank - 1]);
+                vec![
+                    format!("let b = idx / {}u;", r * c),
+                    format!("let i = (idx % {}u) / {}u;", r * c, r),
+                    format!("let j = idx % {}u;", r),
+                    format!(
+                        "{}[idx] = {}[b * {}u + j * {}u + i];",
+                        dst,
+                        names[0],
+                        r * c,
+                        c
+                    ),
+                ]
+            }
+            DimensionalType::Reshape => vec![format!("{}[idx] = {}[idx];", dst, names[0])],
+            DimensionalType::LogSumExp { axis, scores } => {
+                // Online normalizer: a single pass over the row keeps a running maximum and
+                // rescales the running sum whenever it grows.
+                let axis = axis as usize;
+                let len = dims[0][axis];
+                let stride: u32 = dims[0][axis + 1..].iter().product();
+                vec![
+                    format!(
+                        "let base = (idx / {}u) * {}u + idx % {}u;",
+                        stride,
+                        len * stride,
+                        stride
+                    ),
+                    format!(
+                        "var m = {};",
+                        score(&names[0], "base", &dims[0], scores, ty_name)
+                    ),
+                    format!("var s = {}(0);", ty_name),
+                    format!("for (var r = 0u; r < {}u; r = r + 1u) {{", len),
+                    format!(
+                        "    let v = {};",
+                        score(
+                            &names[0],
+                            &format!("base + r * {}u", stride),
+                            &dims[0],
+                            scores,
+                            ty_name
+                        )
+                    ),
+                    "    let next = max(m, v);".to_string(),
+                    "    s = s * exp(m - next) + exp(v - next);".to_string(),
+                    "    m = next;".to_string(),
+                    "}".to_string(),
+                    format!("{}[idx] = m + log(s);", dst),
+                ]
+            }
+            DimensionalType::Softmax { axis, log, scores } => {
+                // Row statistics come in computed once per row, rather than once per element.
+                let axis = axis as usize;
+                let len = dims[0][axis];
+                let stride: u32 = dims[0][axis + 1..].iter().product();
+                let mut body = vec![
+                    format!(
+                        "let row = (idx / {}u) * {}u + idx % {}u;",
+                        len * stride,
+                        stride,
+                        stride
+                    ),
+                    format!(
+                        "let v = {};",
+                        score(&names[0], "idx", &dims[0], scores, ty_name)
+                    ),
+                ];
+                body.push(if log {
+                    format!("{}[idx] = v - {}[row];", dst, names[1])
+                } else {
+                    format!("{}[idx] = exp(v - {}[row]);", dst, names[1])
+                });
+                body
+            }
+            DimensionalType::Unfold(window) => {
+                let (c, h, w) = (dims[0][1], dims[0][2], dims[0][3]);
+                let (kh, kw) = window.kernel;
+                let (oh, ow) = window.extent((h, w));
+                let patch = c * kh * kw;
+                vec![
+                    format!("let row = idx / {}u;", patch),
+                    format!("let col = idx % {}u;", patch),
+                    format!("let n = row / {}u;", oh * ow),
+                    format!("let oy = (row % {}u) / {}u;", oh * ow, ow),
+                    format!("let ox = row % {}u;", ow),
+                    format!("let ch = col / {}u;", kh * kw),
+                    format!("let ky = (col % {}u) / {}u;", kh * kw, kw),
+                    format!("let kx = col % {}u;", kw),
+                    format!(
+                        "let iy = i32(oy * {}u + ky) - {};",
+                        window.stride.0, window.padding.0
+                    ),
+                    format!(
+                        "let ix = i32(ox * {}u + kx) - {};",
+                        window.stride.1, window.padding.1
+                    ),
+                    format!(
+                        "if (iy < 0 || ix < 0 || iy >= {} || ix >= {}) {{ {}[idx] = {}(0); return; }}",
+                        h, w, dst, ty_name
+                    ),
+                    format!(
+                        "{}[idx] = {}[((n * {}u + ch) * {}u + u32(iy)) * {}u + u32(ix)];",
+                        dst, names[0], c, h, w
+                    ),
+                ]
+            }
+            DimensionalType::Fold(window) => {
+                let (c, h, w) = (out[1], out[2], out[3]);
+                let (kh, kw) = window.kernel;
+                let (sh, sw) = window.stride;
+                let (oh, ow) = window.extent((h, w));
+                let patch = c * kh * kw;
+                vec![
+                    format!("let n = idx / {}u;", c * h * w),
+                    format!("let ch = (idx / {}u) % {}u;", h * w, c),
+                    format!("let iy = i32((idx / {}u) % {}u) + {};", w, h, window.padding.0),
+                    format!("let ix = i32(idx % {}u) + {};", w, window.padding.1),
+                    format!("var acc = {}(0);", ty_name),
+                    format!("for (var ky = 0; ky < {}; ky = ky + 1) {{", kh),
+                    format!("    for (var kx = 0; kx < {}; kx = kx + 1) {{", kw),
+                    "        let y = iy - ky;".to_string(),
+                    "        let x = ix - kx;".to_string(),
+                    format!(
+                        "        if (y < 0 || x < 0 || y % {sh} != 0 || x % {sw} != 0 || y / {sh} >= {oh} || x / {sw} >= {ow}) {{ continue; }}",
+                        sh = sh,
+                        sw = sw,
+                        oh = oh,
+                        ow = ow
+                    ),
+                    format!(
+                        "        let row = n * {}u + u32(y / {}) * {}u + u32(x / {});",
+                        oh * ow,
+                        sh,
+                        ow,
+                        sw
+                    ),
+                    format!(
+                        "        acc = acc + {}[row * {}u + ch * {}u + u32(ky) * {}u + u32(kx)];",
+                        names[0],
+                        patch,
+                        kh * kw,
+                        kw
+                    ),
+                    "    }".to_string(),
+                    "}".to_string(),
+                    format!("{}[idx] = acc;", dst),
+                ]
+            }
+            DimensionalType::Gather => {
+                // Indices are stored in the table's packet type and truncated when read; negative
+                // ones count from the last row. A row is everything past the first axis.
+                let row = dims[0][1..].iter().product::<u32>();
+                let rows = dims[0][0];
+                vec![
+                    format!("let raw = i32({}[idx / {}u]);", names[1], row),
+                    format!("let index = u32(select(raw, raw + {}, raw < 0));", rows),
+                    format!(
+                        "{}[idx] = {}[index * {}u + idx % {}u];",
+                        dst, names[0], row, row
+                    ),
+                ]
+            }
+            DimensionalType::Scatter => {
+                let row = out[1..].iter().product::<u32>();
+                let count = dims[0].iter().product::<u32>();
+                vec![
+                    format!("let index = idx / {}u;", row),
+                    format!("var acc = {}(0);", ty_name),
+                    format!("for (var p = 0u; p < {}u; p = p + 1u) {{", count),
+                    format!("    let raw = i32({}[p]);", names[0]),
+                    format!(
+                        "    if (u32(select(raw, raw + {}, raw < 0)) == index) {{ acc = acc + {}[p * {}u + idx % {}u]; }}",
+                        out[0], names[1], row, row
+                    ),
+                    "}".to_string(),
+                    format!("{}[idx] = acc;", dst),
+                ]
+            }
+            DimensionalType::Expand => {
+                let src = pad(dims[0].clone(), out.len());
+                let stride = strides(&src);
+                let mut body = vec!["var rem = idx;".to_string(), "var src = 0u;".to_string()];
+                for axis in (0..out.len()).rev() {
+                    body.push(format!("let c{} = rem % {}u;", axis, out[axis]));
+                    body.push(format!("rem = rem / {}u;", out[axis]));
+                    if src[axis] != 1 {
+                        body.push(format!("src = src + c{} * {}u;", axis, stride[axis]));
+                    }
+                }
+                body.push(format!("{}[idx] = {}[src];", dst, names[0]));
+                body
+            }
+            DimensionalType::Sum | DimensionalType::Max => {
+                let src = dims[0].clone();
+                let out = pad(out, src.len());
+                let stride = strides(&src);
+                let reduced: Vec<usize> = (0..src.len())
+                    .filter(|axis| out[*axis] == 1 && src[*axis] != 1)
+                    .collect();
+                let span: u32 = reduced.iter().map(|axis| src[*axis]).product();
+
+                let mut body = vec!["var rem = idx;".to_string(), "var base = 0u;".to_string()];
+                for axis in (0..src.len()).rev().filter(|axis| !reduced.contains(axis)) {
+                    body.push(format!(
+                        "base = base + (rem % {}u) * {}u;",
+                        out[axis], stride[axis]
+                    ));
+                    body.push(format!("rem = rem / {}u;", out[axis]));
+                }
+                let fold = match ty {
+                    DimensionalType::Max => {
+                        body.push(format!("var acc = {}[base];", names[0]));
+                        format!("max(acc, {}[off])", names[0])
+                    }
+                    _ => {
+                        body.push(format!("var acc = {}(0);", ty_name));
+                        format!("acc + {}[off]", names[0])
+                    }
+                };
+                body.push(format!("for (var r = 0u; r < {}u; r = r + 1u) {{", span));
+                body.push("    var rr = r;".to_string());
+                body.push("    var off = base;".to_string());
+                for axis in reduced.iter().rev() {
+                    body.push(format!(
+                        "    off = off + (rr % {}u) * {}u;",
+                        src[*axis], stride[*axis]
+                    ));
+                    body.push(format!("    rr = rr / {}u;", src[*axis]));
+                }
+                body.push(format!("    acc = {};", fold));
+                body.push("}".to_string());
+                body.push(format!("{}[idx] = acc;", dst));
+                body
+            }
+            DimensionalType::Dequantize(quant) => {
+                let src = names[0].as_str();
+                let mut body = vec![
+                    format!("let j = idx % {}u;", quant.block()),
+                    format!(
+                        "let base = (idx / {}u) * {}u;",
+                        quant.block(),
+                        quant.bytes()
+                    ),
+                ];
+                // Nibbles hold elements `j` and `j + 16` of a block, low half first.
+                let nibble = |qs: u32| {
+                    format!(
+                        "let q = {}; let nib = select(q & 0xfu, q >> 4u, j >= 16u);",
+                        byte(src, &format!("base + {}u + j % 16u", qs))
+                    )
+                };
+                // The fifth bits of every element, packed in a word.
+                let high = |qh: u32| {
+                    format!(
+                        "let qh = {}; let nib5 = nib | (((qh >> j) & 1u) << 4u);",
+                        word(src, &format!("base + {}u", qh))
+                    )
+                };
+                let d = format!("let d = {};", half(src, "base"));
+                let m = format!("let m = {};", half(src, "base + 2u"));
+
+                match quant {
+                    Quant::F16 => body.push(format!(
+                        "let h = unpack2x16float({}[idx / 2u]); {}[idx] = {}(select(h.x, h.y, idx % 2u == 1u));",
+                        src, dst, ty_name
+                    )),
+                    Quant::BF16 => body.push(format!(
+                        "{}[idx] = {}(bitcast<f32>((({}[idx / 2u] >> ((idx % 2u) * 16u)) & 0xffffu) << 16u));",
+                        dst, ty_name, src
+                    )),
+                    Quant::Q4_0 => body.extend([
+                        d,
+                        nibble(2),
+                        format!("{}[idx] = {}((f32(nib) - 8.0) * d);", dst, ty_name),
+                    ]),
+                    Quant::Q4_1 => body.extend([
+                        d,
+                        m,
+                        nibble(4),
+                        format!("{}[idx] = {}(f32(nib) * d + m);", dst, ty_name),
+                    ]),
+                    Quant::Q5_0 => body.extend([
+                        d,
+                        nibble(6),
+                        high(2),
+                        format!("{}[idx] = {}((f32(nib5) - 16.0) * d);", dst, ty_name),
+                    ]),
+                    Quant::Q5_1 => body.extend([
+                        d,
+                        m,
+                        nibble(8),
+                        high(4),
+                        format!("{}[idx] = {}(f32(nib5) * d + m);", dst, ty_name),
+                    ]),
+                    Quant::Q8_0 => body.extend([
+                        d,
+                        format!("let q = {};", byte(src, "base + 2u + j")),
+                        // Sign-extend the byte through an arithmetic shift.
+                        format!(
+                            "{}[idx] = {}(f32(i32(q << 24u) >> 24u) * d);",
+                            dst, ty_name
+                        ),
+                    ]),
+                }
+                body
+            }
+            DimensionalType::Determinant | DimensionalType::Inverse => return None,
+        };
+
+        Some(Self {
+            root,
+            inputs: unique,
+            body,
+        })
+    }
+}
@@ -1,4 +1,10 @@
 pub(crate) mod builder;
+pub(crate) mod fusion;
 pub(crate) mod interface;
+pub(crate) mod kernel;
+pub(crate) mod update;
 pub(crate) use builder::Module;
+pub(crate) use fusion::Fusion;
 pub(crate) use interface::{BundleShader, OperationShader};
+pub(crate) use kernel::Kernel;
+pub(crate) use update::{Rule, Update};
@@ -0,0 +1,104 @@
+use crate::core::Operand;
+
+/// Parameter update rules, reading their hyperparameters from a scalar buffer laid out as
+/// `[lr, beta1 | momentum, beta2, eps, weight_decay, bias_correction1, bias_correction2]`.
+#[derive(Clone, Copy)]
+pub(crate) enum Rule {
+    Sgd { momentum: bool, nesterov: bool },
+    Adam { decoupled: bool },
+}
+
+impl Rule {
+    /// Number of state buffers kept per parameter.
+    #[inline]
+    pub fn states(&self) -> usize {
+        match self {
+            Rule::Sgd { momentum, .. } => *momentum as usize,
+            Rule::Adam { .. } => 2,
+        }
+    }
+}
+
+/// In-place update kernel of a single parameter, fusing its whole rule into one body.
+pub(crate) struct Update<'g> {
+    pub param: &'g dyn Operand,
+    pub grad: &'g dyn Operand,
+    pub states: Vec<&'g dyn Operand>,
+    /// Slot of the hyperparameters, bound right after the states.
+    pub slot: u32,
+    pub scalars: String,
+    pub body: Vec<String>,
+}
+
+impl<'g> Update<'g> {
+    pub fn new(
+        rule: Rule,
+        param: &'g dyn Operand,
+        grad: &'g dyn Operand,
+        states: Vec<&'g dyn Operand>,
+    ) -> Self {
+        let slot = 2 + states.len() as u32;
+        let scalars = format!("hp_{}", slot);
+        let (p, g) = (param.alias(0), grad.alias(1));
+        let hp = |index: usize| format!("{}[{}]", scalars, index);
+
+        let mut body = vec![format!("var g = {}[idx];", g)];
+        match rule {
+            Rule::Sgd { momentum, nesterov } => {
+                body.push(format!("g = g + {} * {}[idx];", hp(4), p));
+                if momentum {
+                    let v = states[0].alias(2);
+                    body.push(format!("{v}[idx] = {} * {v}[idx] + g;", hp(1), v = v));
+                    if nesterov {
+                        body.push(format!("g = g + {} * {}[idx];", hp(1), v));
+                    } else {
+                        body.push(format!("g = {}[idx];", v));
+                    }
+                }
+                body.push(format!("{p}[idx] = {p}[idx] - {} * g;", hp(0), p = p));
+            }
+            Rule::Adam { decoupled } => {
+                let (m, v) = (states[0].alias(2), states[1].alias(3));
+                if decoupled {
+                    body.push(format!(
+                        "{p}[idx] = {p}[idx] * (1.0 - {} * {});",
+                        hp(0),
+                        hp(4),
+                        p = p
+                    ));
+                } else {
+                    body.push(format!("g = g + {} * {}[idx];", hp(4), p));
+                }
+                body.push(format!(
+                    "{m}[idx] = {b1} * {m}[idx] + (1.0 - {b1}) * g;",
+                    m = m,
+                    b1 = hp(1)
+                ));
+                body.push(format!(
+                    "{v}[idx] = {b2} * {v}[idx] + (1.0 - {b2}) * g * g;",
+                    v = v,
+                    b2 = hp(2)
+                ));
+                body.push(format!(
+                    "{p}[idx] = {p}[idx] - {} * ({}[idx] / {}) / (sqrt({}[idx] / {}) + {});",
+                    hp(0),
+                    m,
+                    hp(5),
+                    v,
+                    hp(6),
+                    hp(3),
+                    p = p
+                ));
+            }
+        }
+
+        Self {
+            param,
+            grad,
+            states,
+            slot,
+            scalars,
+            body,
+        }
+    }
+}
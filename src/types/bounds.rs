@@ -1,23 +1,40 @@
-use bytemuck::NoUninit;
-use std::marker::PhantomData;
+use bytemuck::{AnyBitPattern, NoUninit};
+use std::{marker::PhantomData, mem};
 
-use super::{bf16::bf16, f16::f16, f8::f8};
+use crate::api::io::Dtype;
+
+use super::{bf16::bf16, f16::f16, f8::f8, i4::i4};
 
 pub(crate) mod _sealed {
     pub trait Sealed {}
 }
 
 /// Valid types for models and shaders to operate on.
-pub trait Component: _sealed::Sealed + NoUninit {}
-/// Valid types for shaders to operate on.
+pub trait Component: _sealed::Sealed + AnyBitPattern + NoUninit + Send + Sync {}
+/// Valid types for shaders to operate on; plain bits, so they can be shipped to the device as
+/// they are.
 pub trait Abstract: _sealed::Sealed + NoUninit {}
 
-/// Valid types to pack into a `Bundle`; albeit the same for now, f8, f16 and bf16 would go in here.
-pub struct Packet<T>(PhantomData<T>)
-where
-    T: Abstract + Component;
+/// Storage-only integers that weights get quantized to.
+pub trait Integer: Abstract {
+    const BITS: u32;
+}
+
+/// Valid types to pack into a `Bundle`: components as they are, abstract types packed into the
+/// words shaders read them as.
+pub struct Packet<T>(PhantomData<T>);
 
-pub trait SupportedPacket: _sealed::Sealed {}
+pub trait SupportedPacket: _sealed::Sealed {
+    /// Type buffers of these packets are declared with in shaders.
+    const SHADER: &'static str;
+    /// Elements held by every value of `SHADER`.
+    const LANES: u32;
+    /// How files describe the elements, if they have a name for them.
+    const DTYPE: Option<Dtype>;
+    /// Whether `SHADER` is a type shaders can declare at all; WGSL has no 64-bit scalars, so
+    /// such packets only live on the host.
+    const SHADABLE: bool;
+}
 
 macro_rules! impl_component {
     ($($ty:ident)*) => {$(
@@ -34,8 +51,31 @@ macro_rules! impl_abstract {
 }
 
 macro_rules! impl_packet {
-    ($($ty:ident)*) => {$(
-        impl SupportedPacket for Packet<$ty> {}
+    ($($ty:ident: $dtype:ident)*) => {$(
+        impl SupportedPacket for Packet<$ty> {
+            const SHADER: &'static str = stringify!($ty);
+            const LANES: u32 = 1;
+            const DTYPE: Option<Dtype> = Some(Dtype::$dtype);
+            const SHADABLE: bool = true;
+        }
+        impl _sealed::Sealed for Packet<$ty> {}
+    )*};
+    (wide $($ty:ident: $dtype:ident)*) => {$(
+        impl SupportedPacket for Packet<$ty> {
+            const SHADER: &'static str = stringify!($ty);
+            const LANES: u32 = 1;
+            const DTYPE: Option<Dtype> = Some(Dtype::$dtype);
+            const SHADABLE: bool = false;
+        }
+        impl _sealed::Sealed for Packet<$ty> {}
+    )*};
+    ($($ty:ident: $dtype:ident)* => $word:ident) => {$(
+        impl SupportedPacket for Packet<$ty> {
+            const SHADER: &'static str = stringify!($word);
+            const LANES: u32 = (mem::size_of::<$word>() / mem::size_of::<$ty>()) as u32;
+            const DTYPE: Option<Dtype> = Some(Dtype::$dtype);
+            const SHADABLE: bool = true;
+        }
         impl _sealed::Sealed for Packet<$ty> {}
     )*}
 }
@@ -48,11 +88,36 @@ impl_component! {
 
 impl_abstract! {
     f8 f16 bf16
+    i8 i4
+}
+
+impl Integer for i8 {
+    const BITS: u32 = 8;
+}
+
+impl Integer for i4 {
+    const BITS: u32 = 4;
 }
 
 impl_packet! {
-    u16 u32 u64
-    i16 i32 i64
-    f32 f64
-    f8 f16 bf16
+    u32: U32 i32: I32 f32: F32
+}
+
+impl_packet! {
+    u16: U16 i16: I16
+    f8: F8E4M3 f16: F16 bf16: BF16
+    i8: I8 => u32
+}
+
+impl_packet! {
+    wide u64: U64 i64: I64 f64: F64
+}
+
+// Half a byte, which `size_of` can't tell.
+impl SupportedPacket for Packet<i4> {
+    const SHADER: &'static str = "u32";
+    const LANES: u32 = 8;
+    const DTYPE: Option<Dtype> = None;
+    const SHADABLE: bool = true;
 }
+impl _sealed::Sealed for Packet<i4> {}
@@ -1,2 +1,6 @@
 #[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(transparent)]
 pub struct f16(u16);
+
+unsafe impl bytemuck::NoUninit for f16 {}
@@ -0,0 +1,8 @@
+/// Signed 4-bit integer, from -8 to 7, held in the low half of a byte; packets pack two per
+/// byte, low nibble first.
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(transparent)]
+pub struct i4(u8);
+
+unsafe impl bytemuck::NoUninit for i4 {}
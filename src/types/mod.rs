@@ -2,5 +2,6 @@ pub mod bf16;
 pub mod bounds;
 pub mod f16;
 pub mod f8;
+pub mod i4;
 
-pub use bounds::{Abstract, Component, Packet, SupportedPacket};
+pub use bounds::{Component, Integer, Packet, SupportedPacket};
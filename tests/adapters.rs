@@ -0,0 +1,48 @@
+use zelkova::{Error, Instance, InstanceOpts};
+
+#[test]
+fn adapters_are_listed_in_a_stable_order() {
+    let first: Vec<_> = zelkova::adapters()
+        .into_iter()
+        .map(|info| (info.name, info.backend))
+        .collect();
+    let second: Vec<_> = zelkova::adapters()
+        .into_iter()
+        .map(|info| (info.name, info.backend))
+        .collect();
+
+    assert!(!first.is_empty());
+    assert_eq!(first, second);
+}
+
+#[test]
+fn instances_run_on_the_adapter_asked_for() {
+    for (index, listed) in zelkova::adapters().into_iter().enumerate() {
+        let instance = Instance::on_adapter(index, InstanceOpts::default()).unwrap();
+        let info = instance.info();
+
+        assert_eq!(info.name, listed.name);
+        assert_eq!(info.backend, listed.backend);
+        assert_eq!(info.device_type, listed.device_type);
+        assert_eq!(info.features, listed.features);
+    }
+}
+
+#[test]
+fn missing_adapter_is_an_error() {
+    let past = zelkova::adapters().len();
+    assert!(matches!(
+        Instance::on_adapter(past, InstanceOpts::default()),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn limits_are_reported() {
+    let info = zelkova::init().info();
+
+    assert!(info.limits.max_storage_buffer_binding_size > 0);
+    assert!(info.limits.max_compute_workgroups_per_dimension > 0);
+    // Not exposed by the wgpu release in use.
+    assert!(!info.features.subgroups);
+}
@@ -0,0 +1,41 @@
+use zelkova::{Error, Tensor, TensorOrder};
+
+#[test]
+fn tensors_know_their_instance() {
+    let instance = zelkova::init();
+
+    let bound: Tensor<f32, f32> = instance.tensor(vec![1.0], TensorOrder::new(vec![1]));
+    assert!(bound.instance() == Some(instance.clone()));
+    // Results live wherever their operands do.
+    assert!((&bound + &bound).instance() == Some(instance.clone()));
+
+    let loose: Tensor<f32, f32> = Tensor::from_vec(vec![1.0], TensorOrder::new(vec![1]));
+    assert!(loose.instance().is_none());
+    instance.resolve(&loose).unwrap();
+    assert!(loose.instance() == Some(instance));
+}
+
+#[test]
+fn mixing_instances_is_an_error() {
+    let (first, second) = (zelkova::init(), zelkova::init());
+    let a: Tensor<f32, f32> = first.tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]));
+    let b: Tensor<f32, f32> = second.tensor(vec![3.0, 4.0], TensorOrder::new(vec![2]));
+
+    assert!(matches!(first.resolve(&(&a + &b)), Err(Error::Mismatch)));
+    assert!(matches!(second.resolve(&a), Err(Error::Mismatch)));
+}
+
+#[test]
+fn tensors_move_between_instances() {
+    let (first, second) = (zelkova::init(), zelkova::init());
+    let a: Tensor<f32, f32> = first.tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]));
+    let b: Tensor<f32, f32> = second.tensor(vec![3.0, 4.0], TensorOrder::new(vec![2]));
+
+    let moved = (&a + &a).to(&second).unwrap();
+    assert!(moved.instance() == Some(second.clone()));
+    assert_eq!((&moved + &b).to_vec(&second).unwrap(), vec![5.0, 8.0]);
+
+    // Unbound tensors are simply claimed.
+    let loose: Tensor<f32, f32> = Tensor::from_vec(vec![1.0], TensorOrder::new(vec![1]));
+    assert!(loose.to(&first).unwrap().instance() == Some(first));
+}
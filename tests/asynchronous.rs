@@ -0,0 +1,116 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, ThreadId},
+};
+
+use zelkova::{Instance, Tensor, TensorOrder};
+
+#[test]
+fn async_resolve_and_readback() {
+    pollster::block_on(async {
+        let instance = Instance::init_async().await.unwrap();
+        let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0, 3.0], TensorOrder::new(vec![3]));
+        let b = &a * &a;
+
+        instance.resolve_async(&b).await.unwrap();
+        assert_eq!(
+            b.to_vec_async(&instance).await.unwrap(),
+            vec![1.0, 4.0, 9.0]
+        );
+    });
+}
+
+#[test]
+fn readback_without_resolving_first() {
+    let instance = pollster::block_on(zelkova::init_async());
+    let a: Tensor<i32, i32> = instance.tensor(vec![5, 6], TensorOrder::new(vec![2]));
+    let b = &a - &a;
+
+    let values = pollster::block_on(b.to_vec_async(&instance)).unwrap();
+    assert_eq!(values, vec![0, 0]);
+}
+
+#[test]
+fn several_pending_readbacks() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 32], TensorOrder::new(vec![32]));
+    let (b, c) = (&a + &a, &a - &a);
+
+    let (first, second) = (b.to_vec_async(&instance), c.to_vec_async(&instance));
+    pollster::block_on(async {
+        assert_eq!(first.await.unwrap(), vec![2.0; 32]);
+        assert_eq!(second.await.unwrap(), vec![0.0; 32]);
+    });
+}
+
+/// Waker counting how often it was woken, and how often from the thread polling it.
+#[derive(Default)]
+struct Counter {
+    woken: AtomicUsize,
+    inline: AtomicUsize,
+    poller: Mutex<Option<ThreadId>>,
+}
+
+impl Wake for Counter {
+    fn wake(self: Arc<Self>) {
+        if *self.poller.lock().unwrap() == Some(thread::current().id()) {
+            self.inline.fetch_add(1, Ordering::SeqCst);
+        }
+        self.woken.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn pending_futures_sleep_until_woken() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> =
+        instance.tensor(vec![0.5; 256 * 256], TensorOrder::new(vec![256, 256]));
+    let b = a.matmul(&a);
+
+    let counter = Arc::new(Counter::default());
+    *counter.poller.lock().unwrap() = Some(thread::current().id());
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(b.to_vec_async(&instance));
+
+    // Polled again only once woken, as an executor would.
+    let values = loop {
+        if let Poll::Ready(values) = future.as_mut().poll(&mut cx) {
+            break values.unwrap();
+        }
+        while counter.woken.swap(0, Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+    };
+
+    assert_eq!(values[0], 0.5 * 0.5 * 256.0);
+    // Waking itself from within `poll` would have the executor spin on it.
+    assert_eq!(counter.inline.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn large_uploads_stream_without_blocking() {
+    fn send<F: Future + Send>(future: F) -> F {
+        future
+    }
+
+    let instance = zelkova::init();
+    // Spans a few staging chunks, the last one partially.
+    let len = 10_000_003u32;
+    let a: Tensor<f32, f32> = instance.tensor(
+        (0..len).map(|i| i as f32).collect(),
+        TensorOrder::new(vec![len]),
+    );
+    let b = &a + &a;
+
+    pollster::block_on(send(instance.resolve_async(&b))).unwrap();
+    let values = pollster::block_on(send(b.to_vec_async(&instance))).unwrap();
+    assert_eq!(values[9973], 2.0 * 9973.0);
+    assert_eq!(*values.last().unwrap(), 2.0 * (len - 1) as f32);
+}
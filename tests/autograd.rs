@@ -0,0 +1,255 @@
+mod common;
+
+use zelkova::{
+    loss::{self, Reduction},
+    nn::Param,
+    Instance, TensorOrder,
+};
+
+use common::{assert_close, param, tensor};
+
+/// Step the finite differences are taken with.
+const EPS: f32 = 1e-2;
+
+/// Compare the gradient `backward` leaves on `x` against central differences, through a
+/// weighted sum of `f(x)` so every output element gets a distinct gradient.
+fn check_gradient(
+    instance: &Instance,
+    values: &[f32],
+    dims: Vec<u32>,
+    f: impl Fn(&Param) -> Param,
+) {
+    let weights = |len: usize| -> Vec<f32> {
+        (0..len)
+            .map(|i| ((i * 7 % 11) as f32 - 5.0) / 5.0)
+            .collect()
+    };
+    let loss = |values: Vec<f32>| -> f32 {
+        let y = f(&tensor(instance, &values, dims.clone()))
+            .to_vec(instance)
+            .unwrap();
+        y.iter().zip(weights(y.len())).map(|(y, w)| y * w).sum()
+    };
+
+    let x = param(instance, values, dims.clone());
+    let y = f(&x);
+    let w = tensor(instance, &weights(y.order.size() as usize), y.order.pull());
+    (&y * &w).backward().unwrap();
+    let analytic = x.grad().unwrap().to_vec(instance).unwrap();
+
+    let numeric: Vec<f32> = (0..values.len())
+        .map(|i| {
+            let nudged = |delta: f32| {
+                let mut values = values.to_vec();
+                values[i] += delta;
+                loss(values)
+            };
+            (nudged(EPS) - nudged(-EPS)) / (2.0 * EPS)
+        })
+        .collect();
+
+    assert_close(&analytic, &numeric, 1e-2);
+}
+
+#[test]
+fn mul_backward() {
+    let instance = zelkova::init();
+    let a = param(&instance, &[1.0, 2.0, 3.0], vec![3]);
+    let b = param(&instance, &[4.0, 5.0, 6.0], vec![3]);
+
+    (&a * &b).backward().unwrap();
+
+    assert_close(
+        &a.grad().unwrap().to_vec(&instance).unwrap(),
+        &[4.0, 5.0, 6.0],
+        1e-4,
+    );
+    assert_close(
+        &b.grad().unwrap().to_vec(&instance).unwrap(),
+        &[1.0, 2.0, 3.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn add_backward() {
+    let instance = zelkova::init();
+    let a = param(&instance, &[1.0, 2.0, 3.0], vec![3]);
+    let b = param(&instance, &[4.0, 5.0, 6.0], vec![3]);
+
+    (&a + &b).backward().unwrap();
+
+    assert_close(
+        &a.grad().unwrap().to_vec(&instance).unwrap(),
+        &[1.0, 1.0, 1.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn sub_backward() {
+    let instance = zelkova::init();
+    let a = param(&instance, &[1.0, 2.0, 3.0], vec![3]);
+    let b = param(&instance, &[4.0, 5.0, 6.0], vec![3]);
+
+    (&a - &b).backward().unwrap();
+
+    assert_close(
+        &a.grad().unwrap().to_vec(&instance).unwrap(),
+        &[1.0, 1.0, 1.0],
+        1e-4,
+    );
+    assert_close(
+        &b.grad().unwrap().to_vec(&instance).unwrap(),
+        &[-1.0, -1.0, -1.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn div_backward() {
+    let instance = zelkova::init();
+    let a = param(&instance, &[1.0, 2.0, 3.0], vec![3]);
+    let b = param(&instance, &[2.0, 4.0, 5.0], vec![3]);
+
+    (&a / &b).backward().unwrap();
+
+    assert_close(
+        &a.grad().unwrap().to_vec(&instance).unwrap(),
+        &[0.5, 0.25, 0.2],
+        1e-4,
+    );
+    assert_close(
+        &b.grad().unwrap().to_vec(&instance).unwrap(),
+        &[-0.25, -0.125, -0.12],
+        1e-4,
+    );
+}
+
+#[test]
+fn shared_operand_backward() {
+    let instance = zelkova::init();
+    let x = param(&instance, &[1.0, -2.0, 3.0], vec![3]);
+
+    // Both sides read `x`, so its gradient is accumulated twice.
+    (&x * &x).backward().unwrap();
+
+    assert_close(
+        &x.grad().unwrap().to_vec(&instance).unwrap(),
+        &[2.0, -4.0, 6.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn cross_entropy_backward() {
+    let instance = zelkova::init();
+    let logits = param(&instance, &[1.0, 2.0, 3.0, 1.0, 1.0, 1.0], vec![2, 3]);
+    let target = tensor(&instance, &[2.0, 0.0], vec![2]);
+
+    loss::cross_entropy(&logits, &target, Reduction::Mean)
+        .backward()
+        .unwrap();
+
+    // `(softmax(x) - onehot(t)) / n`
+    assert_close(
+        &logits.grad().unwrap().to_vec(&instance).unwrap(),
+        &[0.0450, 0.1224, -0.1674, -0.3333, 0.1667, 0.1667],
+        1e-4,
+    );
+}
+
+#[test]
+fn gather_backward_on_deeper_tables() {
+    let instance = zelkova::init();
+    let values: Vec<f32> = (0..12).map(|x| x as f32).collect();
+    let table = param(&instance, &values, vec![3, 2, 2]);
+    let indices = tensor(&instance, &[2.0, 0.0, 2.0], vec![3]);
+
+    // Every picked slice is whole, and picking one twice adds up.
+    table.gather(&indices).backward().unwrap();
+
+    assert_close(
+        &table.grad().unwrap().to_vec(&instance).unwrap(),
+        &[1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 2.0, 2.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn deep_graphs_record_without_recursing() {
+    let x = Param::from_vec(vec![1.0], TensorOrder::new(vec![1])).requires_grad();
+    let mut y = x.clone();
+    for _ in 0..20_000 {
+        y = &y + &x;
+    }
+
+    // Small enough a stack that walking the chain recursively would run out of it.
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(256 << 10)
+            .spawn_scoped(scope, || y.backward().unwrap())
+            .unwrap()
+            .join()
+            .unwrap()
+    });
+    assert!(x.grad().is_some());
+}
+
+#[test]
+fn matmul_gradients_match_finite_differences() {
+    let instance = zelkova::init();
+    let a = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+    let b = [1.0, 2.0, -0.5, 0.5, 1.5, -1.0];
+
+    check_gradient(&instance, &a, vec![2, 3], |a| {
+        a.matmul(&tensor(&instance, &b, vec![3, 2]))
+    });
+    check_gradient(&instance, &b, vec![3, 2], |b| {
+        tensor(&instance, &a, vec![2, 3]).matmul(b)
+    });
+
+    // Two products of `[1, 3]` by `[3, 2]` side by side.
+    check_gradient(&instance, &a, vec![2, 1, 3], |a| {
+        a.matmul(&tensor(&instance, &[b, b].concat(), vec![2, 3, 2]))
+    });
+    check_gradient(&instance, &[b, b].concat(), vec![2, 3, 2], |b| {
+        tensor(&instance, &a, vec![2, 1, 3]).matmul(b)
+    });
+}
+
+#[test]
+fn reduction_gradients_match_finite_differences() {
+    let instance = zelkova::init();
+    // Distinct values, so the maximum of every row and column is clear of a tie.
+    let x = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+
+    for axis in 0..2 {
+        check_gradient(&instance, &x, vec![2, 3], |x| x.sum(axis));
+        check_gradient(&instance, &x, vec![2, 3], |x| x.max(axis));
+    }
+    // Mean of every row.
+    check_gradient(&instance, &x, vec![2, 3], |x| x.sum(1).scale(1.0 / 3.0));
+}
+
+#[test]
+fn transpose_gradients_match_finite_differences() {
+    let instance = zelkova::init();
+    let x = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+
+    check_gradient(&instance, &x, vec![2, 3], Param::transpose);
+    check_gradient(&instance, &[x, x].concat(), vec![2, 2, 3], Param::transpose);
+}
+
+#[test]
+fn activation_gradients_match_finite_differences() {
+    let instance = zelkova::init();
+    // Away from zero, where `relu` has a kink.
+    let x = [0.5, -1.0, 2.0, 1.5, 0.25, -0.75];
+
+    check_gradient(&instance, &x, vec![6], Param::relu);
+    check_gradient(&instance, &x, vec![6], Param::sigmoid);
+    for axis in 0..2 {
+        check_gradient(&instance, &x, vec![2, 3], |x| x.softmax(axis));
+    }
+}
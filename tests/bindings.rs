@@ -0,0 +1,30 @@
+use zelkova::{Tensor, TensorOrder};
+
+#[test]
+fn bindings_restart_for_every_kernel() {
+    let instance = zelkova::init();
+
+    // A process-wide counter would run past the per-group limit long before this ends.
+    for step in 0..1200 {
+        let a: Tensor<i32, i32> = instance.tensor(vec![step; 4], TensorOrder::new(vec![4]));
+        let b: Tensor<i32, i32> = instance.tensor(vec![1; 4], TensorOrder::new(vec![4]));
+        instance.resolve(&(&a + &b)).unwrap();
+    }
+}
+
+#[test]
+fn wide_fusion() {
+    let instance = zelkova::init();
+    let limits = instance.info().limits;
+    let count = limits.max_storage_buffers_per_shader_stage.min(12) as i32 - 1;
+
+    let inputs: Vec<Tensor<i32, i32>> = (0..count)
+        .map(|input| instance.tensor(vec![input; 8], TensorOrder::new(vec![8])))
+        .collect();
+    let total = inputs[1..]
+        .iter()
+        .fold(inputs[0].clone(), |acc, input| &acc + input);
+
+    let expected = (0..count).sum::<i32>();
+    assert_eq!(total.to_vec(&instance).unwrap(), vec![expected; 8]);
+}
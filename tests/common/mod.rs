@@ -0,0 +1,22 @@
+// Helpers shared by the integration tests; each test crate only uses some of them.
+#![allow(dead_code)]
+
+use zelkova::{nn::Param, Instance, TensorOrder};
+
+/// `values` laid out as `dims`, bound to `instance`.
+pub fn tensor(instance: &Instance, values: &[f32], dims: Vec<u32>) -> Param {
+    instance.tensor(values.to_vec(), TensorOrder::new(dims))
+}
+
+/// Same as `tensor`, tracked so `backward` leaves it a gradient.
+pub fn param(instance: &Instance, values: &[f32], dims: Vec<u32>) -> Param {
+    tensor(instance, values, dims).requires_grad()
+}
+
+/// Every element of `actual` within `tolerance` of the one `expected` at its place.
+pub fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < tolerance, "{:?} != {:?}", actual, expected);
+    }
+}
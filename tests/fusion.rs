@@ -0,0 +1,60 @@
+use zelkova::{Tensor, TensorOrder};
+
+#[test]
+fn fused_chain() {
+    let instance = zelkova::init();
+    let order = || TensorOrder::new(vec![2, 2]);
+    let a: Tensor<i32, i32> = instance.tensor(vec![1, 2, 3, 4], order());
+    let b: Tensor<i32, i32> = instance.tensor(vec![5, 6, 7, 8], order());
+    let c: Tensor<i32, i32> = instance.tensor(vec![2, 2, 3, 3], order());
+
+    let d = (&a + &b) * c - a;
+
+    assert_eq!(d.to_vec(&instance).unwrap(), vec![11, 14, 27, 32]);
+}
+
+#[test]
+fn shared_subexpressions() {
+    let instance = zelkova::init();
+    let values = vec![0.5f32, 1.0, 1.00001, -0.99999];
+    let mut x: Tensor<f32, f32> = instance.tensor(values.clone(), TensorOrder::new(vec![4]));
+
+    // Inlining every use would double the expression each step.
+    for _ in 0..20 {
+        x = &x * &x;
+    }
+
+    let expected: Vec<f32> = values
+        .iter()
+        .map(|v| (0..20).fold(*v, |acc, _| acc * acc))
+        .collect();
+    let actual = x.to_vec(&instance).unwrap();
+    for (a, e) in actual.iter().zip(&expected) {
+        assert!(
+            (a - e).abs() <= e.abs() * 1e-3,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn long_chains_fuse_without_recursing() {
+    let instance = zelkova::init();
+    let mut x: Tensor<f32, f32> = instance.tensor(vec![1.0; 4], TensorOrder::new(vec![4]));
+    for _ in 0..2000 {
+        x = &x * &x;
+    }
+
+    // Small enough a stack that walking the chain recursively would run out of it.
+    let values = std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(256 << 10)
+            .spawn_scoped(scope, || x.to_vec(&instance).unwrap())
+            .unwrap()
+            .join()
+            .unwrap()
+    });
+    assert_eq!(values, vec![1.0; 4]);
+}
@@ -0,0 +1,181 @@
+use std::{fs, path::PathBuf};
+
+use zelkova::{
+    io::{
+        gguf::{GgmlType, Value},
+        Gguf,
+    },
+    Error,
+};
+
+/// f16 bits of 0.5.
+const HALF: [u8; 2] = [0x00, 0x38];
+
+fn string(file: &mut Vec<u8>, value: &str) {
+    file.extend((value.len() as u64).to_le_bytes());
+    file.extend(value.as_bytes());
+}
+
+/// Version 3 file holding `metadata`, already encoded as key, type and value, then `tensors`
+/// as name, dimensions innermost first, ggml type and data.
+fn build(name: &str, metadata: &[Vec<u8>], tensors: &[(&str, Vec<u64>, u32, Vec<u8>)]) -> PathBuf {
+    let mut file = b"GGUF".to_vec();
+    file.extend(3u32.to_le_bytes());
+    file.extend((tensors.len() as u64).to_le_bytes());
+    file.extend((metadata.len() as u64).to_le_bytes());
+    for entry in metadata {
+        file.extend(entry);
+    }
+
+    let mut data = Vec::new();
+    for (name, dims, ty, contents) in tensors {
+        string(&mut file, name);
+        file.extend((dims.len() as u32).to_le_bytes());
+        for dim in dims {
+            file.extend(dim.to_le_bytes());
+        }
+        file.extend(ty.to_le_bytes());
+        file.extend((data.len() as u64).to_le_bytes());
+
+        data.extend(contents);
+        data.resize(data.len().next_multiple_of(32), 0);
+    }
+
+    file.resize(file.len().next_multiple_of(32), 0);
+    file.extend(data);
+
+    let path = std::env::temp_dir().join(format!("zelkova-{}.gguf", name));
+    fs::write(&path, file).unwrap();
+    path
+}
+
+fn entry(key: &str, ty: u32, value: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    string(&mut entry, key);
+    entry.extend(ty.to_le_bytes());
+    entry.extend(value);
+    entry
+}
+
+#[test]
+fn metadata_is_read() {
+    let mut names = 8u32.to_le_bytes().to_vec();
+    names.extend(2u64.to_le_bytes());
+    string(&mut names, "a");
+    string(&mut names, "b");
+
+    let mut name = Vec::new();
+    string(&mut name, "tiny");
+
+    let path = build(
+        "metadata",
+        &[
+            entry("general.name", 8, &name),
+            entry("tiny.layers", 4, &3u32.to_le_bytes()),
+            entry("tokenizer.tokens", 9, &names),
+        ],
+        &[],
+    );
+
+    let file = Gguf::open(&path).unwrap();
+    assert_eq!(file.version, 3);
+    assert_eq!(
+        file.get("general.name").and_then(Value::as_str),
+        Some("tiny")
+    );
+    assert_eq!(file.get("tiny.layers").and_then(Value::as_u64), Some(3));
+    assert_eq!(
+        file.get("tokenizer.tokens"),
+        Some(&Value::Array(vec![
+            Value::String("a".into()),
+            Value::String("b".into())
+        ]))
+    );
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn deeply_nested_arrays_are_rejected() {
+    // Arrays of a single array each, far deeper than any real file.
+    let mut value = Vec::new();
+    for _ in 0..1000 {
+        value.extend(9u32.to_le_bytes());
+        value.extend(1u64.to_le_bytes());
+    }
+    value.extend(4u32.to_le_bytes());
+    value.extend(0u64.to_le_bytes());
+
+    let path = build("nested", &[entry("deep", 9, &value[4..])], &[]);
+    assert!(matches!(Gguf::open(&path), Err(Error::Format(_))));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn tensors_are_unpacked() {
+    let instance = zelkova::init();
+
+    let plain: Vec<u8> = [1.0f32, 2.0, 3.0, 4.0]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect();
+
+    // A single Q8_0 block: scale, then 32 signed bytes.
+    let mut q8 = HALF.to_vec();
+    q8.extend((0..32).map(|i| (i as i8 - 16) as u8));
+
+    // A single Q4_0 block: scale, then nibbles offset by 8, the low ones first.
+    let mut q4 = HALF.to_vec();
+    q4.extend((0..16).map(|i: u8| i | (15 - i) << 4));
+
+    let path = build(
+        "tensors",
+        &[],
+        &[
+            ("plain", vec![2, 2], 0, plain),
+            ("q8", vec![32], 8, q8),
+            ("q4", vec![16, 2], 2, q4),
+        ],
+    );
+    let mut file = Gguf::open(&path).unwrap();
+
+    let info = file.info("q4").unwrap();
+    assert_eq!(info.shape, &[2, 16]);
+    assert_eq!(info.ty, GgmlType::Q4_0);
+
+    let plain = file.tensor("plain").unwrap();
+    assert_eq!(plain.to_vec(&instance).unwrap(), &[1.0, 2.0, 3.0, 4.0]);
+
+    let q8 = file.tensor("q8").unwrap().to_vec(&instance).unwrap();
+    let expected: Vec<f32> = (0..32).map(|i| (i - 16) as f32 * 0.5).collect();
+    assert_eq!(q8, expected);
+
+    let q4 = file.tensor("q4").unwrap().to_vec(&instance).unwrap();
+    let expected: Vec<f32> = (0..16)
+        .map(|i| (i - 8) as f32 * 0.5)
+        .chain((0..16).map(|i| (7 - i) as f32 * 0.5))
+        .collect();
+    assert_eq!(q4, expected);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn tensors_past_the_end_are_rejected() {
+    let path = build(
+        "truncated",
+        &[],
+        &[
+            ("short", vec![1024], 0, vec![0; 16]),
+            ("overflow", vec![u32::MAX as u64; 3], 0, vec![0; 16]),
+        ],
+    );
+    let mut file = Gguf::open(&path).unwrap();
+
+    for name in ["short", "overflow"] {
+        assert!(matches!(file.tensor(name), Err(Error::Format(_))));
+    }
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,84 @@
+use zelkova::{nn::Param, Error, InstanceGroup, TensorOrder};
+
+fn pair() -> InstanceGroup {
+    InstanceGroup::from_instances(vec![zelkova::init(), zelkova::init()]).unwrap()
+}
+
+#[test]
+fn group_spans_every_adapter() {
+    // Needs distinct adapters, e.g. a GPU next to a software rasterizer.
+    if zelkova::adapters().len() < 2 {
+        return;
+    }
+
+    let group = InstanceGroup::init().unwrap();
+    assert_eq!(group.len(), zelkova::adapters().len());
+}
+
+#[test]
+fn empty_group_is_an_error() {
+    assert!(matches!(
+        InstanceGroup::from_instances(Vec::new()),
+        Err(Error::Unsupported(_))
+    ));
+}
+
+#[test]
+fn shards_split_the_leading_axis() {
+    let group = pair();
+    let rows: Vec<f32> = (0..10).map(|i| i as f32).collect();
+
+    let shards = group
+        .shard::<f32, f32>(&rows, TensorOrder::new(vec![5, 2]))
+        .unwrap();
+    let members = group.instances();
+
+    // The odd row goes to the first member.
+    assert_eq!(
+        shards[0].to_vec(&members[0]).unwrap(),
+        &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]
+    );
+    assert_eq!(
+        shards[1].to_vec(&members[1]).unwrap(),
+        &[6.0, 7.0, 8.0, 9.0]
+    );
+
+    assert!(group
+        .shard::<f32, f32>(&[1.0], TensorOrder::new(vec![1]))
+        .is_err());
+}
+
+#[test]
+fn shards_must_cover_the_batch_exactly() {
+    let group = pair();
+    let rows: Vec<f32> = (0..10).map(|i| i as f32).collect();
+
+    for dims in [vec![4, 2], vec![6, 2]] {
+        assert!(matches!(
+            group.shard::<f32, f32>(&rows, TensorOrder::new(dims)),
+            Err(Error::Mismatch)
+        ));
+    }
+}
+
+#[test]
+fn all_reduce_averages_gradients() {
+    let group = pair();
+    let members = group.instances();
+    let weights: Vec<Param> = group
+        .replicate(&[1.0, 2.0], TensorOrder::new(vec![2]))
+        .into_iter()
+        .map(|w: Param| w.requires_grad())
+        .collect();
+
+    // d(k w)/dw = k, so the members see 1 and 3 and end up with 2.
+    for (w, k) in weights.iter().zip([1.0, 3.0]) {
+        w.scale(k).backward().unwrap();
+    }
+    group.all_reduce(&weights).unwrap();
+
+    for (w, member) in weights.iter().zip(members) {
+        let grad = w.grad().unwrap().to_vec(member).unwrap();
+        assert_eq!(grad, &[2.0, 2.0]);
+    }
+}
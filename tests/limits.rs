@@ -0,0 +1,31 @@
+use zelkova::{Error, Tensor, TensorOrder};
+
+#[test]
+fn dispatch_spills_over_a_grid() {
+    let instance = zelkova::init();
+    let limits = instance.info().limits;
+    // More invocations than a single row of workgroups can hold at the largest workgroup size.
+    let len = limits.max_compute_workgroups_per_dimension * 256 + 1000;
+    if len as u64 * 4 > limits.max_storage_buffer_binding_size as u64 {
+        return;
+    }
+
+    let full: Tensor<f32, f32> = Tensor::full(TensorOrder::new(vec![len]), 1.5);
+    let sum = (&full + &full).to_vec(&instance).unwrap();
+
+    assert_eq!(sum.len(), len as usize);
+    assert!(sum.iter().all(|value| *value == 3.0));
+}
+
+#[test]
+fn oversized_tensor_is_reported() {
+    let instance = zelkova::init();
+    let limits = instance.info().limits;
+    let len = (limits.max_storage_buffer_binding_size / 4).saturating_add(1);
+
+    let full: Tensor<f32, f32> = Tensor::full(TensorOrder::new(vec![len]), 0.0);
+    match instance.resolve(&full) {
+        Err(Error::Unsupported(reason)) => assert!(reason.contains("maximum")),
+        other => panic!("expected the tensor to be rejected, got {:?}", other),
+    }
+}
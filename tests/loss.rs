@@ -0,0 +1,78 @@
+mod common;
+
+use std::f32::consts::LN_2;
+
+use zelkova::loss::{self, Reduction};
+
+use common::{assert_close, tensor};
+
+#[test]
+fn mse() {
+    let instance = zelkova::init();
+    let input = tensor(&instance, &[1.0, 2.0, 3.0], vec![3]).requires_grad();
+    let target = tensor(&instance, &[1.0, 0.0, 5.0], vec![3]);
+
+    let mean = loss::mse(&input, &target, Reduction::Mean);
+    assert_close(&mean.to_vec(&instance).unwrap(), &[8.0 / 3.0], 1e-4);
+    let sum = loss::mse(&input, &target, Reduction::Sum);
+    assert_close(&sum.to_vec(&instance).unwrap(), &[8.0], 1e-4);
+
+    mean.backward().unwrap();
+    assert_close(
+        &input.grad().unwrap().to_vec(&instance).unwrap(),
+        &[0.0, 4.0 / 3.0, -4.0 / 3.0],
+        1e-4,
+    );
+}
+
+#[test]
+fn l1() {
+    let instance = zelkova::init();
+    let input = tensor(&instance, &[1.0, 2.0, 3.0], vec![3]);
+    let target = tensor(&instance, &[1.0, 0.0, 5.0], vec![3]);
+
+    let each = loss::l1(&input, &target, Reduction::None);
+    assert_close(&each.to_vec(&instance).unwrap(), &[0.0, 2.0, 2.0], 1e-4);
+    let sum = loss::l1(&input, &target, Reduction::Sum);
+    assert_close(&sum.to_vec(&instance).unwrap(), &[4.0], 1e-4);
+}
+
+#[test]
+fn nll() {
+    let instance = zelkova::init();
+    let input = tensor(&instance, &[-1.0, -2.0, -3.0, -4.0], vec![2, 2]);
+    let target = tensor(&instance, &[1.0, 0.0], vec![2]);
+
+    let each = loss::nll(&input, &target, Reduction::None);
+    assert_close(&each.to_vec(&instance).unwrap(), &[2.0, 3.0], 1e-4);
+    let mean = loss::nll(&input, &target, Reduction::Mean);
+    assert_close(&mean.to_vec(&instance).unwrap(), &[2.5], 1e-4);
+}
+
+#[test]
+fn cross_entropy() {
+    let instance = zelkova::init();
+    let input = tensor(&instance, &[1.0, 2.0, 3.0, 1.0, 1.0, 1.0], vec![2, 3]);
+    let target = tensor(&instance, &[2.0, 0.0], vec![2]);
+
+    let each = loss::cross_entropy(&input, &target, Reduction::None);
+    assert_close(&each.to_vec(&instance).unwrap(), &[0.40761, 1.09861], 1e-4);
+    let mean = loss::cross_entropy(&input, &target, Reduction::Mean);
+    assert_close(&mean.to_vec(&instance).unwrap(), &[0.75311], 1e-4);
+}
+
+#[test]
+fn binary_cross_entropy_with_logits() {
+    let instance = zelkova::init();
+    let input = tensor(&instance, &[0.0, 2.0, -1.0], vec![3]);
+    let target = tensor(&instance, &[0.5, 1.0, 0.0], vec![3]);
+
+    let each = loss::binary_cross_entropy_with_logits(&input, &target, Reduction::None);
+    assert_close(
+        &each.to_vec(&instance).unwrap(),
+        &[LN_2, 0.126928, 0.313262],
+        1e-4,
+    );
+    let mean = loss::binary_cross_entropy_with_logits(&input, &target, Reduction::Mean);
+    assert_close(&mean.to_vec(&instance).unwrap(), &[0.377779], 1e-4);
+}
@@ -0,0 +1,91 @@
+use zelkova::{Error, Instance, InstanceOpts, Tensor, TensorOrder, Usage};
+
+#[test]
+fn usage_is_broken_down() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 1024], TensorOrder::new(vec![1024]));
+    let b: Tensor<i32, i32> = instance.tensor(vec![1; 1024], TensorOrder::new(vec![1024]));
+    let c = &a + &a;
+    instance.resolve(&b).unwrap();
+    instance.resolve(&c).unwrap();
+
+    let stats = instance.memory_stats();
+    assert_eq!(stats.budget, None);
+    assert!(stats.by_dtype["f32"] >= 2 * 4096);
+    assert!(stats.by_dtype["i32"] >= 4096);
+    assert!(stats.by_usage[&Usage::Input] >= 2 * 4096);
+    assert!(stats.by_usage[&Usage::Intermediate] >= 4096);
+    assert!(stats.total >= stats.by_usage.values().sum::<u64>());
+
+    // The buffer a readback goes through is kept for the next one, and counted as staging.
+    assert!(!stats.by_usage.contains_key(&Usage::Staging));
+    c.to_vec(&instance).unwrap();
+    let stats = instance.memory_stats();
+    assert!(stats.by_usage[&Usage::Staging] >= 4096);
+    assert!(stats.total >= stats.by_usage.values().sum::<u64>() + stats.cached);
+
+    instance.trim();
+    assert!(!instance
+        .memory_stats()
+        .by_usage
+        .contains_key(&Usage::Staging));
+}
+
+#[test]
+fn upload_ring_is_counted() {
+    let instance = zelkova::init();
+    // Large enough to stream through the staging ring.
+    let len = 5 << 20;
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; len], TensorOrder::new(vec![len as u32]));
+    instance.resolve(&a).unwrap();
+
+    let stats = instance.memory_stats();
+    assert!(stats.by_usage[&Usage::Staging] >= 16 << 20);
+    assert!(instance.pool_stats().live >= stats.by_usage[&Usage::Staging]);
+
+    instance.trim();
+    assert!(!instance
+        .memory_stats()
+        .by_usage
+        .contains_key(&Usage::Staging));
+}
+
+#[test]
+fn budget_is_enforced() {
+    let opts = InstanceOpts {
+        budget: Some(64 << 10),
+        evict: false,
+        ..Default::default()
+    };
+    let instance = Instance::with_opts(opts).unwrap();
+    assert_eq!(instance.memory_stats().budget, Some(64 << 10));
+
+    let small: Tensor<f32, f32> = instance.tensor(vec![1.0; 1024], TensorOrder::new(vec![1024]));
+    instance.resolve(&small).unwrap();
+
+    let large: Tensor<f32, f32> =
+        instance.tensor(vec![1.0; 1 << 16], TensorOrder::new(vec![1 << 16]));
+    match instance.resolve(&large) {
+        Err(Error::OutOfMemory { requested, budget }) => {
+            assert!(requested >= 1 << 18);
+            assert_eq!(budget, 64 << 10);
+        }
+        other => panic!("expected an out of memory error, got {:?}", other),
+    }
+
+    // Nothing was lost: the upload is still staged and the earlier tensor still readable.
+    assert_eq!(small.to_vec(&instance).unwrap(), vec![1.0; 1024]);
+}
+
+#[test]
+fn shared_chains_are_tracked_once() {
+    let instance = zelkova::init();
+    let mut x: Tensor<f32, f32> = instance.tensor(vec![1.0; 4], TensorOrder::new(vec![4]));
+    // Every step reads the last one twice, so following each read would take 2^64 steps.
+    for _ in 0..64 {
+        x = &x * &x;
+    }
+
+    assert_eq!(x.to_vec(&instance).unwrap(), vec![1.0; 4]);
+    assert!(instance.memory_stats().by_usage[&Usage::Input] >= 16);
+}
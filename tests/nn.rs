@@ -0,0 +1,133 @@
+mod common;
+
+use zelkova::{
+    io::{safetensors, Safetensors},
+    nn::{BatchNorm, Embedding, Linear, Module, Param},
+    Error, TensorOrder,
+};
+
+use common::{assert_close, tensor};
+
+#[test]
+fn linear_forward() {
+    let instance = zelkova::init();
+    let mut linear = Linear {
+        weight: tensor(&instance, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![2, 3]),
+        bias: Some(tensor(&instance, &[0.5, -0.5], vec![2])),
+    };
+    let input = tensor(&instance, &[1.0, 0.0, -1.0], vec![1, 3]);
+
+    let output = linear.forward(&input).unwrap();
+
+    assert_close(&output.to_vec(&instance).unwrap(), &[-1.5, -2.5], 1e-3);
+}
+
+#[test]
+fn embedding_forward() {
+    let instance = zelkova::init();
+    let mut embedding = Embedding {
+        weight: tensor(&instance, &[0.0, 1.0, 10.0, 11.0, 20.0, 21.0], vec![3, 2]),
+    };
+    let input = tensor(&instance, &[2.0, 0.0], vec![2]);
+
+    let output = embedding.forward(&input).unwrap();
+
+    assert_close(
+        &output.to_vec(&instance).unwrap(),
+        &[20.0, 21.0, 0.0, 1.0],
+        1e-3,
+    );
+}
+
+#[test]
+fn batch_norm_running_stats() {
+    let instance = zelkova::init();
+    let mut norm = BatchNorm::new(2, 1e-5, 0.1);
+    let input = tensor(&instance, &[1.0, 2.0, 3.0, 4.0], vec![2, 2]);
+
+    let output = norm.forward(&input).unwrap();
+    assert_close(
+        &output.to_vec(&instance).unwrap(),
+        &[-1.0, -1.0, 1.0, 1.0],
+        1e-3,
+    );
+
+    // Batch mean `[2, 3]`, unbiased variance `2` for both channels.
+    for _ in 0..2 {
+        norm.forward(&input).unwrap();
+    }
+    assert_close(
+        &norm.running_mean.to_vec(&instance).unwrap(),
+        &[0.542, 0.813],
+        1e-3,
+    );
+    assert_close(
+        &norm.running_var.to_vec(&instance).unwrap(),
+        &[1.271, 1.271],
+        1e-3,
+    );
+
+    norm.train(false);
+    let output = norm.forward(&input).unwrap();
+    let expected: Vec<f32> = [1.0f32, 2.0, 3.0, 4.0]
+        .iter()
+        .zip([0.542f32, 0.813, 0.542, 0.813])
+        .map(|(x, mean)| (x - mean) / (1.271f32 + 1e-5).sqrt())
+        .collect();
+    assert_close(&output.to_vec(&instance).unwrap(), &expected, 1e-3);
+}
+
+#[test]
+fn batch_norm_buffers_saved() {
+    let instance = zelkova::init();
+    let mut norm = BatchNorm::new(2, 1e-5, 0.5);
+    let input = tensor(&instance, &[1.0, 2.0, 3.0, 4.0], vec![2, 2]);
+    norm.forward(&input).unwrap();
+
+    let path = std::env::temp_dir().join("zelkova-batch-norm.safetensors");
+    safetensors::save_module(&path, &instance, &norm).unwrap();
+
+    let restored = BatchNorm::new(2, 1e-5, 0.5);
+    Safetensors::open(&path)
+        .unwrap()
+        .load_module(&restored)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_close(
+        &restored.running_mean.to_vec(&instance).unwrap(),
+        &[1.0, 1.5],
+        1e-3,
+    );
+    assert_close(
+        &restored.running_var.to_vec(&instance).unwrap(),
+        &[1.5, 1.5],
+        1e-3,
+    );
+}
+
+#[test]
+fn batch_norm_statistics_stay_shallow() {
+    let instance = zelkova::init();
+    let mut norm = BatchNorm::new(2, 1e-5, 0.1);
+    let input = tensor(&instance, &[1.0, 2.0, 3.0, 4.0], vec![2, 2]);
+
+    norm.forward(&input).unwrap();
+    let depth = (norm.running_mean.depth(), norm.running_var.depth());
+    for _ in 0..10 {
+        norm.forward(&input).unwrap();
+    }
+    assert_eq!(depth, (0, 0));
+    assert_eq!((norm.running_mean.depth(), norm.running_var.depth()), depth);
+}
+
+#[test]
+fn batch_norm_needs_a_bound_input_to_train() {
+    let mut norm = BatchNorm::new(2, 1e-5, 0.1);
+    let input = Param::from_vec(vec![1.0, 2.0, 3.0, 4.0], TensorOrder::new(vec![2, 2]));
+    assert!(matches!(norm.forward(&input), Err(Error::Unsupported(_))));
+
+    // Nothing has to be resolved to evaluate.
+    norm.train(false);
+    assert!(norm.forward(&input).is_ok());
+}
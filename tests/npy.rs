@@ -0,0 +1,140 @@
+use std::{fs, path::PathBuf};
+
+use zelkova::{
+    io::{npy, Npz},
+    Error, Tensor, TensorOrder,
+};
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zelkova-{}", name))
+}
+
+/// Version 1.0 `.npy` file with the given header dict and raw data.
+fn raw(name: &str, header: &str, data: &[u8]) -> PathBuf {
+    let mut header = header.to_string();
+    while !(10 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut contents = b"\x93NUMPY\x01\x00".to_vec();
+    contents.extend((header.len() as u16).to_le_bytes());
+    contents.extend(header.as_bytes());
+    contents.extend(data);
+
+    let path = scratch(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn arrays_round_trip() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> =
+        instance.tensor(vec![1.0, 2.0, 3.0, 4.0], TensorOrder::new(vec![2, 2]));
+
+    let path = scratch("round-trip.npy");
+    (&a + &a).to_npy(&instance, &path).unwrap();
+    let loaded: Tensor<f32, f32> = Tensor::from_npy(&path).unwrap();
+    assert_eq!(loaded.to_vec(&instance).unwrap(), &[2.0, 4.0, 6.0, 8.0]);
+
+    assert!(matches!(
+        Tensor::<i32, i32>::from_npy(&path),
+        Err(Error::Format(_))
+    ));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fortran_and_big_endian_arrays_are_converted() {
+    let instance = zelkova::init();
+
+    // Columns of [[1, 2, 3], [4, 5, 6]].
+    let data: Vec<u8> = [1, 4, 2, 5, 3, 6]
+        .iter()
+        .flat_map(|x: &i32| x.to_be_bytes())
+        .collect();
+    let path = raw(
+        "fortran.npy",
+        "{'descr': '>i4', 'fortran_order': True, 'shape': (2, 3), }",
+        &data,
+    );
+
+    let loaded: Tensor<i32, i32> = Tensor::from_npy(&path).unwrap();
+    assert_eq!(loaded.order.pull(), &[2, 3]);
+    assert_eq!(loaded.to_vec(&instance).unwrap(), &[1, 2, 3, 4, 5, 6]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn lengths_past_the_file_are_rejected() {
+    // A version 2.0 header claiming nearly 4 GiB.
+    let mut contents = b"\x93NUMPY\x02\x00".to_vec();
+    contents.extend(u32::MAX.to_le_bytes());
+    contents.extend(b"{'descr': '<f4'");
+    let long_header = scratch("long-header.npy");
+    fs::write(&long_header, contents).unwrap();
+    assert!(matches!(
+        Tensor::<f32, f32>::from_npy(&long_header),
+        Err(Error::Format(_))
+    ));
+
+    // Dimensions whose product overflows, then ones that merely outgrow the data.
+    let overflow = raw(
+        "overflow.npy",
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (4294967295, 4294967295, 4294967295), }",
+        &[0; 4],
+    );
+    let large = raw(
+        "large.npy",
+        "{'descr': '<f4', 'fortran_order': False, 'shape': (65536, 65536), }",
+        &[0; 4],
+    );
+    for path in [&overflow, &large] {
+        assert!(matches!(
+            Tensor::<f32, f32>::from_npy(path),
+            Err(Error::Format(_))
+        ));
+    }
+
+    for path in [long_header, overflow, large] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn archives_round_trip() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]));
+    let b: Tensor<f32, f32> = instance.tensor(vec![3.0], TensorOrder::new(vec![1]));
+
+    let path = scratch("round-trip.npz");
+    npy::save(&path, &instance, &[("a", &a), ("b", &b)]).unwrap();
+
+    let mut archive = Npz::open(&path).unwrap();
+    assert_eq!(archive.names().collect::<Vec<_>>(), &["a", "b"]);
+    let b: Tensor<f32, f32> = archive.tensor("b").unwrap();
+    let a: Tensor<f32, f32> = archive.tensor("a").unwrap();
+    assert_eq!(a.to_vec(&instance).unwrap(), &[1.0, 2.0]);
+    assert_eq!(b.to_vec(&instance).unwrap(), &[3.0]);
+    assert!(archive.tensor::<f32, f32>("c").is_err());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn archives_past_the_member_limit_are_refused() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0], TensorOrder::new(vec![1]));
+    let names: Vec<String> = (0..=u16::MAX as usize).map(|i| format!("a{}", i)).collect();
+    let tensors: Vec<_> = names.iter().map(|name| (name.as_str(), &a)).collect();
+
+    let path = scratch("limit.npz");
+    assert!(matches!(
+        npy::save(&path, &instance, &tensors),
+        Err(Error::Unsupported(_))
+    ));
+    // Nothing is left behind, not even a truncated archive.
+    assert!(!path.exists());
+    assert!(!scratch("limit.npz.partial").exists());
+}
@@ -0,0 +1,46 @@
+use zelkova::{Instance, InstanceOpts, Tensor, TensorOrder};
+
+#[test]
+fn offloaded_tensors_come_back() {
+    let instance = zelkova::init();
+    let values: Vec<f32> = (0..1000).map(|i| i as f32).collect();
+    let a: Tensor<f32, f32> = instance.tensor(values.clone(), TensorOrder::new(vec![1000]));
+    let b = &a + &a;
+    instance.resolve(&b).unwrap();
+
+    let live = instance.pool_stats().live;
+    instance.offload(&a).unwrap();
+    instance.offload(&b).unwrap();
+    assert!(instance.pool_stats().live < live);
+
+    let doubled: Vec<f32> = values.iter().map(|v| v * 2.0).collect();
+    assert_eq!(b.to_vec(&instance).unwrap(), doubled);
+    assert_eq!(a.to_vec(&instance).unwrap(), values);
+}
+
+#[test]
+fn budget_evicts_cold_tensors() {
+    // Room for about three of the tensors below at a time.
+    let opts = InstanceOpts {
+        budget: Some(3 << 16),
+        evict: true,
+        ..Default::default()
+    };
+    let instance = Instance::with_opts(opts).unwrap();
+    let order = || TensorOrder::new(vec![1 << 14]);
+
+    let layers: Vec<Tensor<f32, f32>> = (0..8)
+        .map(|layer| instance.tensor(vec![layer as f32; 1 << 14], order()))
+        .collect();
+    let outputs: Vec<_> = layers.iter().map(|layer| layer + layer).collect();
+    for output in outputs.iter() {
+        instance.resolve(output).unwrap();
+        assert!(instance.pool_stats().live <= 3 << 16);
+    }
+
+    // Evicted uploads were offloaded and evicted intermediates are recomputed from them.
+    for (layer, output) in outputs.iter().enumerate() {
+        let expected = vec![2.0 * layer as f32; 1 << 14];
+        assert_eq!(output.to_vec(&instance).unwrap(), expected);
+    }
+}
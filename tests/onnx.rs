@@ -0,0 +1,263 @@
+use std::fs;
+
+use zelkova::{io::Onnx, nn::Param, Error, Instance, TensorOrder};
+
+/// Just enough protobuf to write small models by hand.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(mut self, field: u32, value: u64) -> Self {
+        self.key(field, 0);
+        self.raw_varint(value);
+        self
+    }
+
+    fn bytes(mut self, field: u32, bytes: &[u8]) -> Self {
+        self.key(field, 2);
+        self.raw_varint(bytes.len() as u64);
+        self.0.extend(bytes);
+        self
+    }
+
+    fn string(self, field: u32, value: &str) -> Self {
+        self.bytes(field, value.as_bytes())
+    }
+
+    fn message(self, field: u32, message: Message) -> Self {
+        self.bytes(field, &message.0)
+    }
+
+    fn key(&mut self, field: u32, wire: u64) {
+        self.raw_varint(((field as u64) << 3) | wire);
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+}
+
+fn floats(name: &str, dims: &[u64], values: &[f32]) -> Message {
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    dims.iter()
+        .fold(Message::default(), |tensor, dim| tensor.varint(1, *dim))
+        .varint(2, 1)
+        .string(8, name)
+        .bytes(9, &raw)
+}
+
+fn int64s(name: &str, dims: &[u64], values: &[i64]) -> Message {
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    dims.iter()
+        .fold(Message::default(), |tensor, dim| tensor.varint(1, *dim))
+        .varint(2, 7)
+        .string(8, name)
+        .bytes(9, &raw)
+}
+
+fn node(op: &str, inputs: &[&str], output: &str) -> Message {
+    inputs
+        .iter()
+        .fold(Message::default(), |node, input| node.string(1, input))
+        .string(2, output)
+        .string(3, output)
+        .string(4, op)
+}
+
+fn named(name: &str) -> Message {
+    Message::default().string(1, name)
+}
+
+/// Opset 17 model over `nodes`, fed `inputs` and producing `Y`.
+fn model(nodes: Vec<Message>, initializers: Vec<Message>, inputs: &[&str]) -> Onnx {
+    Onnx::from_bytes(&encode(nodes, initializers, inputs)).unwrap()
+}
+
+fn encode(nodes: Vec<Message>, initializers: Vec<Message>, inputs: &[&str]) -> Vec<u8> {
+    let mut graph = Message::default();
+    for node in nodes {
+        graph = graph.message(1, node);
+    }
+    for initializer in initializers {
+        graph = graph.message(5, initializer);
+    }
+    for input in inputs {
+        graph = graph.message(11, named(input));
+    }
+    graph = graph.message(12, named("Y"));
+
+    let model = Message::default()
+        .varint(1, 8)
+        .message(7, graph)
+        .message(8, Message::default().varint(2, 17));
+    model.0
+}
+
+/// Float tensor whose data lives in `location`, as `length` bytes from the start.
+fn external(name: &str, dims: &[u64], location: &str, length: u64) -> Message {
+    let entry = |key: &str, value: &str| Message::default().string(1, key).string(2, value);
+    dims.iter()
+        .fold(Message::default(), |tensor, dim| tensor.varint(1, *dim))
+        .varint(2, 1)
+        .string(8, name)
+        .message(13, entry("location", location))
+        .message(13, entry("length", &length.to_string()))
+        .varint(14, 1)
+}
+
+fn input(instance: &Instance, values: &[f32], dims: Vec<u32>) -> Param {
+    instance.tensor(values.to_vec(), TensorOrder::new(dims))
+}
+
+#[test]
+fn linear_layers_run() {
+    let instance = zelkova::init();
+    let onnx = model(
+        vec![
+            node("MatMul", &["X", "W"], "H"),
+            node("Add", &["H", "B"], "Y"),
+        ],
+        vec![
+            floats("W", &[2, 2], &[1.0, 2.0, 3.0, 4.0]),
+            floats("B", &[2], &[0.5, -0.5]),
+        ],
+        &["X", "W", "B"],
+    );
+    assert_eq!(onnx.opset, 17);
+    assert_eq!(onnx.inputs.len(), 1);
+
+    let x = input(&instance, &[1.0, 1.0, 0.0, 2.0], vec![2, 2]);
+    let y = onnx.run(&[("X", &x)]).unwrap().remove(0);
+    assert_eq!(y.to_vec(&instance).unwrap(), &[4.5, 5.5, 6.5, 7.5]);
+}
+
+#[test]
+fn external_weights_are_bounded_by_their_file() {
+    let instance = zelkova::init();
+    let directory = std::env::temp_dir().join("zelkova-onnx-external");
+    fs::create_dir_all(&directory).unwrap();
+    let weights: Vec<u8> = [1.0f32, 2.0].iter().flat_map(|x| x.to_le_bytes()).collect();
+    fs::write(directory.join("weights.bin"), weights).unwrap();
+
+    let path = directory.join("model.onnx");
+    let nodes = || vec![node("Add", &["X", "W"], "Y")];
+    fs::write(
+        &path,
+        encode(nodes(), vec![external("W", &[2], "weights.bin", 8)], &["X"]),
+    )
+    .unwrap();
+    let x = input(&instance, &[0.5, 0.5], vec![2]);
+    let y = Onnx::open(&path)
+        .unwrap()
+        .run(&[("X", &x)])
+        .unwrap()
+        .remove(0);
+    assert_eq!(y.to_vec(&instance).unwrap(), &[1.5, 2.5]);
+
+    // A length far past the file is refused before anything is allocated for it.
+    fs::write(
+        &path,
+        encode(
+            nodes(),
+            vec![external("W", &[2], "weights.bin", u64::MAX / 2)],
+            &["X"],
+        ),
+    )
+    .unwrap();
+    assert!(matches!(Onnx::open(&path), Err(Error::Format(_))));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn gathers_take_negative_indices() {
+    let instance = zelkova::init();
+    let table = floats("T", &[3, 2], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+
+    // Constant indices.
+    let onnx = model(
+        vec![node("Gather", &["T", "I"], "Y")],
+        vec![table, int64s("I", &[2], &[-1, 0])],
+        &[],
+    );
+    let y = onnx.run(&[]).unwrap().remove(0);
+    assert_eq!(y.to_vec(&instance).unwrap(), &[4.0, 5.0, 0.0, 1.0]);
+
+    // Indices only known once computed on the device.
+    let onnx = model(
+        vec![node("Gather", &["T", "I"], "Y")],
+        vec![floats("T", &[3, 2], &[0.0, 1.0, 2.0, 3.0, 4.0, 5.0])],
+        &["I"],
+    );
+    let indices = input(&instance, &[-2.0, 2.0], vec![2]);
+    let y = onnx.run(&[("I", &indices)]).unwrap().remove(0);
+    assert_eq!(y.to_vec(&instance).unwrap(), &[2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn gathers_pick_whole_slices_of_deeper_tables() {
+    let instance = zelkova::init();
+    let values: Vec<f32> = (0..12).map(|x| x as f32).collect();
+    let onnx = model(
+        vec![node("Gather", &["T", "I"], "Y")],
+        vec![floats("T", &[3, 2, 2], &values)],
+        &["I"],
+    );
+
+    let indices = input(&instance, &[2.0, -3.0], vec![2]);
+    let y = onnx.run(&[("I", &indices)]).unwrap().remove(0);
+    assert_eq!(y.order.pull(), &[2, 2, 2]);
+    assert_eq!(
+        y.to_vec(&instance).unwrap(),
+        &[8.0, 9.0, 10.0, 11.0, 0.0, 1.0, 2.0, 3.0]
+    );
+}
+
+#[test]
+fn gathering_out_of_range_is_an_error() {
+    let onnx = model(
+        vec![node("Gather", &["T", "I"], "Y")],
+        vec![floats("T", &[3, 2], &[0.0; 6]), int64s("I", &[1], &[-4])],
+        &[],
+    );
+    assert!(matches!(onnx.run(&[]), Err(Error::Graph(_))));
+}
+
+#[test]
+fn mismatched_products_are_errors() {
+    let instance = zelkova::init();
+    let onnx = model(vec![node("MatMul", &["A", "B"], "Y")], vec![], &["A", "B"]);
+
+    let a = input(&instance, &[1.0; 6], vec![2, 3]);
+    let b = input(&instance, &[1.0; 4], vec![2, 2]);
+    assert!(matches!(
+        onnx.run(&[("A", &a), ("B", &b)]),
+        Err(Error::Graph(_))
+    ));
+
+    // Rank-0 operands have no axis to contract over.
+    let scalar = input(&instance, &[1.0], vec![]);
+    assert!(matches!(
+        onnx.run(&[("A", &scalar), ("B", &b)]),
+        Err(Error::Graph(_))
+    ));
+}
+
+#[test]
+fn unmapped_operators_are_listed() {
+    let onnx = model(
+        vec![
+            node("NonMaxSuppression", &["X"], "H"),
+            node("Relu", &["H"], "Y"),
+        ],
+        vec![],
+        &["X"],
+    );
+
+    assert_eq!(onnx.unmapped(), &["NonMaxSuppression"]);
+    assert!(matches!(onnx.run(&[]), Err(Error::Unmapped(_))));
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use zelkova::{
+    optim::{Adam, AdamOpts, OptimState, Optimizer, Sgd, SgdOpts},
+    Error,
+};
+
+use common::{assert_close, param, tensor};
+
+#[test]
+fn sgd_step() {
+    let instance = zelkova::init();
+    let w = param(&instance, &[1.0, 2.0], vec![2]);
+
+    (&w * &w).backward().unwrap();
+    let mut sgd = Sgd::new(
+        &[&w],
+        SgdOpts {
+            lr: 0.1,
+            ..Default::default()
+        },
+    );
+    sgd.step(&instance).unwrap();
+
+    assert_close(&w.to_vec(&instance).unwrap(), &[0.8, 1.6], 1e-4);
+}
+
+#[test]
+fn gradients_read_before_updates() {
+    let instance = zelkova::init();
+    let x = tensor(&instance, &[1.0], vec![1, 1]);
+    let w1 = param(&instance, &[2.0], vec![1, 1]);
+    let w2 = param(&instance, &[3.0], vec![1, 1]);
+
+    // The gradient of `w2` reads `w1`, which must still hold its value from the forward pass.
+    x.matmul(&w1).matmul(&w2).backward().unwrap();
+    let mut sgd = Sgd::new(
+        &[&w1, &w2],
+        SgdOpts {
+            lr: 0.1,
+            ..Default::default()
+        },
+    );
+    sgd.step(&instance).unwrap();
+
+    assert_close(&w1.to_vec(&instance).unwrap(), &[1.7], 1e-4);
+    assert_close(&w2.to_vec(&instance).unwrap(), &[2.8], 1e-4);
+}
+
+#[test]
+fn adam_first_step() {
+    let instance = zelkova::init();
+    let w = param(&instance, &[1.0, -2.0], vec![2]);
+
+    // Bias correction makes the first step `lr` against the sign of the gradient.
+    (&w * &w).backward().unwrap();
+    let mut adam = Adam::new(
+        &[&w],
+        AdamOpts {
+            lr: 0.1,
+            ..Default::default()
+        },
+    );
+    adam.step(&instance).unwrap();
+
+    assert_close(&w.to_vec(&instance).unwrap(), &[0.9, -1.9], 1e-4);
+}
+
+#[test]
+fn state_round_trip() {
+    let instance = zelkova::init();
+    let w = param(&instance, &[1.0, 2.0], vec![2]);
+
+    (&w * &w).backward().unwrap();
+    let mut adam = Adam::new(&[&w], AdamOpts::default());
+    adam.step(&instance).unwrap();
+
+    let state = adam.state(&instance).unwrap();
+    let restored = OptimState::from_bytes(&state.to_bytes()).unwrap();
+    assert_eq!(restored.step, 1);
+    assert_eq!(restored.buffers, state.buffers);
+
+    adam.load_state(restored).unwrap();
+    assert!(OptimState::from_bytes(b"ZKOS").is_err());
+}
+
+#[test]
+fn malformed_states_are_rejected() {
+    let instance = zelkova::init();
+    let w = param(&instance, &[1.0, 2.0], vec![2]);
+    (&w * &w).backward().unwrap();
+    let mut adam = Adam::new(&[&w], AdamOpts::default());
+    adam.step(&instance).unwrap();
+    let bytes = adam.state(&instance).unwrap().to_bytes();
+
+    for bytes in [
+        &b"ZKOX"[..],
+        &bytes[..bytes.len() - 1],
+        &[bytes.as_slice(), &[0]].concat(),
+    ] {
+        assert!(matches!(
+            OptimState::from_bytes(bytes),
+            Err(Error::Format(_))
+        ));
+    }
+
+    // Sized for another set of parameters.
+    let mut other = OptimState::from_bytes(&bytes).unwrap();
+    other.buffers.pop();
+    assert!(matches!(adam.load_state(other), Err(Error::Format(_))));
+
+    let mut other = OptimState::from_bytes(&bytes).unwrap();
+    other.buffers[0].extend([0; 4]);
+    assert!(matches!(adam.load_state(other), Err(Error::Format(_))));
+}
@@ -0,0 +1,60 @@
+use zelkova::{Tensor, TensorOrder};
+
+#[test]
+fn released_buffers_are_reused() {
+    let instance = zelkova::init();
+    let order = || TensorOrder::new(vec![256]);
+
+    let run = || {
+        let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 256], order());
+        let b: Tensor<f32, f32> = instance.tensor(vec![2.0; 256], order());
+        let c = &a + &b;
+        assert_eq!(c.to_vec(&instance).unwrap(), vec![3.0; 256]);
+    };
+
+    run();
+    let first = instance.pool_stats();
+    assert!(first.misses > 0);
+    assert_eq!(first.live, 0);
+    assert!(first.cached > 0);
+    assert!(first.peak >= first.cached);
+
+    // Same sizes again: everything comes out of the free lists.
+    run();
+    let second = instance.pool_stats();
+    assert_eq!(second.allocated, first.allocated);
+    assert_eq!(second.misses, first.misses);
+    assert!(second.hits > first.hits);
+}
+
+#[test]
+fn live_buffers_are_counted() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 1000], TensorOrder::new(vec![1000]));
+    instance.resolve(&a).unwrap();
+
+    let stats = instance.pool_stats();
+    assert!(stats.live >= 4000);
+    assert!(stats.peak >= stats.live);
+
+    drop(a);
+    let stats = instance.pool_stats();
+    assert_eq!(stats.live, 0);
+
+    instance.trim();
+    assert_eq!(instance.pool_stats().cached, 0);
+}
+
+#[test]
+fn large_buffers_are_not_doubled() {
+    let instance = zelkova::init();
+    // Just past 4 MiB, which a power-of-two bucket would round up to 8 MiB.
+    let len = 1_100_000;
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; len], TensorOrder::new(vec![len as u32]));
+    instance.resolve(&a).unwrap();
+
+    let size = len as u64 * 4;
+    let live = instance.pool_stats().live;
+    assert!(live >= size);
+    assert!(live <= size + size / 8, "{} bytes for {}", live, size);
+}
@@ -0,0 +1,61 @@
+use zelkova::{Clock, Tensor, TensorOrder};
+
+#[test]
+fn fused_chains_are_one_span() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0, 3.0, 4.0], TensorOrder::new(vec![4]));
+    let b = &(&a + &a).exp() * &a;
+
+    let profile = instance.resolve_profiled(&b).unwrap();
+
+    assert_eq!(profile.spans.len(), 1);
+    let span = &profile.spans[0];
+    assert!(span.duration > 0.0);
+    assert!(span.grid.iter().all(|&groups| groups >= 1));
+    if !instance.info().features.timestamps {
+        assert_eq!(span.clock, Clock::Cpu);
+    }
+}
+
+#[test]
+fn spans_follow_dispatch_order() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 16], TensorOrder::new(vec![4, 4]));
+    let c = a.matmul(&a).sum(1).exp();
+
+    let profile = instance.resolve_profiled(&c).unwrap();
+
+    assert!(profile.spans.len() >= 2);
+    assert!(profile
+        .spans
+        .windows(2)
+        .all(|pair| pair[0].start <= pair[1].start));
+
+    // Already resolved, nothing left to run.
+    assert!(instance.resolve_profiled(&c).unwrap().spans.is_empty());
+}
+
+#[test]
+fn summary_and_trace_cover_every_span() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0; 16], TensorOrder::new(vec![4, 4]));
+    let c = a.matmul(&a).matmul(&a);
+
+    let profile = instance.resolve_profiled(&c).unwrap();
+    let summary = profile.summary();
+
+    let calls: usize = summary.iter().map(|(_, count, _)| count).sum();
+    assert_eq!(calls, profile.spans.len());
+    let time: f64 = summary.iter().map(|(_, _, time)| time).sum();
+    assert!((time - profile.total()).abs() <= 1e-6 * profile.total());
+    assert!(summary.windows(2).all(|pair| pair[0].2 >= pair[1].2));
+
+    let trace = profile.to_chrome_trace();
+    assert!(trace.starts_with("{\"traceEvents\":["));
+    assert!(trace.ends_with("],\"displayTimeUnit\":\"ns\"}"));
+    assert_eq!(trace.matches("\"ph\":\"X\"").count(), profile.spans.len());
+
+    let table = profile.to_string();
+    assert!(table.starts_with("kernel"));
+    assert!(table.lines().last().unwrap().starts_with("total"));
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use zelkova::{
+    i4,
+    nn::{Linear, Module, QuantizedLinear},
+    quant::{Granularity, QuantOpts, Quantized},
+    Error, TensorOrder,
+};
+
+use common::tensor;
+
+/// Deterministic weights in [-1, 1), rough enough to exercise rounding.
+fn weights(count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|i| ((i * 7919 % 1000) as f32 / 500.0) - 1.0)
+        .collect()
+}
+
+/// Row-major `input @ weights^T`, on the host.
+fn reference(input: &[f32], weights: &[f32], inputs: usize) -> Vec<f32> {
+    input
+        .chunks(inputs)
+        .flat_map(|row| {
+            weights
+                .chunks(inputs)
+                .map(|column| row.iter().zip(column).map(|(x, w)| x * w).sum())
+                .collect::<Vec<f32>>()
+        })
+        .collect()
+}
+
+fn max_error(actual: &[f32], expected: &[f32]) -> f32 {
+    assert_eq!(actual.len(), expected.len());
+    actual
+        .iter()
+        .zip(expected)
+        .map(|(a, e)| (a - e).abs())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn products_match_the_integers_they_stand_for() {
+    let instance = zelkova::init();
+    // Two outputs over four inputs, in blocks of two with their own zero points.
+    let values = vec![-8, 7, 0, 3, 1, -1, -5, 6];
+    let (scales, zeros) = (vec![0.5, 0.25, 1.0, 0.125], vec![0.0, 1.0, -2.0, 0.0]);
+    let expanded: Vec<f32> = values
+        .iter()
+        .enumerate()
+        .map(|(i, q)| (*q as f32 - zeros[i / 2]) * scales[i / 2])
+        .collect();
+
+    let input = [1.0, 2.0, -1.0, 0.5, 0.0, 1.0, 3.0, -2.0];
+    let expected = reference(&input, &expanded, 4);
+
+    let order = TensorOrder::new(vec![2, 4]);
+    let q8 = Quantized::<i8>::from_parts(
+        values.clone(),
+        order.clone(),
+        scales.clone(),
+        zeros.clone(),
+        Granularity::Block(2),
+    )
+    .unwrap();
+    let q4 =
+        Quantized::<i4>::from_parts(values, order, scales, zeros, Granularity::Block(2)).unwrap();
+
+    assert_eq!(q8.dequantize().to_vec(&instance).unwrap(), expanded);
+    assert_eq!(q4.dequantize().to_vec(&instance).unwrap(), expanded);
+
+    let x = tensor(&instance, &input, vec![2, 4]);
+    let y8 = x.matmul_quantized(&q8).to_vec(&instance).unwrap();
+    let y4 = x.matmul_quantized(&q4).to_vec(&instance).unwrap();
+    assert!(
+        max_error(&y8, &expected) < 1e-5,
+        "{:?} != {:?}",
+        y8,
+        expected
+    );
+    assert!(
+        max_error(&y4, &expected) < 1e-5,
+        "{:?} != {:?}",
+        y4,
+        expected
+    );
+}
+
+#[test]
+fn quantized_products_stay_close() {
+    let instance = zelkova::init();
+    let (outputs, inputs) = (16, 64);
+    let w = weights(outputs * inputs);
+    let input: Vec<f32> = weights(3 * inputs).iter().map(|x| x * 0.5).collect();
+    let expected = reference(&input, &w, inputs);
+    let x = tensor(&instance, &input, vec![3, inputs as u32]);
+    let order = TensorOrder::new(vec![outputs as u32, inputs as u32]);
+
+    let cases = [
+        (QuantOpts::default(), 8, 0.02),
+        (
+            QuantOpts {
+                granularity: Granularity::Block(16),
+                symmetric: false,
+            },
+            8,
+            0.02,
+        ),
+        (
+            QuantOpts {
+                granularity: Granularity::Block(16),
+                symmetric: true,
+            },
+            4,
+            0.3,
+        ),
+    ];
+
+    for (opts, bits, tolerance) in cases {
+        let y = match bits {
+            8 => x.matmul_quantized(&Quantized::<i8>::quantize(&w, order.clone(), opts).unwrap()),
+            _ => x.matmul_quantized(&Quantized::<i4>::quantize(&w, order.clone(), opts).unwrap()),
+        };
+        let error = max_error(&y.to_vec(&instance).unwrap(), &expected);
+        assert!(error < tolerance, "{} bits, {:?}: {}", bits, opts, error);
+    }
+}
+
+#[test]
+fn leading_axes_are_batched() {
+    let instance = zelkova::init();
+    let w = weights(4 * 8);
+    let input = weights(2 * 3 * 8);
+    let quantized =
+        Quantized::<i8>::quantize(&w, TensorOrder::new(vec![4, 8]), QuantOpts::default()).unwrap();
+
+    let batched = tensor(&instance, &input, vec![2, 3, 8]).matmul_quantized(&quantized);
+    let flat = tensor(&instance, &input, vec![6, 8]).matmul_quantized(&quantized);
+
+    assert_eq!(batched.order.pull(), &[2, 3, 4]);
+    assert_eq!(
+        batched.to_vec(&instance).unwrap(),
+        flat.to_vec(&instance).unwrap()
+    );
+}
+
+#[test]
+fn quantized_layers_keep_their_bias() {
+    let instance = zelkova::init();
+    let mut linear = Linear::new(8, 4, true);
+    let input = tensor(&instance, &weights(2 * 8), vec![2, 8]);
+    let expected = linear.forward(&input).unwrap().to_vec(&instance).unwrap();
+
+    let mut quantized =
+        QuantizedLinear::<i8>::from_linear(&linear, &instance, QuantOpts::default()).unwrap();
+    let actual = quantized
+        .forward(&input)
+        .unwrap()
+        .to_vec(&instance)
+        .unwrap();
+
+    assert!(max_error(&actual, &expected) < 0.05);
+    assert_eq!(quantized.named_parameters().len(), 1);
+}
+
+#[test]
+fn blocks_must_divide_the_inputs() {
+    let opts = QuantOpts {
+        granularity: Granularity::Block(3),
+        ..Default::default()
+    };
+    assert!(matches!(
+        Quantized::<i8>::quantize(&[0.0; 8], TensorOrder::new(vec![2, 4]), opts),
+        Err(Error::Unsupported(_))
+    ));
+}
@@ -0,0 +1,90 @@
+use std::{error, io};
+
+use zelkova::{
+    nn::Param,
+    optim::{Optimizer, Sgd, SgdOpts},
+    Error, Instance, InstanceOpts, Tensor, TensorOrder,
+};
+
+fn mirrored() -> Instance {
+    Instance::with_opts(InstanceOpts {
+        mirror: true,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+fn source(reason: &str) -> Box<dyn error::Error + Send> {
+    Box::new(io::Error::other(reason.to_string()))
+}
+
+#[test]
+fn device_errors_map_to_variants() {
+    let validation = wgpu::Error::Validation {
+        source: source("bad binding"),
+        description: "bad binding".to_string(),
+    };
+    assert!(matches!(Error::from(validation), Error::Validation(_)));
+
+    let lost = wgpu::Error::Validation {
+        source: source("lost"),
+        description: "Parent device is lost".to_string(),
+    };
+    assert!(matches!(Error::from(lost), Error::DeviceLost));
+
+    let oom = wgpu::Error::OutOfMemory {
+        source: source("oom"),
+    };
+    assert!(matches!(Error::from(oom), Error::DeviceOutOfMemory));
+}
+
+#[test]
+fn recovered_instances_recompute_and_restore() {
+    let instance = mirrored();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]));
+    let b = &a + &a;
+    instance.resolve(&b).unwrap();
+
+    instance.recover().unwrap();
+    assert!(!instance.lost());
+
+    // Uploads come back from their host copy, intermediates are computed again.
+    assert_eq!(a.to_vec(&instance).unwrap(), &[1.0, 2.0]);
+    assert_eq!(b.to_vec(&instance).unwrap(), &[2.0, 4.0]);
+}
+
+#[test]
+fn uploads_without_mirror_are_gone() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]));
+    instance.resolve(&a).unwrap();
+
+    instance.recover().unwrap();
+    assert!(matches!(a.to_vec(&instance), Err(Error::DeviceLost)));
+}
+
+#[test]
+fn checkpoint_covers_parameters() {
+    let instance = mirrored();
+    let w: Param = instance
+        .tensor(vec![1.0, 2.0], TensorOrder::new(vec![2]))
+        .requires_grad();
+
+    (&w * &w).backward().unwrap();
+    let mut sgd = Sgd::new(
+        &[&w],
+        SgdOpts {
+            lr: 0.1,
+            ..Default::default()
+        },
+    );
+    sgd.step(&instance).unwrap();
+    instance.checkpoint().unwrap();
+
+    // w - 0.1 * 2w, as of the checkpoint rather than as uploaded.
+    instance.recover().unwrap();
+    let restored = w.to_vec(&instance).unwrap();
+    for (r, e) in restored.iter().zip([0.8, 1.6]) {
+        assert!((r - e).abs() < 1e-5, "{:?}", restored);
+    }
+}
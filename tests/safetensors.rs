@@ -0,0 +1,177 @@
+use std::{fs, path::PathBuf};
+
+use zelkova::{
+    io::{safetensors, Dtype, Safetensors},
+    Error, Tensor, TensorOrder,
+};
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zelkova-{}.safetensors", name))
+}
+
+/// File with the given JSON header and `data` bytes after it.
+fn raw(name: &str, header: &str, data: &[u8]) -> PathBuf {
+    let path = scratch(name);
+    let mut contents = (header.len() as u64).to_le_bytes().to_vec();
+    contents.extend_from_slice(header.as_bytes());
+    contents.extend_from_slice(data);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn tensors_round_trip() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0, 2.0, 3.0], TensorOrder::new(vec![3]));
+    let b: Tensor<f32, f32> = instance.tensor(vec![4.0; 4], TensorOrder::new(vec![2, 2]));
+
+    let path = scratch("round-trip");
+    safetensors::save(&path, &instance, &[("a", &a), ("b", &(&b + &b))]).unwrap();
+
+    let file = Safetensors::open(&path).unwrap();
+    assert_eq!(file.names().collect::<Vec<_>>(), &["a", "b"]);
+    let entry = file.entry("b").unwrap();
+    assert_eq!(entry.dtype, Dtype::F32);
+    assert_eq!(entry.shape, &[2, 2]);
+    assert_eq!(entry.offsets, (12, 28));
+
+    let a: Tensor<f32, f32> = file.tensor("a").unwrap();
+    let b: Tensor<f32, f32> = file.tensor("b").unwrap();
+    // Tensors stay readable from the mapping once the file is closed.
+    drop(file);
+    assert_eq!(a.to_vec(&instance).unwrap(), &[1.0, 2.0, 3.0]);
+    assert_eq!(b.to_vec(&instance).unwrap(), &[8.0; 4]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn single_tensors_save_and_load() {
+    let instance = zelkova::init();
+    let a: Tensor<i32, i32> = instance.tensor(vec![-1, 7], TensorOrder::new(vec![2]));
+
+    let path = scratch("single");
+    a.save(&instance, &path).unwrap();
+    let loaded: Tensor<i32, i32> = Tensor::load(&path).unwrap();
+    assert_eq!(loaded.to_vec(&instance).unwrap(), &[-1, 7]);
+
+    // Stored as I32, so it can't be read back as floats.
+    assert!(matches!(
+        Tensor::<f32, f32>::load(&path),
+        Err(Error::Format(_))
+    ));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn failed_saves_keep_the_previous_file() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![1.0], TensorOrder::new(vec![1]));
+    let path = scratch("failed-save");
+    safetensors::save(&path, &instance, &[("a", &a)]).unwrap();
+    let before = fs::read(&path).unwrap();
+
+    // Bound to another instance, so reading it fails halfway through the save.
+    let other: Tensor<f32, f32> = zelkova::init().tensor(vec![2.0], TensorOrder::new(vec![1]));
+    assert!(safetensors::save(&path, &instance, &[("a", &a), ("b", &other)]).is_err());
+
+    assert_eq!(fs::read(&path).unwrap(), before);
+    assert!(!scratch("failed-save.partial").exists());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    let past_end = raw(
+        "past-end",
+        r#"{"a":{"dtype":"F32","shape":[4],"data_offsets":[0,16]}}"#,
+        &[0; 8],
+    );
+    assert!(matches!(
+        Safetensors::open(&past_end),
+        Err(Error::Format(_))
+    ));
+
+    let mismatched = raw(
+        "mismatched",
+        r#"{"a":{"dtype":"F32","shape":[3],"data_offsets":[0,16]}}"#,
+        &[0; 16],
+    );
+    assert!(matches!(
+        Safetensors::open(&mismatched),
+        Err(Error::Format(_))
+    ));
+
+    // Deep nesting is refused before it can exhaust the stack.
+    let deep = format!(
+        "{{\"__metadata__\":{}{}}}",
+        "[".repeat(10000),
+        "]".repeat(10000)
+    );
+    let nested = raw("nested", &deep, &[]);
+    assert!(matches!(Safetensors::open(&nested), Err(Error::Format(_))));
+
+    // Shapes whose byte count overflows, and offsets past what an f64 holds exactly.
+    let overflow = raw(
+        "overflow",
+        r#"{"a":{"dtype":"F64","shape":[4294967295,4294967295,4294967295],"data_offsets":[0,8]}}"#,
+        &[0; 8],
+    );
+    assert!(matches!(
+        Safetensors::open(&overflow),
+        Err(Error::Format(_))
+    ));
+
+    let inexact = raw(
+        "inexact",
+        r#"{"a":{"dtype":"F32","shape":[1],"data_offsets":[1e300,1e300]}}"#,
+        &[0; 4],
+    );
+    assert!(matches!(Safetensors::open(&inexact), Err(Error::Format(_))));
+
+    for path in [past_end, mismatched, nested, overflow, inexact] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn metadata_is_kept() {
+    let path = raw(
+        "metadata",
+        r#"{"__metadata__":{"format":"pt"},"a":{"dtype":"F32","shape":[],"data_offsets":[0,4]}}"#,
+        &1.5f32.to_le_bytes(),
+    );
+
+    let file = Safetensors::open(&path).unwrap();
+    assert_eq!(file.metadata, &[("format".to_string(), "pt".to_string())]);
+    assert_eq!(file.entry("a").unwrap().shape, Vec::<u32>::new());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn narrow_and_wide_dtypes() {
+    let instance = zelkova::init();
+    let values: Vec<u8> = [1u16, 2, 65535]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    let path = raw(
+        "narrow",
+        r#"{"a":{"dtype":"U16","shape":[3],"data_offsets":[0,6]},"b":{"dtype":"F64","shape":[1],"data_offsets":[6,14]}}"#,
+        &[values, 2.5f64.to_le_bytes().to_vec()].concat(),
+    );
+    let file = Safetensors::open(&path).unwrap();
+
+    // 16-bit elements travel packed two to a word.
+    let a: Tensor<u16, u16> = file.tensor("a").unwrap();
+    assert_eq!(a.to_vec(&instance).unwrap(), &[1, 2, 65535]);
+
+    // Shaders have no 64-bit types to hold these in.
+    assert!(matches!(
+        file.tensor::<f64, f64>("b"),
+        Err(Error::Format(_))
+    ));
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,162 @@
+use zelkova::{
+    nn::{self, Param},
+    Instance, TensorOrder,
+};
+
+fn tensor(instance: &Instance, values: &[f32], dims: Vec<u32>) -> Param {
+    instance.tensor(values.to_vec(), TensorOrder::new(dims))
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+/// Softmax of every `len` consecutive values.
+fn softmax(values: &[f32], len: usize) -> Vec<f32> {
+    values
+        .chunks(len)
+        .flat_map(|row| {
+            let max = row.iter().cloned().fold(f32::MIN, f32::max);
+            let sum: f32 = row.iter().map(|v| (v - max).exp()).sum();
+            row.iter().map(move |v| (v - max).exp() / sum)
+        })
+        .collect()
+}
+
+#[test]
+fn softmax_rows() {
+    let instance = zelkova::init();
+    let values = [1.0, 2.0, 3.0, -1.0, 0.0, 1.0];
+    let x = tensor(&instance, &values, vec![2, 3]);
+
+    assert_close(
+        &x.softmax(1).to_vec(&instance).unwrap(),
+        &softmax(&values, 3),
+    );
+
+    let logs: Vec<f32> = softmax(&values, 3).iter().map(|p| p.ln()).collect();
+    assert_close(&x.log_softmax(1).to_vec(&instance).unwrap(), &logs);
+}
+
+#[test]
+fn softmax_leading_axis() {
+    let instance = zelkova::init();
+    let x = tensor(&instance, &[1.0, 2.0, 3.0, -1.0, 0.0, 1.0], vec![2, 3]);
+
+    // Columns normalized: each pair differs by 2, 2 and 2.
+    let p = 1.0 / (1.0 + (-2.0f32).exp());
+    assert_close(
+        &x.softmax(0).to_vec(&instance).unwrap(),
+        &[p, p, p, 1.0 - p, 1.0 - p, 1.0 - p],
+    );
+}
+
+#[test]
+fn softmax_large_logits() {
+    let instance = zelkova::init();
+    let x = tensor(&instance, &[1000.0, 1001.0, 1002.0], vec![1, 3]);
+
+    assert_close(
+        &x.softmax(1).to_vec(&instance).unwrap(),
+        &softmax(&[0.0, 1.0, 2.0], 3),
+    );
+}
+
+#[test]
+fn softmax_backward() {
+    let instance = zelkova::init();
+    let values = [1.0, 2.0, 3.0];
+    let weights = [1.0, 0.0, -1.0];
+    let x = tensor(&instance, &values, vec![1, 3]).requires_grad();
+    let w = tensor(&instance, &weights, vec![1, 3]);
+
+    (&x.softmax(1) * &w).backward().unwrap();
+
+    // `y (w - sum(w y))`
+    let y = softmax(&values, 3);
+    let dot: f32 = y.iter().zip(&weights).map(|(y, w)| y * w).sum();
+    let expected: Vec<f32> = y.iter().zip(&weights).map(|(y, w)| y * (w - dot)).collect();
+    assert_close(&x.grad().unwrap().to_vec(&instance).unwrap(), &expected);
+}
+
+/// `softmax(q kᵀ / sqrt(d) + mask) v` for a single batch.
+fn attention(
+    q: &[f32],
+    k: &[f32],
+    v: &[f32],
+    dims: (usize, usize, usize, usize),
+    causal: bool,
+) -> Vec<f32> {
+    let (queries, keys, features, values) = dims;
+    let shift = keys as i64 - queries as i64;
+    let mut scores = vec![0.0; queries * keys];
+    for i in 0..queries {
+        for j in 0..keys {
+            let dot: f32 = (0..features)
+                .map(|f| q[i * features + f] * k[j * features + f])
+                .sum();
+            let masked = causal && j as i64 > i as i64 + shift;
+            scores[i * keys + j] = if masked {
+                f32::MIN
+            } else {
+                dot / (features as f32).sqrt()
+            };
+        }
+    }
+
+    let probs = softmax(&scores, keys);
+    (0..queries * values)
+        .map(|at| {
+            let (i, c) = (at / values, at % values);
+            (0..keys)
+                .map(|j| probs[i * keys + j] * v[j * values + c])
+                .sum()
+        })
+        .collect()
+}
+
+#[test]
+fn attention_matches_reference() {
+    let instance = zelkova::init();
+    let q: Vec<f32> = (0..6).map(|i| (i as f32 * 0.7).sin()).collect();
+    let k: Vec<f32> = (0..8).map(|i| (i as f32 * 0.3).cos()).collect();
+    let v: Vec<f32> = (0..12).map(|i| i as f32 * 0.25 - 1.0).collect();
+
+    for causal in [false, true] {
+        let output = nn::attention(
+            &tensor(&instance, &q, vec![1, 3, 2]),
+            &tensor(&instance, &k, vec![1, 4, 2]),
+            &tensor(&instance, &v, vec![1, 4, 3]),
+            causal,
+        );
+
+        assert_close(
+            &output.to_vec(&instance).unwrap(),
+            &attention(&q, &k, &v, (3, 4, 2, 3), causal),
+        );
+    }
+}
+
+#[test]
+fn attention_backward() {
+    let instance = zelkova::init();
+    let q = tensor(&instance, &[1.0, 0.0, 0.0, 1.0], vec![1, 2, 2]).requires_grad();
+    let k = tensor(&instance, &[1.0, 0.0, 0.0, 1.0], vec![1, 2, 2]).requires_grad();
+    let v = tensor(&instance, &[1.0, 2.0, 3.0, 4.0], vec![1, 2, 2]).requires_grad();
+
+    nn::attention(&q, &k, &v, true).backward().unwrap();
+
+    // The first query only sees the first key; the second weighs both with `softmax([0, s])`.
+    let p = 1.0 / (1.0 + (-1.0f32 / 2.0f32.sqrt()).exp());
+    assert_close(
+        &v.grad().unwrap().to_vec(&instance).unwrap(),
+        &[2.0 - p, 2.0 - p, p, p],
+    );
+    // A single visible key leaves the first query's weights constant.
+    let dq = q.grad().unwrap().to_vec(&instance).unwrap();
+    assert_close(&dq[..2], &[0.0, 0.0]);
+    assert_eq!(k.grad().unwrap().to_vec(&instance).unwrap().len(), 4);
+}
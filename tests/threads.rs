@@ -0,0 +1,80 @@
+use std::thread;
+
+use zelkova::{Instance, Tensor, TensorOrder};
+
+fn shareable<T: Clone + Send + Sync>() {}
+
+#[test]
+fn instance_is_shareable() {
+    shareable::<Instance>();
+}
+
+#[test]
+fn resolve_from_many_threads() {
+    let instance = zelkova::init();
+
+    let handles: Vec<_> = (0..8)
+        .map(|worker| {
+            let instance = instance.clone();
+            thread::spawn(move || {
+                for step in 0..10 {
+                    let value = (worker * 100 + step) as f32;
+                    let a: Tensor<f32, f32> =
+                        instance.tensor(vec![value; 64], TensorOrder::new(vec![8, 8]));
+                    let b = &(&a * &a) - &a;
+                    assert_eq!(
+                        b.to_vec(&instance).unwrap(),
+                        vec![value * value - value; 64]
+                    );
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn tensors_shared_across_threads() {
+    let instance = zelkova::init();
+    let a: Tensor<f32, f32> = instance.tensor(vec![2.0; 16], TensorOrder::new(vec![16]));
+    let b = &a + &a;
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| assert_eq!(b.to_vec(&instance).unwrap(), vec![4.0; 16]));
+        }
+    });
+}
+
+#[test]
+fn instances_start_while_others_work() {
+    let busy = thread::spawn(|| {
+        let instance = zelkova::init();
+        for _ in 0..50 {
+            let a: Tensor<f32, f32> =
+                instance.tensor(vec![1.0; 4096], TensorOrder::new(vec![64, 64]));
+            assert_eq!(a.matmul(&a).to_vec(&instance).unwrap(), vec![64.0; 4096]);
+        }
+    });
+
+    let starting: Vec<_> = (0..2)
+        .map(|_| {
+            thread::spawn(|| {
+                for _ in 0..10 {
+                    let instance = zelkova::init();
+                    let a: Tensor<f32, f32> =
+                        instance.tensor(vec![1.0; 16], TensorOrder::new(vec![16]));
+                    assert_eq!((&a + &a).to_vec(&instance).unwrap(), vec![2.0; 16]);
+                }
+            })
+        })
+        .collect();
+
+    for handle in starting {
+        handle.join().unwrap();
+    }
+    busy.join().unwrap();
+}
@@ -0,0 +1,35 @@
+use zelkova::{Tensor, TensorOrder};
+
+#[test]
+fn small_upload() {
+    let instance = zelkova::init();
+    let values = vec![1.5f32, -2.0, 3.25];
+    let tensor: Tensor<f32, f32> = Tensor::from_vec(values.clone(), TensorOrder::new(vec![3]));
+
+    assert_eq!(tensor.to_vec(&instance).unwrap(), values);
+}
+
+#[test]
+fn chunked_upload() {
+    let instance = zelkova::init();
+    // Spans a few staging chunks, the last one partially.
+    let len = 10_000_003u32;
+    let values: Vec<f32> = (0..len).map(|i| i as f32).collect();
+    let tensor: Tensor<f32, f32> = instance.tensor(values, TensorOrder::new(vec![len]));
+
+    let doubled = (&tensor + &tensor).to_vec(&instance).unwrap();
+    assert_eq!(doubled.len(), len as usize);
+    for (i, value) in doubled.iter().enumerate().step_by(9973) {
+        assert_eq!(*value, 2.0 * i as f32);
+    }
+    assert_eq!(*doubled.last().unwrap(), 2.0 * (len - 1) as f32);
+}
+
+#[test]
+fn borrowed_upload() {
+    let instance = zelkova::init();
+    let values = [7i32, 8, 9, 10];
+    let tensor: Tensor<i32, i32> = Tensor::from_slice(&values, TensorOrder::new(vec![2, 2]));
+
+    assert_eq!(tensor.to_vec(&instance).unwrap(), values.to_vec());
+}
@@ -0,0 +1,41 @@
+use zelkova::{Instance, InstanceOpts, Tensor, TensorOrder};
+
+/// Grid of the single kernel adding a tensor of `len` ones to itself, checking the result.
+fn grid(instance: &Instance, len: u32) -> [u32; 3] {
+    let ones: Tensor<f32, f32> =
+        instance.tensor(vec![1.0; len as usize], TensorOrder::new(vec![len]));
+    let sum = &ones + &ones;
+
+    let profile = instance.resolve_profiled(&sum).unwrap();
+    assert_eq!(sum.to_vec(instance).unwrap(), vec![2.0; len as usize]);
+
+    let span = profile.spans.last().unwrap();
+    span.grid
+}
+
+#[test]
+fn sized_from_shape() {
+    let instance = zelkova::init();
+
+    // Narrow workgroups for small tensors, wider ones as they grow.
+    assert_eq!(grid(&instance, 100), [2, 1, 1]);
+    assert_eq!(grid(&instance, 100_000), [782, 1, 1]);
+    assert_eq!(grid(&instance, 2_000_000), [7813, 1, 1]);
+}
+
+#[test]
+fn tuned_sizes_are_cached() {
+    let opts = InstanceOpts {
+        tune: true,
+        ..Default::default()
+    };
+    let instance = Instance::with_opts(opts).unwrap();
+
+    // Without timestamp queries tuning falls back to the heuristic; either way the results hold
+    // and the same kind of kernel keeps the size it got the first time.
+    let first = grid(&instance, 50_000);
+    assert!([32, 64, 128, 256]
+        .iter()
+        .any(|lanes| 50_000u32.div_ceil(*lanes) == first[0]));
+    assert_eq!(grid(&instance, 50_000), first);
+}